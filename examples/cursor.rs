@@ -0,0 +1,65 @@
+use pixelated::{
+    canvas::{self, Sprite},
+    input::{self, KeyCode},
+    window::{self, CursorIcon},
+    Callbacks, Config, Context,
+};
+
+const ICONS: [CursorIcon; 4] = [
+    CursorIcon::Default,
+    CursorIcon::Crosshair,
+    CursorIcon::Hand,
+    CursorIcon::Move,
+];
+
+struct Game {
+    icon: usize,
+    cursor: Sprite,
+}
+
+/// Small white arrow with a black outline, hotspot in the top left
+fn arrow() -> Sprite {
+    let mut sprite = Sprite::new(7, 7, vec![0; 7 * 7 * 4]);
+    for y in 0..7 {
+        for x in 0..=y {
+            let edge = x == 0 || x == y || y == 6;
+            let color = if edge {
+                [0, 0, 0, 255]
+            } else {
+                [255, 255, 255, 255]
+            };
+            sprite.write_pixel(x, y, &color);
+        }
+    }
+    sprite
+}
+
+impl Callbacks for Game {
+    fn init(&self, ctx: &mut Context) {
+        canvas::resize(ctx, 96, 64);
+    }
+
+    fn update(&mut self, ctx: &mut Context, _dt: f32) -> bool {
+        canvas::clear_screen(ctx);
+        canvas::draw_text(ctx, "C: next icon", 2, 2, &[255, 255, 255, 255]);
+        canvas::draw_text(ctx, "S: sprite", 2, 12, &[255, 255, 255, 255]);
+
+        if input::key_just_pressed(ctx, KeyCode::C) {
+            self.icon = (self.icon + 1) % ICONS.len();
+            window::set_cursor_icon(ctx, ICONS[self.icon]);
+        }
+        if input::key_just_pressed(ctx, KeyCode::S) {
+            window::set_cursor_sprite(ctx, &self.cursor, 0, 0).expect("invalid cursor sprite");
+        }
+
+        false
+    }
+}
+
+fn main() {
+    let app = Game {
+        icon: 0,
+        cursor: arrow(),
+    };
+    pixelated::run_with_config(app, Config::default());
+}
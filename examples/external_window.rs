@@ -0,0 +1,33 @@
+use pixelated::{canvas, Callbacks, Config, Context};
+use winit::{event_loop::EventLoop, window::WindowBuilder};
+
+const WIDTH: u32 = 128;
+const HEIGHT: u32 = 128;
+
+struct Game {}
+
+impl Callbacks for Game {
+    fn init(&self, ctx: &mut Context) {
+        canvas::resize(ctx, WIDTH, HEIGHT);
+    }
+
+    fn update(&mut self, ctx: &mut Context, _dt: f32) -> bool {
+        canvas::clear_screen(ctx);
+        canvas::draw_text(ctx, "Own window", 4, 4, &[255, 255, 255, 255]);
+
+        false
+    }
+}
+
+fn main() {
+    // Window created by the host application
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new()
+        .with_title("External window")
+        .with_inner_size(winit::dpi::LogicalSize::new(512, 512))
+        .build(&event_loop)
+        .expect("could not build window");
+
+    let app = Game {};
+    pixelated::run_with_window(app, Config::default(), window, event_loop);
+}
@@ -1,4 +1,4 @@
-use pixelated::{canvas, input, media, window, Callbacks, Context};
+use pixelated::{canvas, input, media, window, Callbacks, Config, Context};
 
 const WIDTH: u32 = 256;
 const HEIGHT: u32 = 256;
@@ -7,7 +7,6 @@ struct Game {}
 
 impl Callbacks for Game {
     fn init(&self, ctx: &mut Context) {
-        canvas::resize(ctx, WIDTH, HEIGHT);
         window::set_resizeable(ctx, true);
     }
 
@@ -35,5 +34,10 @@ impl Callbacks for Game {
 fn main() {
     let app = Game {};
     println!("S: to screenshot");
-    pixelated::run(app);
+    let config = Config {
+        canvas_width: WIDTH,
+        canvas_height: HEIGHT,
+        ..Default::default()
+    };
+    pixelated::run_with_config(app, config);
 }
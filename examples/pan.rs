@@ -0,0 +1,45 @@
+use pixelated::{
+    canvas, render,
+    render::{AddressMode, UpscaleFilter},
+    Callbacks, Context,
+};
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 64;
+const PAN_SPEED: f32 = 0.1;
+
+struct Game {
+    pan: f32,
+}
+
+impl Callbacks for Game {
+    fn init(&self, ctx: &mut Context) {
+        canvas::resize(ctx, WIDTH, HEIGHT);
+        render::set_upscale_filter(ctx, UpscaleFilter::Nearest);
+        render::set_address_mode(ctx, AddressMode::Repeat);
+
+        // Checkerboard drawn once, scrolled by the GPU
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let color = if (x / 8 + y / 8) % 2 == 0 {
+                    [40, 40, 60]
+                } else {
+                    [200, 180, 80]
+                };
+                canvas::write_pixel_rgb(ctx, x, y, &color);
+            }
+        }
+    }
+
+    fn update(&mut self, ctx: &mut Context, dt: f32) -> bool {
+        self.pan += PAN_SPEED * dt;
+        render::set_pan(ctx, self.pan, self.pan * 0.5);
+
+        false
+    }
+}
+
+fn main() {
+    let app = Game { pan: 0.0 };
+    pixelated::run(app);
+}
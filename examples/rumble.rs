@@ -0,0 +1,35 @@
+use pixelated::{canvas, input, Callbacks, Context};
+use winit::event::VirtualKeyCode as KeyCode;
+
+const WIDTH: u32 = 128;
+const HEIGHT: u32 = 64;
+
+struct Game {}
+
+impl Callbacks for Game {
+    fn init(&self, ctx: &mut Context) {
+        canvas::resize(ctx, WIDTH, HEIGHT);
+    }
+
+    fn update(&mut self, ctx: &mut Context, _dt: f32) -> bool {
+        canvas::clear_screen(ctx);
+        let connected = input::gamepads(ctx);
+        let text = format!("Gamepads: {}\nSpace: rumble", connected.len());
+        canvas::draw_text(ctx, &text, 4, 4, &[255, 255, 255, 255]);
+
+        if input::key_just_pressed(ctx, KeyCode::Space) {
+            for id in connected {
+                if let Err(err) = input::gamepad_rumble(ctx, id, 1.0, 0.5, 500) {
+                    println!("{}", err);
+                }
+            }
+        }
+
+        false
+    }
+}
+
+fn main() {
+    let app = Game {};
+    pixelated::run(app);
+}
@@ -0,0 +1,102 @@
+use pixelated::{canvas, render, Callbacks, Context};
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 64;
+
+// Fullscreen triangle with a flat color
+const SHADER: &str = "
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(0.1, 0.6, 0.9, 1.0);
+}
+";
+
+struct Game {
+    pipeline: Option<wgpu::RenderPipeline>,
+}
+
+impl Game {
+    fn create_pipeline(ctx: &Context) -> wgpu::RenderPipeline {
+        let device = render::device(ctx);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Corner Shader"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Corner Pipeline"),
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(render::surface_format(ctx).into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+}
+
+impl Callbacks for Game {
+    fn init(&self, ctx: &mut Context) {
+        canvas::resize(ctx, WIDTH, HEIGHT);
+    }
+
+    fn update(&mut self, ctx: &mut Context, _dt: f32) -> bool {
+        canvas::clear_screen(ctx);
+        canvas::draw_text(ctx, "Canvas", 4, 4, &[255, 255, 255, 255]);
+
+        false
+    }
+
+    fn render(
+        &mut self,
+        ctx: &mut Context,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        let pipeline = self
+            .pipeline
+            .get_or_insert_with(|| Self::create_pipeline(ctx));
+
+        // Paint over the bottom right quarter, keeping the canvas elsewhere
+        let (width, height) = render::surface_size(ctx);
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Corner Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        pass.set_pipeline(pipeline);
+        pass.set_scissor_rect(
+            width / 2,
+            height / 2,
+            width - width / 2,
+            height - height / 2,
+        );
+        pass.draw(0..3, 0..1);
+    }
+}
+
+fn main() {
+    let app = Game { pipeline: None };
+    pixelated::run(app);
+}
@@ -0,0 +1,43 @@
+use pixelated::{canvas, Callbacks, Context};
+
+const WIDTH: u32 = 256;
+const HEIGHT: u32 = 128;
+
+struct Game {
+    font: Option<canvas::FontId>,
+}
+
+impl Callbacks for Game {
+    fn init(&self, ctx: &mut Context) {
+        canvas::resize(ctx, WIDTH, HEIGHT);
+    }
+
+    fn update(&mut self, ctx: &mut Context, _dt: f32) -> bool {
+        let font = *self.font.get_or_insert_with(|| {
+            canvas::load_font(
+                ctx,
+                include_bytes!("../tests/fixtures/FiraSans-Regular.ttf"),
+            )
+            .unwrap()
+        });
+
+        canvas::clear_screen(ctx);
+        canvas::draw_text_ttf(
+            ctx,
+            font,
+            "Hello pixels",
+            10,
+            10,
+            32.0,
+            &[255, 255, 255, 255],
+        );
+        canvas::draw_text_ttf(ctx, font, "small text", 10, 60, 12.0, &[0, 255, 255, 255]);
+
+        false
+    }
+}
+
+fn main() {
+    let app = Game { font: None };
+    pixelated::run(app);
+}
@@ -0,0 +1,47 @@
+use pixelated::{canvas, input, input::KeyCode, window, Callbacks, Config, Context};
+
+struct Game {
+    shown: bool,
+    maximized: bool,
+}
+
+impl Callbacks for Game {
+    fn init(&self, ctx: &mut Context) {
+        canvas::resize(ctx, 96, 64);
+    }
+
+    fn update(&mut self, ctx: &mut Context, _dt: f32) -> bool {
+        canvas::clear_screen(ctx);
+        canvas::draw_text(ctx, "M: toggle max", 2, 2, &[255, 255, 255, 255]);
+        canvas::draw_text(ctx, "N: minimize", 2, 12, &[255, 255, 255, 255]);
+
+        // Setup is done, show the window
+        if !self.shown {
+            window::set_visible(ctx, true);
+            self.shown = true;
+        }
+
+        if input::key_just_pressed(ctx, KeyCode::M) {
+            self.maximized = !self.maximized;
+            window::set_maximized(ctx, self.maximized);
+        }
+        if input::key_just_pressed(ctx, KeyCode::N) {
+            window::set_minimized(ctx, true);
+        }
+
+        false
+    }
+}
+
+fn main() {
+    let app = Game {
+        shown: false,
+        maximized: true,
+    };
+    let config = Config {
+        maximized: true,
+        visible: false,
+        ..Default::default()
+    };
+    pixelated::run_with_config(app, config);
+}
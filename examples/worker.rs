@@ -0,0 +1,72 @@
+use pixelated::{
+    canvas::{self, PixelBuffer},
+    Callbacks, Config, Context,
+};
+use std::{
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+const WIDTH: u32 = 256;
+const HEIGHT: u32 = 256;
+
+struct Game {
+    /// Number of the frame generated next
+    frame: u32,
+    sender: Sender<PixelBuffer>,
+    receiver: Receiver<PixelBuffer>,
+    generating: bool,
+}
+
+/// Expensive procedural pattern, computed off the main thread
+fn generate(frame: u32) -> PixelBuffer {
+    let mut buffer = PixelBuffer::new(WIDTH, HEIGHT);
+    let t = frame as f32 * 0.05;
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let (fx, fy) = (x as f32 / 32.0, y as f32 / 32.0);
+            let v = (fx + t).sin() + (fy - t).cos() + ((fx + fy) * 0.5 + t).sin();
+            let c = ((v + 3.0) / 6.0 * 255.0) as u8;
+            buffer.write_pixel(x, y, &[c, 255 - c, 128, 255]);
+        }
+    }
+    buffer
+}
+
+impl Callbacks for Game {
+    fn update(&mut self, ctx: &mut Context, _dt: f32) -> bool {
+        if !self.generating {
+            let sender = self.sender.clone();
+            let frame = self.frame;
+            thread::spawn(move || {
+                let _ = sender.send(generate(frame));
+            });
+            self.frame += 1;
+            self.generating = true;
+        }
+
+        // The previous buffer stays on screen until the next one is ready
+        if let Ok(buffer) = self.receiver.try_recv() {
+            canvas::apply_buffer(ctx, &buffer, 0, 0);
+            self.generating = false;
+        }
+
+        false
+    }
+}
+
+fn main() {
+    let (sender, receiver) = mpsc::channel();
+    let app = Game {
+        frame: 0,
+        sender,
+        receiver,
+        generating: false,
+    };
+    let config = Config {
+        canvas_width: WIDTH,
+        canvas_height: HEIGHT,
+        ..Default::default()
+    };
+    pixelated::run_with_config(app, config);
+}
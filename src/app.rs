@@ -1,7 +1,17 @@
 use crate::{
-    context::Context, input::InputContext, render::RenderContext, time::TimeContext, window,
+    canvas,
+    config::Config,
+    context::Context,
+    input::InputContext,
+    render::{RenderContext, RenderError},
+    time::TimeContext,
+    util::Rng,
+    widgets::WidgetContext,
+    window,
+    window::WindowContext,
 };
-use winit::event_loop::EventLoop;
+use std::path::PathBuf;
+use winit::{event::WindowEvent, event_loop::EventLoop, window::Window};
 
 /// User callbacks
 ///
@@ -16,6 +26,124 @@ pub trait Callbacks {
     fn update(&mut self, _ctx: &mut Context, _dt: f32) -> bool {
         false
     }
+
+    /// Called for every window event before it is handled internally
+    /// Return true to consume the event and skip internal handling
+    ///
+    /// Resized and ScaleFactorChanged always resize the surface even if consumed
+    fn on_event(&mut self, _ctx: &mut Context, _event: &WindowEvent) -> bool {
+        false
+    }
+
+    /// Called once per frame after the canvas has been drawn to the frame view
+    ///
+    /// Record custom passes into encoder, use ```LoadOp::Load``` to keep the canvas
+    ///
+    /// The view has the surface format and is copied to the surface after this returns
+    ///
+    /// The encoder is shared with the canvas pass and submitted after this returns,
+    /// do not submit it or present the view yourself
+    fn render(
+        &mut self,
+        _ctx: &mut Context,
+        _encoder: &mut wgpu::CommandEncoder,
+        _view: &wgpu::TextureView,
+    ) {
+    }
+
+    /// Called when a file is dropped onto the window
+    fn on_file_dropped(&mut self, _ctx: &mut Context, _path: PathBuf) {}
+
+    /// Called after the canvas has been resized
+    ///
+    /// Deferred until the callback that resized the canvas, such as init, update or on_file_dropped, returns
+    fn on_canvas_resize(&mut self, _ctx: &mut Context, _width: u32, _height: u32) {}
+
+    /// Called when the window gains or loses focus
+    fn on_focus_changed(&mut self, _ctx: &mut Context, _focused: bool) {}
+
+    /// Called when the work of a frame exceeded ```time::set_frame_budget```
+    ///
+    /// actual: Time spent on update and render in seconds
+    fn on_frame_overrun(&mut self, _ctx: &mut Context, _actual: f32) {}
+
+    /// Called once for every error reported by the GPU since the last frame
+    ///
+    /// The default logs the error, rendering continues with the previous pipeline if possible
+    fn on_render_error(&mut self, _ctx: &mut Context, error: RenderError) {
+        log::error!("{}", error);
+    }
+
+    /// Called when the app is suspended, before the surface is dropped
+    ///
+    /// Release resources tied to the surface, rendering is paused until resumed
+    fn on_suspend(&mut self, _ctx: &mut Context) {}
+
+    /// Called when the app is resumed after a suspend, once the surface has been recreated
+    ///
+    /// Not called on startup
+    fn on_resume(&mut self, _ctx: &mut Context) {}
+
+    /// Called once when the event loop exits
+    fn on_exit(&mut self, _ctx: &mut Context) {}
+}
+
+/// Forwards to the boxed callbacks
+///
+/// Allows running a ```Box<dyn Callbacks>``` chosen at runtime
+impl<C: Callbacks + ?Sized> Callbacks for Box<C> {
+    fn init(&self, ctx: &mut Context) {
+        (**self).init(ctx)
+    }
+
+    fn update(&mut self, ctx: &mut Context, dt: f32) -> bool {
+        (**self).update(ctx, dt)
+    }
+
+    fn on_event(&mut self, ctx: &mut Context, event: &WindowEvent) -> bool {
+        (**self).on_event(ctx, event)
+    }
+
+    fn render(
+        &mut self,
+        ctx: &mut Context,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        (**self).render(ctx, encoder, view)
+    }
+
+    fn on_file_dropped(&mut self, ctx: &mut Context, path: PathBuf) {
+        (**self).on_file_dropped(ctx, path)
+    }
+
+    fn on_canvas_resize(&mut self, ctx: &mut Context, width: u32, height: u32) {
+        (**self).on_canvas_resize(ctx, width, height)
+    }
+
+    fn on_focus_changed(&mut self, ctx: &mut Context, focused: bool) {
+        (**self).on_focus_changed(ctx, focused)
+    }
+
+    fn on_frame_overrun(&mut self, ctx: &mut Context, actual: f32) {
+        (**self).on_frame_overrun(ctx, actual)
+    }
+
+    fn on_render_error(&mut self, ctx: &mut Context, error: RenderError) {
+        (**self).on_render_error(ctx, error)
+    }
+
+    fn on_suspend(&mut self, ctx: &mut Context) {
+        (**self).on_suspend(ctx)
+    }
+
+    fn on_resume(&mut self, ctx: &mut Context) {
+        (**self).on_resume(ctx)
+    }
+
+    fn on_exit(&mut self, ctx: &mut Context) {
+        (**self).on_exit(ctx)
+    }
 }
 
 /// Main App
@@ -32,51 +160,261 @@ where
     /// Main loop which is called from window event loop
     /// Returns true if app should exit
     pub(crate) fn update(&mut self, ctx: &mut Context) -> bool {
+        #[cfg(feature = "gamepad")]
+        ctx.input.gamepad.poll();
+
+        for error in ctx.render.take_errors() {
+            self.callbacks.on_render_error(ctx, error);
+        }
+
         let dt = ctx.time.update_time();
+        let dt = match ctx.time.consume_update(dt) {
+            Some(dt) => dt,
+            None => return false,
+        };
 
         // Update callback
         if self.callbacks.update(ctx, dt) {
             return true;
         }
+        self.notify_canvas_resize(ctx);
+
+        if ctx.render.canvas.debug_overlay {
+            canvas::draw_debug_overlay(ctx);
+        }
 
         // Reset input
-        ctx.input.keyboard.save_keys();
-        ctx.input.keyboard.save_modifiers();
-        ctx.input.mouse.save_buttons();
-        ctx.input.mouse.set_mouse_delta((0.0, 0.0));
+        ctx.input.end_frame();
+        ctx.input.files.clear_dropped();
+        ctx.input.touch.clear_ended();
 
         false
     }
+
+    /// Calls on_canvas_resize if the canvas has been resized
+    pub(crate) fn notify_canvas_resize(&mut self, ctx: &mut Context) {
+        if let Some((width, height)) = ctx.render.canvas.take_resized() {
+            self.callbacks.on_canvas_resize(ctx, width, height);
+        }
+    }
 }
 
 /// Runs the event loop
 ///
 /// Calls user defined functions thorugh Callback trait
+// Signature kept as released, the bound is repeated in the where clause
+#[allow(clippy::multiple_bound_locations)]
 pub fn run<C: Callbacks + 'static>(callbacks: C)
 where
     C: Callbacks + 'static,
 {
-    env_logger::init();
-    let app = App { callbacks };
+    run_with_config(callbacks, Config::default());
+}
+
+/// Runs the event loop with custom settings
+///
+/// Calls user defined functions thorugh Callback trait
+pub fn run_with_config<C>(callbacks: C, config: Config)
+where
+    C: Callbacks + 'static,
+{
+    let (window, event_loop) = window::new_window(&config);
+    run_with_window(callbacks, config, window, event_loop);
+}
+
+/// Runs the event loop with callbacks only known at runtime
+///
+/// Useful for plugin style architectures
+pub fn run_boxed(callbacks: Box<dyn Callbacks>, config: Config) {
+    run_with_config(callbacks, config);
+}
+
+/// Runs the event loop on an existing window
+///
+/// Useful when embedding in an application that creates its own window
+///
+/// The window must have been created from the event loop
+pub fn run_with_window<C>(callbacks: C, config: Config, window: Window, event_loop: EventLoop<()>)
+where
+    C: Callbacks + 'static,
+{
+    init_logger(&config);
+    let mut app = App { callbacks };
 
-    let (mut ctx, event_loop) = pollster::block_on(build_context());
+    let mut ctx = pollster::block_on(build_context(window, &config));
 
     app.callbacks.init(&mut ctx);
+    app.notify_canvas_resize(&mut ctx);
 
     pollster::block_on(window::run_window(event_loop, app, ctx));
 }
 
-async fn build_context() -> (Context, EventLoop<()>) {
-    let (window, event_loop) = window::new_window();
+/// Installs env_logger if enabled and no logger is set
+///
+/// Returns true if the logger was installed
+fn init_logger(config: &Config) -> bool {
+    if !config.init_logger {
+        return false;
+    }
+    let logger = build_logger(env_logger::Env::default());
+    let max_level = logger.filter();
+    if log::set_boxed_logger(Box::new(logger)).is_err() {
+        return false;
+    }
+    log::set_max_level(max_level);
+    true
+}
+
+/// Builds env_logger with the filters and style read from env, RUST_LOG by default
+fn build_logger(env: env_logger::Env) -> env_logger::Logger {
+    env_logger::Builder::from_env(env).build()
+}
+
+async fn build_context(window: Window, config: &Config) -> Context {
+    let render = RenderContext::new(window, config).await;
+    context_with_render(render, config)
+}
+
+/// Context drawing to a width x height texture instead of a window
+/// None on machines without any adapter
+#[cfg(test)]
+pub(crate) fn headless_context(config: &Config, width: u32, height: u32) -> Option<Context> {
+    let render = RenderContext::headless(config, width, height)?;
+    Some(context_with_render(render, config))
+}
+
+/// Headless context with a default config and the given canvas and surface sizes
+/// None on machines without any adapter
+#[cfg(test)]
+pub(crate) fn headless_canvas(canvas: (u32, u32), surface: (u32, u32)) -> Option<Context> {
+    let config = Config {
+        canvas_width: canvas.0,
+        canvas_height: canvas.1,
+        ..Default::default()
+    };
+    headless_context(&config, surface.0, surface.1)
+}
 
+fn context_with_render(render: RenderContext, config: &Config) -> Context {
     let time = TimeContext::default();
     let input = InputContext::default();
-    let render = RenderContext::new(window).await;
-    let context = Context {
+    let seed = config.seed.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default()
+    });
+    let rng = Rng::seed(seed);
+    let window = WindowContext::default();
+    Context {
         render,
         time,
         input,
-    };
+        rng,
+        window,
+        widgets: WidgetContext::default(),
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::{build_logger, headless_canvas, init_logger, App, Callbacks};
+    use crate::{canvas, Config};
+
+    struct Empty;
+
+    impl Callbacks for Empty {}
+
+    fn assert_runnable<C: Callbacks + 'static>(_callbacks: &C) {}
+
+    #[test]
+    fn test_boxed_callbacks_are_runnable() {
+        let callbacks: Box<dyn Callbacks> = Box::new(Empty);
+
+        assert_runnable(&callbacks);
+    }
+
+    #[test]
+    fn test_debug_overlay_draws_in_corner() {
+        // Skip on machines without any adapter
+        let Some(mut ctx) = headless_canvas((64, 64), (64, 64)) else {
+            return;
+        };
+        let mut app = App { callbacks: Empty };
+        let corner_drawn = |ctx: &crate::Context| {
+            (0..16)
+                .flat_map(|y| (0..16).map(move |x| (x, y)))
+                .any(|(x, y)| ctx.render.canvas.pixel_rgb(x, y) != [0, 0, 0])
+        };
+
+        app.update(&mut ctx);
+        assert!(!corner_drawn(&ctx));
+
+        canvas::set_debug_overlay(&mut ctx, true);
+        app.update(&mut ctx);
+        assert!(corner_drawn(&ctx));
+        assert_eq!(ctx.render.canvas.pixel_rgb(63, 63), [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_init_logger_disabled() {
+        let config = Config {
+            init_logger: false,
+            ..Default::default()
+        };
+
+        assert!(!init_logger(&config));
+    }
+
+    #[test]
+    fn test_build_logger() {
+        // Variables no test sets, so only the defaults apply
+        let env = || env_logger::Env::new().filter("PIXELATED_TEST_LOG");
+        assert_eq!(build_logger(env()).filter(), log::LevelFilter::Error);
+
+        let logger = build_logger(env().default_filter_or("warn,pixelated=debug"));
+        assert_eq!(logger.filter(), log::LevelFilter::Debug);
+        let record = |target| {
+            log::Metadata::builder()
+                .level(log::Level::Info)
+                .target(target)
+                .build()
+        };
+        assert!(log::Log::enabled(&logger, &record("pixelated::render")));
+        assert!(!log::Log::enabled(&logger, &record("wgpu_core")));
+    }
 
-    (context, event_loop)
+    // Other platforms only create event loops on the main thread
+    #[cfg(target_os = "linux")]
+    #[test]
+    #[ignore = "needs a display, run with --ignored"]
+    fn test_build_context_from_external_window() {
+        use super::build_context;
+        use crate::render::{self, test_device};
+        use winit::{
+            dpi::PhysicalSize, event_loop::EventLoopBuilder,
+            platform::unix::EventLoopBuilderExtUnix, window::WindowBuilder,
+        };
+
+        // Skip on machines without any adapter
+        if test_device().is_none() {
+            return;
+        }
+
+        // Window created by the host application
+        let event_loop = EventLoopBuilder::new().with_any_thread(true).build();
+        let window = WindowBuilder::new()
+            .with_visible(false)
+            .with_inner_size(PhysicalSize::new(64, 48))
+            .build(&event_loop)
+            .unwrap();
+        let id = window.id();
+        let size = window.inner_size();
+
+        let ctx = pollster::block_on(build_context(window, &Config::default()));
+
+        assert!(ctx.render.is_window(id));
+        assert_eq!(render::surface_size(&ctx), (size.width, size.height));
+    }
 }
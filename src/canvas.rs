@@ -1,8 +1,139 @@
-use crate::Context;
+use crate::{font, input, time, Context};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
 
 pub(crate) const DEFAULT_CLEAR_COLOR: [u8; 4] = [0, 0, 0, 255]; // Black
-pub(crate) const DEFAULT_CANVAS_WIDTH: u32 = 512;
-pub(crate) const DEFAULT_CANVAS_HEIGHT: u32 = 512;
+pub(crate) const DEFAULT_RESIZE_FILL: [u8; 4] = [0, 0, 0, 0]; // Transparent
+/// Canvas width used when ```Config::canvas_width``` is not set
+pub const DEFAULT_CANVAS_WIDTH: u32 = 512;
+/// Canvas height used when ```Config::canvas_height``` is not set
+pub const DEFAULT_CANVAS_HEIGHT: u32 = 512;
+const DEBUG_OVERLAY_MARGIN: i32 = 2;
+
+/// Color format of the canvas texture
+///
+/// Decides how the written bytes are interpreted when presented
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CanvasFormat {
+    /// Bytes are treated as sRGB encoded
+    ///
+    /// Decoded to linear when sampled and encoded again by an sRGB surface
+    Srgb,
+    /// Bytes are passed through as is
+    ///
+    /// Prefers a non sRGB surface so the written values reach the screen unchanged
+    Linear,
+}
+
+impl CanvasFormat {
+    /// Texture format used for the canvas texture
+    pub(crate) fn texture_format(&self) -> wgpu::TextureFormat {
+        match self {
+            CanvasFormat::Srgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+            CanvasFormat::Linear => wgpu::TextureFormat::Rgba8Unorm,
+        }
+    }
+
+    /// Returns true if the surface should use an sRGB format
+    pub(crate) fn srgb(&self) -> bool {
+        match self {
+            CanvasFormat::Srgb => true,
+            CanvasFormat::Linear => false,
+        }
+    }
+}
+
+/// What happens to the pixels when the canvas is resized
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResizeMode {
+    /// Clear everything to the clear color
+    Clear,
+    /// Keep the overlapping pixels anchored in the top left corner
+    ///
+    /// Newly exposed pixels are set to the resize fill color
+    Preserve,
+}
+
+/// How blended colors store their color channels
+///
+/// The canvas itself always stores non premultiplied colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AlphaMode {
+    /// Color channels are independent of alpha, result = (src * src_a + dst * dst_a * (1 - src_a)) / out_a
+    #[default]
+    Straight,
+    /// Color channels are already multiplied by alpha, result = (src + dst * dst_a * (1 - src_a)) / out_a
+    ///
+    /// Avoids dark edges when drawing images that were exported premultiplied
+    Premultiplied,
+}
+
+/// Error returned by ```try_resize```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResizeError {
+    /// A side exceeds the largest texture the GPU supports
+    TooLarge { width: u32, height: u32, max: u32 },
+    /// The size is fixed by ```Config::logical_resolution```
+    Locked,
+}
+
+impl std::fmt::Display for ResizeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResizeError::TooLarge { width, height, max } => write!(
+                f,
+                "canvas {}x{} exceeds the max texture dimension {}",
+                width, height, max
+            ),
+            ResizeError::Locked => write!(f, "canvas size is locked by the logical resolution"),
+        }
+    }
+}
+
+impl std::error::Error for ResizeError {}
+
+/// Copies made of everything drawn, mirrored or rotated about the canvas center
+///
+/// Rects and lines blend once where their copies overlap them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SymmetryMode {
+    /// Only the drawn pixel
+    #[default]
+    None,
+    /// Mirrored left to right
+    Horizontal,
+    /// Mirrored top to bottom
+    Vertical,
+    /// Mirrored left to right and top to bottom, four copies
+    Quad,
+    /// n copies rotated in 2π/n steps
+    ///
+    /// Rotated copies outside the canvas are skipped
+    RadialN(u32),
+}
+
+/// Color channel of a pixel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    R,
+    G,
+    B,
+    A,
+}
+
+impl Channel {
+    /// Offset of the channel within a pixel
+    fn offset(&self) -> usize {
+        match self {
+            Channel::R => 0,
+            Channel::G => 1,
+            Channel::B => 2,
+            Channel::A => 3,
+        }
+    }
+}
 
 /// Represent the screen of pixels
 pub(crate) struct Canvas {
@@ -10,11 +141,37 @@ pub(crate) struct Canvas {
     pub(crate) width: u32,
     pub(crate) height: u32,
     clear_color: [u8; 4],
+    resize_fill: [u8; 4],
+    alpha_mode: AlphaMode,
+    resized: Option<(u32, u32)>,
+    pub(crate) debug_overlay: bool,
+    /// Pixels changed since the last upload
+    dirty: Option<DirtyRegion>,
+    /// Pixels changed since the user last cleared them, independent of uploads
+    user_dirty: Option<DirtyRegion>,
+    /// Accumulated offsets, last is the current transform
+    transforms: Vec<(i32, i32)>,
+    /// Depth of every pixel, allocated on first depth tested write
+    depth: Option<Vec<f32>>,
+    /// Samples per canvas pixel along each axis, pixels and size are in samples
+    supersample: u32,
+    /// Copies made by every pixel write
+    symmetry: SymmetryMode,
+    #[cfg(feature = "text")]
+    fonts: Vec<fontdue::Font>,
 }
 
 impl Canvas {
     /// Create new canvas with specified width and height
     pub(crate) fn new(width: u32, height: u32) -> Self {
+        Self::with_supersample(width, height, 1)
+    }
+
+    /// Create new canvas storing supersample x supersample samples for every pixel
+    /// Drawing takes pixel coordinates and is rasterized per sample
+    pub(crate) fn with_supersample(width: u32, height: u32, supersample: u32) -> Self {
+        let supersample = supersample.max(1);
+        let (width, height) = (width * supersample, height * supersample);
         let capacity = width * height * 4;
         let pixels = vec![0; capacity as usize];
         let clear_color = DEFAULT_CLEAR_COLOR;
@@ -23,50 +180,225 @@ impl Canvas {
             width,
             height,
             clear_color,
+            resize_fill: DEFAULT_RESIZE_FILL,
+            alpha_mode: AlphaMode::Straight,
+            resized: None,
+            debug_overlay: false,
+            dirty: Some(DirtyRegion::full(width, height)),
+            user_dirty: Some(DirtyRegion::full(width, height)),
+            transforms: Vec::new(),
+            depth: None,
+            supersample,
+            symmetry: SymmetryMode::None,
+            #[cfg(feature = "text")]
+            fonts: Vec::new(),
         }
     }
 
     /// Resizes the canvas
     /// Clears screen to ```clear_color```
     pub(crate) fn resize(&mut self, width: u32, height: u32) {
+        self.resized = Some((width, height));
+        let (width, height) = (width * self.supersample, height * self.supersample);
         let capacity = width * height * 4;
 
         self.pixels.resize(capacity as usize, 0);
         self.width = width;
         self.height = height;
+        self.depth = None;
 
         self.clear_screen();
     }
 
+    /// Resizes the canvas according to mode
+    pub(crate) fn resize_with(&mut self, width: u32, height: u32, mode: ResizeMode) {
+        if mode == ResizeMode::Clear {
+            self.resize(width, height);
+            return;
+        }
+
+        self.resized = Some((width, height));
+        let (width, height) = (width * self.supersample, height * self.supersample);
+        let mut pixels = self.resize_fill.repeat((width * height) as usize);
+        let copy_width = width.min(self.width) as usize * 4;
+        for y in 0..height.min(self.height) {
+            let src = (y * self.width * 4) as usize;
+            let dst = (y * width * 4) as usize;
+            pixels[dst..dst + copy_width].copy_from_slice(&self.pixels[src..src + copy_width]);
+        }
+
+        self.pixels = pixels;
+        self.width = width;
+        self.height = height;
+        self.depth = None;
+        self.mark_all_dirty();
+    }
+
+    /// Set color of pixels exposed by a preserving resize (r,g,b,a)
+    pub(crate) fn set_resize_fill(&mut self, color: &[u8; 4]) {
+        self.resize_fill = *color;
+    }
+
+    /// Set how blended colors are interpreted
+    pub(crate) fn set_alpha_mode(&mut self, alpha_mode: AlphaMode) {
+        self.alpha_mode = alpha_mode;
+    }
+
+    /// Samples per pixel along each axis
+    pub(crate) fn supersample(&self) -> u32 {
+        self.supersample
+    }
+
+    /// Size in pixels, the sample buffer is supersample times larger
+    pub(crate) fn logical_size(&self) -> (u32, u32) {
+        (
+            self.width / self.supersample,
+            self.height / self.supersample,
+        )
+    }
+
+    /// Pixels with the samples of every pixel averaged
+    /// Borrows the buffer when not supersampled
+    pub(crate) fn resolved(&self) -> Cow<'_, [u8]> {
+        if self.supersample == 1 {
+            return Cow::Borrowed(&self.pixels);
+        }
+        Cow::Owned(downsample(
+            &self.pixels,
+            self.width,
+            self.height,
+            self.supersample,
+        ))
+    }
+
+    /// Color of pixel (x, y) averaged over its samples
+    /// Panics if trying to access outside canvas
+    pub(crate) fn resolved_rgba(&self, x: u32, y: u32) -> [u8; 4] {
+        let (width, height) = self.logical_size();
+        assert_pixel(x, y, width, height);
+        let mut sum = [0u32; 4];
+        for (sx, sy) in sample_block(x, y, self.supersample) {
+            for (total, channel) in sum.iter_mut().zip(self.pixel_rgba(sx, sy)) {
+                *total += channel as u32;
+            }
+        }
+        let count = self.supersample * self.supersample;
+        sum.map(|total| ((total + count / 2) / count) as u8)
+    }
+
+    /// Overwrite every pixel, each pixel is copied to all of its samples
+    /// Panics if pixels does not match the pixel size
+    pub(crate) fn set_resolved(&mut self, pixels: &[u8]) {
+        if self.supersample == 1 {
+            self.pixels.copy_from_slice(pixels);
+        } else {
+            let s = self.supersample;
+            let (width, _) = self.logical_size();
+            assert_eq!(pixels.len() as u32, self.width * self.height * 4 / (s * s));
+            for (i, pixel) in self.pixels.chunks_exact_mut(4).enumerate() {
+                let (sx, sy) = (i as u32 % self.width, i as u32 / self.width);
+                let from = ((sy / s * width + sx / s) * 4) as usize;
+                pixel.copy_from_slice(&pixels[from..from + 4]);
+            }
+        }
+        self.mark_all_dirty();
+    }
+
+    /// Marks a single pixel as changed
+    fn mark_dirty(&mut self, x: u32, y: u32) {
+        self.mark_dirty_rect(x, y, x + 1, y + 1);
+    }
+
+    /// Marks the pixels in [min_x, max_x) x [min_y, max_y) as changed
+    pub(crate) fn mark_dirty_rect(&mut self, min_x: u32, min_y: u32, max_x: u32, max_y: u32) {
+        let region = DirtyRegion {
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+        };
+        self.dirty = Some(match self.dirty {
+            Some(dirty) => dirty.union(&region),
+            None => region,
+        });
+        self.user_dirty = Some(match self.user_dirty {
+            Some(dirty) => dirty.union(&region),
+            None => region,
+        });
+    }
+
+    /// Marks the whole canvas as changed
+    pub(crate) fn mark_all_dirty(&mut self) {
+        self.dirty = Some(DirtyRegion::full(self.width, self.height));
+        self.user_dirty = self.dirty;
+    }
+
+    /// Returns the pixels changed since last call
+    pub(crate) fn take_dirty(&mut self) -> Option<DirtyRegion> {
+        self.dirty.take()
+    }
+
+    /// Pixels (x, y, w, h) changed since ```clear_user_dirty```, rounded out to whole pixels
+    pub(crate) fn user_dirty(&self) -> Option<(u32, u32, u32, u32)> {
+        let dirty = self.user_dirty?;
+        let s = self.supersample;
+        let (min_x, min_y) = (dirty.min_x / s, dirty.min_y / s);
+        let (max_x, max_y) = (dirty.max_x.div_ceil(s), dirty.max_y.div_ceil(s));
+        Some((min_x, min_y, max_x - min_x, max_y - min_y))
+    }
+
+    /// Forget the changed pixels reported by ```user_dirty```
+    /// They are still uploaded
+    pub(crate) fn clear_user_dirty(&mut self) {
+        self.user_dirty = None;
+    }
+
+    /// Marks pixels (x, y, w, h) as changed, clipped to the canvas
+    pub(crate) fn mark_pixels_dirty(&mut self, x: u32, y: u32, w: u32, h: u32) {
+        let s = self.supersample;
+        let min_x = x.saturating_mul(s).min(self.width);
+        let min_y = y.saturating_mul(s).min(self.height);
+        let max_x = x.saturating_add(w).saturating_mul(s).min(self.width);
+        let max_y = y.saturating_add(h).saturating_mul(s).min(self.height);
+        if min_x < max_x && min_y < max_y {
+            self.mark_dirty_rect(min_x, min_y, max_x, max_y);
+        }
+    }
+
+    /// Returns the new size if the canvas has been resized since last call
+    pub(crate) fn take_resized(&mut self) -> Option<(u32, u32)> {
+        self.resized.take()
+    }
+
     /// Clone pixel buffer
     pub(crate) fn get_pixel_buffer(&self) -> Vec<u8> {
         self.pixels.clone()
     }
 
-    /// Get pixel data for a coordianate
+    /// Iterate over (x, y, pixel) in row major order
+    pub(crate) fn pixels_iter_mut(&mut self) -> impl Iterator<Item = (u32, u32, &mut [u8; 4])> {
+        self.mark_all_dirty();
+        let width = self.width;
+        self.pixels
+            .chunks_exact_mut(4)
+            .enumerate()
+            .map(move |(i, pixel)| {
+                let pixel: &mut [u8; 4] = pixel.try_into().unwrap();
+                (i as u32 % width, i as u32 / width, pixel)
+            })
+    }
+
+    /// Get pixel data for a coordianate, averaged over its samples
     /// Panics if trying to access outside canvas
     pub(crate) fn pixel_rgb(&self, x: u32, y: u32) -> [u8; 3] {
-        assert_pixel(x, y, self.width, self.height);
-
-        let index = (y * 4 * self.width + x * 4) as usize;
-        [
-            self.pixels[index],
-            self.pixels[index + 1],
-            self.pixels[index + 2],
-        ]
+        let [r, g, b, _] = self.resolved_rgba(x, y);
+        [r, g, b]
     }
 
-    /// Get pixel data for a coordianate
+    /// Get pixel data for a coordianate, averaged over its samples
     /// Panics if trying to access outside canvas
     pub(crate) fn pixel_rgb_f32(&self, x: u32, y: u32) -> [f32; 3] {
-        assert_pixel(x, y, self.width, self.height);
-
-        let index = (y * 4 * self.width + x * 4) as usize;
-        [
-            self.pixels[index] as f32 / 255.0,
-            self.pixels[index + 1] as f32 / 255.0,
-            self.pixels[index + 2] as f32 / 255.0,
-        ]
+        self.pixel_rgb(x, y).map(|c| c as f32 / 255.0)
     }
 
     /// Get pixel data for a coordianate
@@ -83,30 +415,25 @@ impl Canvas {
         ]
     }
 
-    /// Get pixel data for a coordianate
+    /// Get pixel data for a coordianate, averaged over its samples
     /// Panics if trying to access outside canvas
     pub(crate) fn pixel_rgba_f32(&self, x: u32, y: u32) -> [f32; 4] {
-        assert_pixel(x, y, self.width, self.height);
-
-        let index = (y * 4 * self.width + x * 4) as usize;
-        [
-            self.pixels[index] as f32 / 255.0,
-            self.pixels[index + 1] as f32 / 255.0,
-            self.pixels[index + 2] as f32 / 255.0,
-            self.pixels[index + 3] as f32 / 255.0,
-        ]
+        self.resolved_rgba(x, y).map(|c| c as f32 / 255.0)
     }
 
     /// Write pixel data to a coordinate (r,g,b,a)
     /// Overwrites previous pixel
     pub(crate) fn write_pixel(&mut self, x: u32, y: u32, color: &[u8; 3]) {
         assert_pixel(x, y, self.width, self.height);
+        for (x, y) in self.symmetric(x, y) {
+            self.mark_dirty(x, y);
 
-        let index = (y * 4 * self.width + x * 4) as usize;
-        self.pixels[index] = color[0];
-        self.pixels[index + 1] = color[1];
-        self.pixels[index + 2] = color[2];
-        self.pixels[index + 3] = 255;
+            let index = (y * 4 * self.width + x * 4) as usize;
+            self.pixels[index] = color[0];
+            self.pixels[index + 1] = color[1];
+            self.pixels[index + 2] = color[2];
+            self.pixels[index + 3] = 255;
+        }
     }
 
     /// Write pixel data to a coordinate (r,g,b,a)
@@ -129,7 +456,45 @@ impl Canvas {
     /// Non premultiplied alpha blending
     pub(crate) fn write_pixel_blend(&mut self, x: u32, y: u32, color: &[u8; 4]) {
         assert_pixel(x, y, self.width, self.height);
+        for (x, y) in self.symmetric(x, y) {
+            self.mark_dirty(x, y);
+            self.blend_pixel(x, y, color);
+        }
+    }
+
+    /// Sets the copies made by every pixel write
+    pub(crate) fn set_symmetry(&mut self, symmetry: SymmetryMode) {
+        self.symmetry = symmetry;
+    }
+
+    /// Sample (x, y) followed by its copies under the current symmetry
+    fn symmetric(&self, x: u32, y: u32) -> impl Iterator<Item = (u32, u32)> {
+        symmetric_points(x, y, self.width, self.height, self.symmetry)
+    }
+
+    /// Blends color over every sample and its copies under the current symmetry
+    /// Samples covered by several copies of the same shape are blended once
+    fn blend_once(&mut self, samples: impl Iterator<Item = (u32, u32)>, color: &[u8; 4]) {
+        if self.symmetry == SymmetryMode::None {
+            for (x, y) in samples {
+                self.write_pixel_blend(x, y, color);
+            }
+            return;
+        }
+        let mut covered = vec![false; (self.width * self.height) as usize];
+        for (x, y) in samples {
+            for (x, y) in self.symmetric(x, y) {
+                let index = (y * self.width + x) as usize;
+                if !std::mem::replace(&mut covered[index], true) {
+                    self.mark_dirty(x, y);
+                    self.blend_pixel(x, y, color);
+                }
+            }
+        }
+    }
 
+    /// Blends color over the pixel at (x, y) without marking it as changed
+    fn blend_pixel(&mut self, x: u32, y: u32, color: &[u8; 4]) {
         // Alpha blending where a is over b
         // https://en.wikipedia.org/wiki/Alpha_compositing
         let prev_color = self.pixel_rgba(x, y);
@@ -149,10 +514,15 @@ impl Canvas {
         let alpha_a = a[3];
         let alpha_b = b[3];
         let alpha_over = alpha_a + alpha_b * (1.0 - alpha_a);
+        // Premultiplied colors already contain their alpha
+        let weight_a = match self.alpha_mode {
+            AlphaMode::Straight => alpha_a,
+            AlphaMode::Premultiplied => 1.0,
+        };
 
-        let result_r = (a[0] * alpha_a + b[0] * alpha_b * (1.0 - alpha_a)) / alpha_over;
-        let result_g = (a[1] * alpha_a + b[1] * alpha_b * (1.0 - alpha_a)) / alpha_over;
-        let result_b = (a[2] * alpha_a + b[2] * alpha_b * (1.0 - alpha_a)) / alpha_over;
+        let result_r = (a[0] * weight_a + b[0] * alpha_b * (1.0 - alpha_a)) / alpha_over;
+        let result_g = (a[1] * weight_a + b[1] * alpha_b * (1.0 - alpha_a)) / alpha_over;
+        let result_b = (a[2] * weight_a + b[2] * alpha_b * (1.0 - alpha_a)) / alpha_over;
         let result_a = alpha_over;
 
         let index = (y * 4 * self.width + x * 4) as usize;
@@ -179,6 +549,15 @@ impl Canvas {
         self.write_pixel_blend(x, y, &color);
     }
 
+    /// Map normalized coordinates to the nearest pixel
+    /// Coordinates outside [0,1] are clamped to the canvas
+    pub(crate) fn to_pixel(&self, u: f32, v: f32) -> (u32, u32) {
+        let (width, height) = self.logical_size();
+        let x = (u.clamp(0.0, 1.0) * width.saturating_sub(1) as f32).round() as u32;
+        let y = (v.clamp(0.0, 1.0) * height.saturating_sub(1) as f32).round() as u32;
+        (x, y)
+    }
+
     /// Set canvas clear color (r,g,b,a)
     pub(crate) fn set_clear_color(&mut self, color: &[u8; 3]) {
         self.clear_color[0] = color[0];
@@ -203,6 +582,7 @@ impl Canvas {
 
     /// Clears all pixels in canvas to clear color
     pub(crate) fn clear_screen(&mut self) {
+        self.mark_all_dirty();
         for pixel in self.pixels.chunks_mut(4) {
             pixel[0] = self.clear_color[0];
             pixel[1] = self.clear_color[1];
@@ -210,220 +590,3559 @@ impl Canvas {
             pixel[3] = self.clear_color[3];
         }
     }
-}
-
-/// Asserts a pixel is inside the screen
-fn assert_pixel(x: u32, y: u32, width: u32, height: u32) {
-    debug_assert!(
-        x < width && y < height,
-        "pixel ({}, {}) not in range [{}, {})",
-        x,
-        y,
-        width,
-        height
-    );
-}
 
-/// Asserts color components are in range [0,1]
-fn assert_rgb(color: &[f32; 3]) {
-    debug_assert!(
-        (0.0..=1.0).contains(&color[0]),
-        "r ({}) not in range [0,1]",
-        color[0]
-    );
-    debug_assert!(
-        (0.0..=1.0).contains(&color[1]),
-        "g ({}) not in range [0,1]",
-        color[1]
-    );
-    debug_assert!(
-        (0.0..=1.0).contains(&color[2]),
-        "b ({}) not in range [0,1]",
-        color[2]
-    );
-}
+    /// Moves all pixels right and down by (dx, dy) canvas pixels
+    /// Exposed pixels are set to the clear color and the furthest depth
+    pub(crate) fn scroll(&mut self, dx: i32, dy: i32) {
+        if dx == 0 && dy == 0 {
+            return;
+        }
+        let s = self.supersample as i32;
+        let offset = (dx * s, dy * s);
+        let size = (self.width, self.height);
+        self.pixels = scroll_buffer(&self.pixels, size, offset, &self.clear_color);
+        if let Some(depth) = &mut self.depth {
+            *depth = scroll_buffer(depth, size, offset, &[f32::INFINITY]);
+        }
+        self.mark_all_dirty();
+    }
 
-/// Asserts color components are in range [0,1]
-fn assert_rgba(color: &[f32; 4]) {
-    assert_rgb(&[color[0], color[1], color[2]]);
-    debug_assert!(
-        (0.0..=1.0).contains(&color[3]),
-        "a ({}) not in range [0,1]",
-        color[3]
-    );
-}
+    /// Overwrites all pixels with sprite repeated from the top left corner
+    /// Empty sprites leave the canvas unchanged
+    pub(crate) fn clear_with_pattern(&mut self, sprite: &Sprite) {
+        if sprite.width == 0 || sprite.height == 0 {
+            return;
+        }
+        self.mark_all_dirty();
+        let s = self.supersample;
+        let row_bytes = (self.width * 4) as usize;
+        for (y, row) in self.pixels.chunks_mut(row_bytes).enumerate() {
+            let sy = y as u32 / s % sprite.height;
+            for (x, pixel) in row.chunks_mut(4).enumerate() {
+                let sx = x as u32 / s % sprite.width;
+                let from = ((sy * sprite.width + sx) * 4) as usize;
+                pixel.copy_from_slice(&sprite.pixels[from..from + 4]);
+            }
+        }
+    }
 
-// Commands
+    /// Replaces every pixel within tolerance of from with to
+    pub(crate) fn replace_color(&mut self, from: &[u8; 4], to: &[u8; 4], tolerance: u8) {
+        if replace_pixels(&mut self.pixels, from, to, tolerance) {
+            self.mark_all_dirty();
+        }
+    }
 
-/// Mutable reference to pixel array
-///
-/// Stored as list of u8, chunks of 4 represent RGBA
-pub fn pixels_ref(ctx: &mut Context) -> &mut Vec<u8> {
-    &mut ctx.render.canvas.pixels
-}
+    /// Copy pixels and size
+    pub(crate) fn save_state(&self) -> CanvasState {
+        let (width, height) = self.logical_size();
+        CanvasState {
+            pixels: self.pixels.clone(),
+            width,
+            height,
+            supersample: self.supersample,
+        }
+    }
 
-/// Copy of pixel buffer
-///
-/// Stored as list of u8, chunks of 4 represent RGBA
-pub fn pixels_copy(ctx: &Context) -> Vec<u8> {
-    ctx.render.canvas.get_pixel_buffer()
-}
+    /// Restore pixels and size from a saved state
+    /// Drops the depth buffer if the size changes
+    /// The state must come from a canvas with the same supersampling
+    pub(crate) fn restore_state(&mut self, state: &CanvasState) {
+        debug_assert_eq!(state.supersample, self.supersample);
+        if (state.width, state.height) != self.logical_size() {
+            self.depth = None;
+        }
+        self.pixels.clone_from(&state.pixels);
+        self.width = state.width * self.supersample;
+        self.height = state.height * self.supersample;
+        self.mark_all_dirty();
+    }
 
-/// Write color to pixel at (x, y)
-///
-/// Color: Full opacity RGB \[0,255\]
-///
-/// Panics if trying to write outside canvas
-pub fn write_pixel_rgb(ctx: &mut Context, x: u32, y: u32, color: &[u8; 3]) {
-    ctx.render.canvas.write_pixel(x, y, color);
-}
+    /// Parse a font and store it for drawing
+    #[cfg(feature = "text")]
+    pub(crate) fn load_font(&mut self, bytes: &[u8]) -> Result<FontId, &'static str> {
+        let font = fontdue::Font::from_bytes(bytes, fontdue::FontSettings::default())?;
+        self.fonts.push(font);
+        Ok(FontId(self.fonts.len() - 1))
+    }
 
-/// Write color to pixel at (x, y)
-///
-/// Color: Full opacity RGB \[0,1\]
-///
-/// Panics if trying to write outside canvas
-pub fn write_pixel_rgb_f32(ctx: &mut Context, x: u32, y: u32, color: &[f32; 3]) {
-    ctx.render.canvas.write_pixel_f32(x, y, color);
-}
+    /// Rasterize text and blend it onto the canvas
+    /// (x, y) is the top left corner of the first line
+    /// Glyphs outside the canvas are clipped
+    #[cfg(feature = "text")]
+    pub(crate) fn draw_text_ttf(
+        &mut self,
+        font_id: FontId,
+        text: &str,
+        x: i32,
+        y: i32,
+        px_size: f32,
+        color: &[u8; 4],
+    ) {
+        let font = &self.fonts[font_id.0];
+        let ascent = font
+            .horizontal_line_metrics(px_size)
+            .map(|metrics| metrics.ascent)
+            .unwrap_or(px_size);
+        let baseline = y as f32 + ascent;
 
-/// Write color to pixel at (x, y)
-///
-/// Color: Non premultiplied RGBA \[0,255\]
-///
-/// Panics if trying to write outside canvas
-pub fn write_pixel_rgba(ctx: &mut Context, x: u32, y: u32, color: &[u8; 4]) {
-    ctx.render.canvas.write_pixel_blend(x, y, color);
-}
+        let mut glyphs = Vec::new();
+        let mut pen_x = x as f32;
+        let mut previous = None;
+        for c in text.chars() {
+            if let Some(kern) = previous.and_then(|p| font.horizontal_kern(p, c, px_size)) {
+                pen_x += kern;
+            }
+            let (metrics, coverage) = font.rasterize(c, px_size);
+            let glyph_x = pen_x.round() as i32 + metrics.xmin;
+            let glyph_y = (baseline - metrics.height as f32 - metrics.ymin as f32).round() as i32;
+            glyphs.push((glyph_x, glyph_y, metrics.width, coverage));
+            pen_x += metrics.advance_width;
+            previous = Some(c);
+        }
 
-/// Write color to pixel at (x, y)
-///
-/// Color: Non premultiplied RGBA \[0,1\]
-///
-/// Panics if trying to write outside canvas
-pub fn write_pixel_rgba_f32(ctx: &mut Context, x: u32, y: u32, color: &[f32; 4]) {
-    ctx.render.canvas.write_pixel_blend_f32(x, y, color);
-}
+        for (glyph_x, glyph_y, glyph_width, coverage) in glyphs {
+            for (i, alpha) in coverage.into_iter().enumerate() {
+                let px = glyph_x + (i % glyph_width) as i32;
+                let py = glyph_y + (i / glyph_width) as i32;
+                if alpha == 0 {
+                    continue;
+                }
+                let alpha = (color[3] as u32 * alpha as u32 / 255) as u8;
+                self.plot(px, py, &[color[0], color[1], color[2], alpha]);
+            }
+        }
+    }
 
-/// Color at pixel (x, y)
-///
-/// Color: RGB \[0,255\]
-///
-/// Panics if trying to access outside canvas
-pub fn pixel_rgb(ctx: &Context, x: u32, y: u32) -> [u8; 3] {
-    ctx.render.canvas.pixel_rgb(x, y)
-}
+    /// Offset drawing by (dx, dy) on top of the current transform
+    pub(crate) fn push_transform(&mut self, dx: i32, dy: i32) {
+        let (x, y) = self.offset();
+        self.transforms.push((x + dx, y + dy));
+    }
 
-/// Color at pixel (x, y)
-///
-/// Color: RGB \[0,1\]
-///
-/// Panics if trying to access outside canvas
-pub fn pixel_rgb_f32(ctx: &Context, x: u32, y: u32) -> [f32; 3] {
-    ctx.render.canvas.pixel_rgb_f32(x, y)
-}
+    /// Restores the transform before the last push
+    pub(crate) fn pop_transform(&mut self) {
+        self.transforms.pop();
+    }
 
-/// Color at pixel (x, y)
-///
-/// Color: RGBA \[0,255\]
-///
-/// Panics if trying to access outside canvas
-pub fn pixel_rgba(ctx: &Context, x: u32, y: u32) -> [u8; 4] {
-    ctx.render.canvas.pixel_rgba(x, y)
-}
+    /// Current drawing offset
+    pub(crate) fn offset(&self) -> (i32, i32) {
+        self.transforms.last().copied().unwrap_or((0, 0))
+    }
 
-/// Color at pixel (x, y)
-///
-/// Color: RGBA \[0,1\]
-///
-/// Panics if trying to access outside canvas
-pub fn pixel_rgba_f32(ctx: &Context, x: u32, y: u32) -> [f32; 4] {
-    ctx.render.canvas.pixel_rgba_f32(x, y)
-}
+    /// Applies the current transform to a pixel
+    /// Pixels moved above or left of the canvas end up out of range
+    pub(crate) fn translate_pixel(&self, x: u32, y: u32) -> (u32, u32) {
+        let (dx, dy) = self.offset();
+        (x.wrapping_add_signed(dx), y.wrapping_add_signed(dy))
+    }
 
-/// Resizes the canvas
-///
-/// Clears screen to clear color
-pub fn resize(ctx: &mut Context, width: u32, height: u32) {
-    ctx.render.canvas.resize(width, height);
-    ctx.render.screenshot_uploader.resize(width, height);
-    ctx.render.resize_canvas_texture(width, height);
-}
+    /// Returns true if (x, y) is inside the canvas
+    fn contains(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height
+    }
 
-/// Set canvas clear color
-///
-/// Color: RGB \[0,255\]
-pub fn set_clear_color(ctx: &mut Context, color: &[u8; 3]) {
-    ctx.render.canvas.set_clear_color(color);
-}
+    /// Returns true if pixel (x, y) is inside the canvas
+    fn contains_pixel(&self, x: i32, y: i32) -> bool {
+        let (width, height) = self.logical_size();
+        x >= 0 && y >= 0 && (x as u32) < width && (y as u32) < height
+    }
+
+    /// Blend color at (x, y) offset by the current transform
+    /// Pixels outside the canvas are skipped
+    pub(crate) fn plot(&mut self, x: i32, y: i32, color: &[u8; 4]) {
+        let (dx, dy) = self.offset();
+        let (x, y) = (x + dx, y + dy);
+        if self.contains_pixel(x, y) {
+            for (sx, sy) in sample_block(x as u32, y as u32, self.supersample) {
+                self.write_pixel_blend(sx, sy, color);
+            }
+        }
+    }
+
+    /// Blend color at (x, y) if depth is nearer than the stored depth
+    /// Smaller depth is nearer, the stored depth is updated on write
+    /// Panics if trying to write outside canvas
+    pub(crate) fn write_pixel_depth(&mut self, x: u32, y: u32, color: &[u8; 4], depth: f32) {
+        assert_pixel(x, y, self.width, self.height);
+        let size = (self.width * self.height) as usize;
+        for (x, y) in self.symmetric(x, y) {
+            let buffer = self.depth.get_or_insert_with(|| vec![f32::INFINITY; size]);
+            let index = (y * self.width + x) as usize;
+            if depth >= buffer[index] {
+                continue;
+            }
+            buffer[index] = depth;
+            self.mark_dirty(x, y);
+            self.blend_pixel(x, y, color);
+        }
+    }
+
+    /// Reset the depth of every pixel to the furthest possible
+    pub(crate) fn clear_depth(&mut self) {
+        if let Some(depth) = &mut self.depth {
+            depth.fill(f32::INFINITY);
+        }
+    }
+
+    /// Overwrites all pixels with color and the depth buffer, if allocated, with depth
+    pub(crate) fn clear_all(&mut self, color: &[u8; 4], depth: f32) {
+        self.mark_all_dirty();
+        for pixel in self.pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(color);
+        }
+        if let Some(buffer) = &mut self.depth {
+            buffer.fill(depth);
+        }
+    }
+
+    /// Depth tested ```plot```
+    /// Fully transparent pixels leave the depth untouched
+    pub(crate) fn plot_depth(&mut self, x: i32, y: i32, color: &[u8; 4], depth: f32) {
+        let (dx, dy) = self.offset();
+        let (x, y) = (x + dx, y + dy);
+        if color[3] > 0 && self.contains_pixel(x, y) {
+            for (sx, sy) in sample_block(x as u32, y as u32, self.supersample) {
+                self.write_pixel_depth(sx, sy, color, depth);
+            }
+        }
+    }
+
+    /// Blend a rectangle with top left corner at (x, y)
+    /// Pixels outside the canvas are skipped
+    pub(crate) fn fill_rect(&mut self, x: i32, y: i32, w: u32, h: u32, color: &[u8; 4]) {
+        let s = self.supersample;
+        let (dx, dy) = self.offset();
+        let (x, y) = ((x + dx) * s as i32, (y + dy) * s as i32);
+        let (w, h) = (w * s, h * s);
+        let min_x = x.max(0);
+        let min_y = y.max(0);
+        let max_x = span_end(x, w).min(self.width as i32);
+        let max_y = span_end(y, h).min(self.height as i32);
+        let samples =
+            (min_y..max_y).flat_map(|py| (min_x..max_x).map(move |px| (px as u32, py as u32)));
+        self.blend_once(samples, color);
+    }
+
+    /// Blend a one pixel wide line from (x0, y0) to (x1, y1), both ends included
+    /// Supersampled canvases rasterize between pixel centers at sample resolution
+    /// Pixels outside the canvas are skipped
+    pub(crate) fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: &[u8; 4]) {
+        let s = self.supersample as i32;
+        let (dx, dy) = self.offset();
+        let center = |v: i32, d: i32| (v + d) * s + s / 2;
+        let (mut x, mut y) = (center(x0, dx), center(y0, dy));
+        let (end_x, end_y) = (center(x1, dx), center(y1, dy));
+
+        // Each step stamps a pixel sized square, samples are blended once
+        let mut samples = HashSet::new();
+        let (step_x, step_y) = ((end_x - x).signum(), (end_y - y).signum());
+        let (delta_x, delta_y) = ((end_x - x).abs(), -(end_y - y).abs());
+        let mut error = delta_x + delta_y;
+        loop {
+            for oy in 0..s {
+                for ox in 0..s {
+                    samples.insert((x - s / 2 + ox, y - s / 2 + oy));
+                }
+            }
+            if x == end_x && y == end_y {
+                break;
+            }
+            let doubled = 2 * error;
+            if doubled >= delta_y {
+                error += delta_y;
+                x += step_x;
+            }
+            if doubled <= delta_x {
+                error += delta_x;
+                y += step_y;
+            }
+        }
+        let samples = samples
+            .into_iter()
+            .filter(|&(sx, sy)| self.contains(sx, sy))
+            .map(|(sx, sy)| (sx as u32, sy as u32))
+            .collect::<Vec<_>>();
+        self.blend_once(samples.into_iter(), color);
+    }
+
+    /// Blend one pixel wide lines every spacing pixels, passing through origin
+    /// Origin is offset by the current transform
+    /// Spacing 0 or 1 draws nothing
+    pub(crate) fn draw_grid(&mut self, spacing: u32, origin: (i32, i32), color: &[u8; 4]) {
+        if spacing <= 1 {
+            return;
+        }
+        let (dx, dy) = self.offset();
+        let spacing = spacing as i32;
+        let start_x = (origin.0 + dx).rem_euclid(spacing) as u32;
+        let start_y = (origin.1 + dy).rem_euclid(spacing) as u32;
+        let on_column = |x: u32| x >= start_x && (x - start_x) % spacing as u32 == 0;
+        let (width, height) = self.logical_size();
+        let blend = |canvas: &mut Self, x: u32, y: u32| {
+            for (sx, sy) in sample_block(x, y, canvas.supersample) {
+                canvas.write_pixel_blend(sx, sy, color);
+            }
+        };
+
+        for x in (start_x..width).step_by(spacing as usize) {
+            for y in 0..height {
+                blend(self, x, y);
+            }
+        }
+        for y in (start_y..height).step_by(spacing as usize) {
+            // Intersections are already drawn by the columns
+            for x in (0..width).filter(|x| !on_column(*x)) {
+                blend(self, x, y);
+            }
+        }
+    }
+
+    /// Blend color at every point offset by the current transform
+    /// Points outside the canvas are skipped
+    pub(crate) fn draw_points(&mut self, points: &[(i32, i32)], color: &[u8; 4]) {
+        self.draw_points_with(points.iter().map(|&point| (point, color)));
+    }
+
+    /// Blend each point with its own color offset by the current transform
+    /// Points outside the canvas are skipped
+    pub(crate) fn draw_points_colored(&mut self, points: &[((i32, i32), [u8; 4])]) {
+        self.draw_points_with(points.iter().map(|(point, color)| (*point, color)));
+    }
+
+    /// Blends all points and marks their bounding box as changed once
+    fn draw_points_with<'a>(&mut self, points: impl Iterator<Item = ((i32, i32), &'a [u8; 4])>) {
+        if self.symmetry != SymmetryMode::None {
+            for ((x, y), color) in points {
+                self.plot(x, y, color);
+            }
+            return;
+        }
+        let (dx, dy) = self.offset();
+        let mut bounds: Option<DirtyRegion> = None;
+        let s = self.supersample;
+        for ((x, y), color) in points {
+            let (x, y) = (x + dx, y + dy);
+            if !self.contains_pixel(x, y) {
+                continue;
+            }
+            let (x, y) = (x as u32, y as u32);
+            for (sx, sy) in sample_block(x, y, s) {
+                self.blend_pixel(sx, sy, color);
+            }
+            let point = DirtyRegion {
+                min_x: x * s,
+                min_y: y * s,
+                max_x: (x + 1) * s,
+                max_y: (y + 1) * s,
+            };
+            bounds = Some(match bounds {
+                Some(bounds) => bounds.union(&point),
+                None => point,
+            });
+        }
+        if let Some(bounds) = bounds {
+            self.mark_dirty_rect(bounds.min_x, bounds.min_y, bounds.max_x, bounds.max_y);
+        }
+    }
+
+    /// Blend an antialiased circle outline centered at (cx, cy)
+    /// Pixels are covered by their distance to the radius
+    pub(crate) fn draw_circle_aa(&mut self, cx: i32, cy: i32, radius: u32, color: &[u8; 4]) {
+        self.circle_aa(cx, cy, radius, color, |distance, radius| {
+            1.0 - (distance - radius).abs()
+        });
+    }
+
+    /// Blend an antialiased filled circle centered at (cx, cy)
+    /// Edge pixels are covered by how far they are inside the radius
+    pub(crate) fn fill_circle_aa(&mut self, cx: i32, cy: i32, radius: u32, color: &[u8; 4]) {
+        self.circle_aa(cx, cy, radius, color, |distance, radius| {
+            radius + 0.5 - distance
+        });
+    }
+
+    /// Blends color into every pixel around (cx, cy) scaled by coverage(distance, radius)
+    fn circle_aa(
+        &mut self,
+        cx: i32,
+        cy: i32,
+        radius: u32,
+        color: &[u8; 4],
+        coverage: impl Fn(f32, f32) -> f32,
+    ) {
+        // Rasterized per sample, distances stay in pixels
+        let s = self.supersample as i32;
+        let (dx, dy) = self.offset();
+        let (base_x, base_y) = ((cx + dx) * s, (cy + dy) * s);
+        let half = (s - 1) as f32 / 2.0;
+        let extent = (radius as i32 + 1) * s;
+        for y in base_y - extent..=base_y + s - 1 + extent {
+            for x in base_x - extent..=base_x + s - 1 + extent {
+                if !self.contains(x, y) {
+                    continue;
+                }
+                let (ox, oy) = ((x - base_x) as f32 - half, (y - base_y) as f32 - half);
+                let distance = (ox * ox + oy * oy).sqrt() / s as f32;
+                let coverage = coverage(distance, radius as f32).clamp(0.0, 1.0);
+                if coverage <= 0.0 {
+                    continue;
+                }
+                let alpha = (color[3] as f32 * coverage).round() as u8;
+                self.write_pixel_blend(x as u32, y as u32, &[color[0], color[1], color[2], alpha]);
+            }
+        }
+    }
+
+    /// Blend sprite with top left corner at (x, y)
+    /// Pixels outside the canvas are skipped
+    pub(crate) fn draw_sprite(&mut self, sprite: &Sprite, x: i32, y: i32) {
+        for sy in 0..sprite.height {
+            for sx in 0..sprite.width {
+                let color = sprite.pixel_rgba(sx, sy);
+                self.plot(x + sx as i32, y + sy as i32, &color);
+            }
+        }
+    }
+
+    /// Overwrite pixels with buffer, top left corner at (x, y) offset by the current transform
+    /// Pixels outside the canvas are skipped
+    pub(crate) fn apply_buffer(&mut self, buffer: &PixelBuffer, x: i32, y: i32) {
+        let s = self.supersample;
+        let (dx, dy) = self.offset();
+        let (x, y) = (x + dx, y + dy);
+        let (width, height) = self.logical_size();
+        let min_x = x.max(0);
+        let min_y = y.max(0);
+        let max_x = span_end(x, buffer.width).min(width as i32);
+        let max_y = span_end(y, buffer.height).min(height as i32);
+        if min_x >= max_x || min_y >= max_y {
+            return;
+        }
+
+        for py in min_y..max_y {
+            for px in min_x..max_x {
+                let from = (((py - y) as u32 * buffer.width + (px - x) as u32) * 4) as usize;
+                for (sx, sy) in sample_block(px as u32, py as u32, s) {
+                    let to = ((sy * self.width + sx) * 4) as usize;
+                    self.pixels[to..to + 4].copy_from_slice(&buffer.pixels[from..from + 4]);
+                }
+            }
+        }
+        self.mark_dirty_rect(
+            min_x as u32 * s,
+            min_y as u32 * s,
+            max_x as u32 * s,
+            max_y as u32 * s,
+        );
+    }
+
+    /// Blend every sprite of the batch in submission order
+    /// Marks one dirty rect covering all drawn pixels
+    pub(crate) fn draw_batch(&mut self, batch: &SpriteBatch) {
+        if self.supersample > 1 || self.symmetry != SymmetryMode::None {
+            for entry in &batch.entries {
+                self.draw_sprite_tinted(entry.sprite, entry.x, entry.y, &entry.tint);
+            }
+            return;
+        }
+        let (dx, dy) = self.offset();
+        let mut bounds: Option<DirtyRegion> = None;
+        for entry in &batch.entries {
+            let sprite = entry.sprite;
+            let (x, y) = (entry.x + dx, entry.y + dy);
+            // Clip the sprite to the canvas once instead of per pixel
+            let min_x = x.max(0);
+            let min_y = y.max(0);
+            let max_x = span_end(x, sprite.width).min(self.width as i32);
+            let max_y = span_end(y, sprite.height).min(self.height as i32);
+            if min_x >= max_x || min_y >= max_y {
+                continue;
+            }
+            for py in min_y..max_y {
+                for px in min_x..max_x {
+                    let color = sprite.pixel_rgba((px - x) as u32, (py - y) as u32);
+                    self.blend_pixel(px as u32, py as u32, &tint_color(&color, &entry.tint));
+                }
+            }
+            let region = DirtyRegion {
+                min_x: min_x as u32,
+                min_y: min_y as u32,
+                max_x: max_x as u32,
+                max_y: max_y as u32,
+            };
+            bounds = Some(match bounds {
+                Some(bounds) => bounds.union(&region),
+                None => region,
+            });
+        }
+        if let Some(bounds) = bounds {
+            self.mark_dirty_rect(bounds.min_x, bounds.min_y, bounds.max_x, bounds.max_y);
+        }
+    }
+
+    /// Depth tested ```fill_rect```
+    pub(crate) fn fill_rect_depth(
+        &mut self,
+        x: i32,
+        y: i32,
+        w: u32,
+        h: u32,
+        color: &[u8; 4],
+        depth: f32,
+    ) {
+        // Clip before the offset applied by plot_depth
+        let (dx, dy) = self.offset();
+        let (width, height) = self.logical_size();
+        let max_x = span_end(x, w).min((width as i32).saturating_sub(dx));
+        let max_y = span_end(y, h).min((height as i32).saturating_sub(dy));
+        for py in y.max(dy.saturating_neg())..max_y {
+            for px in x.max(dx.saturating_neg())..max_x {
+                self.plot_depth(px, py, color, depth);
+            }
+        }
+    }
+
+    /// Blend sprite with every pixel multiplied by tint, top left corner at (x, y)
+    /// Pixels outside the canvas are skipped
+    pub(crate) fn draw_sprite_tinted(&mut self, sprite: &Sprite, x: i32, y: i32, tint: &[u8; 4]) {
+        for sy in 0..sprite.height {
+            for sx in 0..sprite.width {
+                let color = tint_color(&sprite.pixel_rgba(sx, sy), tint);
+                self.plot(x + sx as i32, y + sy as i32, &color);
+            }
+        }
+    }
+
+    /// Depth tested ```draw_sprite```
+    pub(crate) fn draw_sprite_depth(&mut self, sprite: &Sprite, x: i32, y: i32, depth: f32) {
+        for sy in 0..sprite.height {
+            for sx in 0..sprite.width {
+                let color = sprite.pixel_rgba(sx, sy);
+                self.plot_depth(x + sx as i32, y + sy as i32, &color, depth);
+            }
+        }
+    }
+
+    /// Blend sprite scaled to w x h with fixed size borders (left, top, right, bottom)
+    /// Corners are drawn 1:1, edges and center are stretched
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn draw_nine_slice(
+        &mut self,
+        sprite: &Sprite,
+        border: (u32, u32, u32, u32),
+        x: i32,
+        y: i32,
+        w: u32,
+        h: u32,
+    ) {
+        let (left, top, right, bottom) = border;
+        assert!(
+            left + right <= sprite.width && top + bottom <= sprite.height,
+            "nine slice border {:?} does not fit sprite {}x{}",
+            border,
+            sprite.width,
+            sprite.height
+        );
+
+        for ty in 0..h {
+            let sy = nine_slice_source(ty, h, top, bottom, sprite.height);
+            for tx in 0..w {
+                let sx = nine_slice_source(tx, w, left, right, sprite.width);
+                let color = sprite.pixel_rgba(sx, sy);
+                self.plot(x + tx as i32, y + ty as i32, &color);
+            }
+        }
+    }
+
+    /// Draw text using the built in bitmap font
+    /// (x, y) is the top left corner of the first line
+    pub(crate) fn draw_text(&mut self, text: &str, x: i32, y: i32, color: &[u8; 4]) {
+        for (line_index, line) in text.lines().enumerate() {
+            let line_y = y + (line_index as u32 * font::LINE_HEIGHT) as i32;
+            for (char_index, c) in line.chars().enumerate() {
+                let char_x = x + (char_index as u32 * font::GLYPH_ADVANCE) as i32;
+                for gy in 0..font::GLYPH_HEIGHT {
+                    for gx in 0..font::GLYPH_WIDTH {
+                        if font::glyph_pixel(c, gx, gy) {
+                            self.plot(char_x + gx as i32, line_y + gy as i32, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draw text broken into lines no wider than max_width
+    /// Returns the height of all drawn lines
+    pub(crate) fn draw_text_wrapped(
+        &mut self,
+        text: &str,
+        x: i32,
+        y: i32,
+        max_width: u32,
+        color: &[u8; 4],
+    ) -> u32 {
+        let lines = font::wrap_text(text, max_width);
+        for (line_index, line) in lines.iter().enumerate() {
+            let line_y = y + (line_index as u32 * font::LINE_HEIGHT) as i32;
+            self.draw_text(line, x, line_y, color);
+        }
+        (lines.len() as u32 * font::LINE_HEIGHT).saturating_sub(1)
+    }
+
+    /// Draw text on top of a background rectangle
+    /// The background covers the text and padding on all sides
+    pub(crate) fn draw_label(
+        &mut self,
+        text: &str,
+        x: i32,
+        y: i32,
+        fg: &[u8; 4],
+        bg: &[u8; 4],
+        padding: u32,
+    ) {
+        if bg[3] > 0 {
+            let w = font::text_width(text) + 2 * padding;
+            let h = font::text_height(text) + 2 * padding;
+            self.fill_rect(x, y, w, h, bg);
+        }
+        self.draw_text(text, x + padding as i32, y + padding as i32, fg);
+    }
+
+    /// Draw debug text on a translucent backdrop in the top left corner
+    pub(crate) fn draw_debug_overlay(&mut self, text: &str) {
+        // Overlay is always in screen space
+        let transforms = std::mem::take(&mut self.transforms);
+        let symmetry = std::mem::take(&mut self.symmetry);
+        self.draw_label(
+            text,
+            DEBUG_OVERLAY_MARGIN,
+            DEBUG_OVERLAY_MARGIN,
+            &[255, 255, 255, 255],
+            &[0, 0, 0, 160],
+            2,
+        );
+        self.transforms = transforms;
+        self.symmetry = symmetry;
+    }
+
+    /// Blend a one pixel wide outline of ```safe_rect```
+    /// Always in screen space, the current transform and symmetry are ignored
+    pub(crate) fn draw_safe_area(&mut self, margin_percent: f32, color: &[u8; 4]) {
+        let (width, height) = self.logical_size();
+        let (x, y, w, h) = inset_rect(width, height, margin_percent);
+        if w == 0 || h == 0 {
+            return;
+        }
+        let transforms = std::mem::take(&mut self.transforms);
+        let symmetry = std::mem::take(&mut self.symmetry);
+        let (x, y) = (x as i32, y as i32);
+        self.fill_rect(x, y, w, 1, color);
+        if h > 1 {
+            self.fill_rect(x, span_end(y, h - 1), w, 1, color);
+        }
+        // Sides without the corners already drawn
+        if h > 2 {
+            self.fill_rect(x, y + 1, 1, h - 2, color);
+            if w > 1 {
+                self.fill_rect(span_end(x, w - 1), y + 1, 1, h - 2, color);
+            }
+        }
+        self.transforms = transforms;
+        self.symmetry = symmetry;
+    }
+
+    /// Sets a single channel of all pixels to value
+    /// Leaves other channels intact
+    pub(crate) fn clear_channel(&mut self, channel: Channel, value: u8) {
+        self.mark_all_dirty();
+        let offset = channel.offset();
+        for pixel in self.pixels.chunks_mut(4) {
+            pixel[offset] = value;
+        }
+    }
+
+    /// Replaces the color of every pixel with its luminance
+    /// Alpha is left intact
+    pub(crate) fn apply_grayscale(&mut self) {
+        self.mark_all_dirty();
+        for pixel in self.pixels.chunks_mut(4) {
+            let luminance =
+                0.2126 * pixel[0] as f32 + 0.7152 * pixel[1] as f32 + 0.0722 * pixel[2] as f32;
+            let luminance = luminance.round().min(255.0) as u8;
+            pixel[..3].fill(luminance);
+        }
+    }
+
+    /// Multiplies every pixel (r,g,b,a) in range [0,1] with matrix
+    /// Results are clamped to [0,1]
+    pub(crate) fn apply_color_matrix(&mut self, matrix: &[[f32; 4]; 4]) {
+        self.mark_all_dirty();
+        for pixel in self.pixels.chunks_mut(4) {
+            let color = [
+                pixel[0] as f32 / 255.0,
+                pixel[1] as f32 / 255.0,
+                pixel[2] as f32 / 255.0,
+                pixel[3] as f32 / 255.0,
+            ];
+            for (channel, row) in pixel.iter_mut().zip(matrix) {
+                let value = row.iter().zip(color).map(|(m, c)| m * c).sum::<f32>();
+                *channel = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+            }
+        }
+    }
+
+    /// Blend color modulated by the brush alpha centered at (x, y)
+    /// Pixels outside the canvas are skipped
+    pub(crate) fn stamp(&mut self, brush: &Brush, x: i32, y: i32, color: &[u8; 4]) {
+        let left = x - (brush.width / 2) as i32;
+        let top = y - (brush.height / 2) as i32;
+        for by in 0..brush.height {
+            for bx in 0..brush.width {
+                let mask = brush.alpha[(by * brush.width + bx) as usize];
+                if mask == 0 {
+                    continue;
+                }
+                let alpha = (color[3] as u32 * mask as u32 / 255) as u8;
+                self.plot(
+                    left + bx as i32,
+                    top + by as i32,
+                    &[color[0], color[1], color[2], alpha],
+                );
+            }
+        }
+    }
+
+    /// Stamp the brush every spacing pixels along the path
+    /// The first point is always stamped
+    pub(crate) fn stroke(
+        &mut self,
+        brush: &Brush,
+        points: &[(i32, i32)],
+        color: &[u8; 4],
+        spacing: u32,
+    ) {
+        assert!(spacing > 0, "stroke spacing must be greater than 0");
+
+        let Some(&(first_x, first_y)) = points.first() else {
+            return;
+        };
+        self.stamp(brush, first_x, first_y, color);
+
+        let spacing = spacing as f32;
+        // Distance travelled since the last stamp
+        let mut travelled = 0.0;
+        for segment in points.windows(2) {
+            let (ax, ay) = (segment[0].0 as f32, segment[0].1 as f32);
+            let (bx, by) = (segment[1].0 as f32, segment[1].1 as f32);
+            let length = ((bx - ax).powi(2) + (by - ay).powi(2)).sqrt();
+            if length == 0.0 {
+                continue;
+            }
+
+            let mut next = spacing - travelled;
+            while next <= length {
+                let t = next / length;
+                let x = (ax + (bx - ax) * t).round() as i32;
+                let y = (ay + (by - ay) * t).round() as i32;
+                self.stamp(brush, x, y, color);
+                next += spacing;
+            }
+            travelled = length - (next - spacing);
+        }
+    }
+
+    /// Box blur the rectangle with top left corner at (x, y)
+    /// Two separable passes, samples outside the rectangle are clamped to its edge
+    pub(crate) fn blur(&mut self, x: i32, y: i32, w: u32, h: u32, radius: u32) {
+        let s = self.supersample;
+        let (dx, dy) = self.offset();
+        let (x, y) = ((x + dx) * s as i32, (y + dy) * s as i32);
+        let (w, h, radius) = (w * s, h * s, radius * s);
+        let min_x = x.max(0) as u32;
+        let min_y = y.max(0) as u32;
+        let max_x = span_end(x, w).clamp(0, self.width as i32) as u32;
+        let max_y = span_end(y, h).clamp(0, self.height as i32) as u32;
+        if radius == 0 || min_x >= max_x || min_y >= max_y {
+            return;
+        }
+
+        let region_width = max_x - min_x;
+        let region_height = max_y - min_y;
+        let mut region = Vec::with_capacity((region_width * region_height * 4) as usize);
+        for py in min_y..max_y {
+            let start = (py * self.width + min_x) as usize * 4;
+            let end = (py * self.width + max_x) as usize * 4;
+            region.extend_from_slice(&self.pixels[start..end]);
+        }
+
+        self.mark_dirty_rect(min_x, min_y, max_x, max_y);
+        let region = box_blur_pass(&region, region_width, region_height, radius, (1, 0));
+        let region = box_blur_pass(&region, region_width, region_height, radius, (0, 1));
+
+        for (row, py) in (min_y..max_y).enumerate() {
+            let start = (py * self.width + min_x) as usize * 4;
+            let end = (py * self.width + max_x) as usize * 4;
+            let row_len = region_width as usize * 4;
+            self.pixels[start..end].copy_from_slice(&region[row * row_len..(row + 1) * row_len]);
+        }
+    }
+
+    /// Blend color on every pixel that differs from a neighbour by more than threshold
+    /// Edges are detected on a copy, non edge pixels are cleared unless keep_original
+    pub(crate) fn outline(&mut self, threshold: u8, color: &[u8; 4], keep_original: bool) {
+        let edges = edge_mask(&self.pixels, self.width, self.height, threshold);
+        if !keep_original {
+            self.pixels.fill(0);
+        }
+        self.mark_all_dirty();
+        for (i, _) in edges.iter().enumerate().filter(|(_, &edge)| edge) {
+            let (x, y) = (i as u32 % self.width, i as u32 / self.width);
+            self.blend_pixel(x, y, color);
+        }
+    }
+
+    /// Blend other canvas with top left corner at (x, y)
+    /// Pixels outside the canvas are skipped
+    pub(crate) fn composite(&mut self, other: &Canvas, x: i32, y: i32) {
+        let (width, height) = other.logical_size();
+        for sy in 0..height {
+            for sx in 0..width {
+                let color = other.resolved_rgba(sx, sy);
+                self.plot(x + sx as i32, y + sy as i32, &color);
+            }
+        }
+    }
+
+    /// Hashes the size and pixels of the canvas
+    /// FxHash over 8 byte words, fast but not collision resistant
+    pub(crate) fn content_hash(&self) -> u64 {
+        const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+        let add = |hash: u64, word: u64| (hash.rotate_left(5) ^ word).wrapping_mul(SEED);
+
+        let mut hash = add(0, (self.width as u64) << 32 | self.height as u64);
+        let mut words = self.pixels.chunks_exact(8);
+        for word in &mut words {
+            hash = add(hash, u64::from_le_bytes(word.try_into().unwrap()));
+        }
+        let mut rest = [0; 8];
+        rest[..words.remainder().len()].copy_from_slice(words.remainder());
+        add(hash, u64::from_le_bytes(rest))
+    }
+
+    /// Counts the pixels of every distinct color
+    pub(crate) fn color_histogram(&self) -> HashMap<[u8; 4], u32> {
+        let mut histogram = HashMap::new();
+        for pixel in self.pixels.chunks_exact(4) {
+            let color: [u8; 4] = pixel.try_into().unwrap();
+            *histogram.entry(color).or_insert(0) += 1;
+        }
+        histogram
+    }
+
+    /// Returns the n most common colors with their counts, most common first
+    /// Ties are ordered by color
+    pub(crate) fn dominant_colors(&self, n: usize) -> Vec<([u8; 4], u32)> {
+        let mut colors = self.color_histogram().into_iter().collect::<Vec<_>>();
+        colors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        colors.truncate(n);
+        colors
+    }
+
+    /// Copy src (x, y, w, h) onto dst (x, y, w, h) with nearest neighbor sampling
+    /// Both rectangles are offset by the current transform
+    /// Samples are read from a copy so overlapping rectangles are supported
+    /// Pixels outside the canvas are skipped
+    pub(crate) fn blit_scaled(&mut self, src: (i32, i32, u32, u32), dst: (i32, i32, u32, u32)) {
+        let (dx, dy) = self.offset();
+        let scale = |(x, y, w, h): (i32, i32, u32, u32)| {
+            let s = self.supersample;
+            ((x + dx) * s as i32, (y + dy) * s as i32, w * s, h * s)
+        };
+        let (src_x, src_y, src_w, src_h) = scale(src);
+        let (dst_x, dst_y, dst_w, dst_h) = scale(dst);
+        let min_x = dst_x.max(0);
+        let min_y = dst_y.max(0);
+        let max_x = span_end(dst_x, dst_w).min(self.width as i32);
+        let max_y = span_end(dst_y, dst_h).min(self.height as i32);
+        if src_w == 0 || src_h == 0 || min_x >= max_x || min_y >= max_y {
+            return;
+        }
+
+        let source = self.pixels.clone();
+        for py in min_y..max_y {
+            let sy = src_y + ((py - dst_y) as u64 * src_h as u64 / dst_h as u64) as i32;
+            for px in min_x..max_x {
+                let sx = src_x + ((px - dst_x) as u64 * src_w as u64 / dst_w as u64) as i32;
+                if !self.contains(sx, sy) {
+                    continue;
+                }
+                let from = (sy as u32 * self.width + sx as u32) as usize * 4;
+                for (x, y) in self.symmetric(px as u32, py as u32) {
+                    let to = (y * self.width + x) as usize * 4;
+                    self.pixels[to..to + 4].copy_from_slice(&source[from..from + 4]);
+                    self.mark_dirty(x, y);
+                }
+            }
+        }
+    }
+}
+
+/// Replaces pixels where every channel is within tolerance of from with to
+/// Returns true if any pixel was replaced
+fn replace_pixels(pixels: &mut [u8], from: &[u8; 4], to: &[u8; 4], tolerance: u8) -> bool {
+    let mut replaced = false;
+    for pixel in pixels.chunks_exact_mut(4) {
+        if pixel
+            .iter()
+            .zip(from)
+            .all(|(c, f)| c.abs_diff(*f) <= tolerance)
+        {
+            pixel.copy_from_slice(to);
+            replaced = true;
+        }
+    }
+    replaced
+}
+
+/// Multiplies every channel of color by tint, where 255 is 1.0
+fn tint_color(color: &[u8; 4], tint: &[u8; 4]) -> [u8; 4] {
+    let mul = |c: u8, t: u8| ((c as u32 * t as u32 + 127) / 255) as u8;
+    [
+        mul(color[0], tint[0]),
+        mul(color[1], tint[1]),
+        mul(color[2], tint[2]),
+        mul(color[3], tint[3]),
+    ]
+}
+
+/// Returns an error if a canvas of width x height does not fit in a texture
+pub(crate) fn check_texture_size(width: u32, height: u32, max: u32) -> Result<(), ResizeError> {
+    if width > max || height > max {
+        return Err(ResizeError::TooLarge { width, height, max });
+    }
+    Ok(())
+}
+
+/// Maps a target coordinate of a nine slice to the source sprite along one axis
+/// Start and end borders are kept 1:1, the middle is stretched
+fn nine_slice_source(target: u32, target_size: u32, start: u32, end: u32, source_size: u32) -> u32 {
+    if target < start {
+        return target;
+    }
+    if target >= target_size.saturating_sub(end) {
+        return source_size - (target_size - target);
+    }
+    let source_middle = source_size - start - end;
+    let target_middle = target_size - start - end;
+    if source_middle == 0 {
+        return start.min(source_size - 1);
+    }
+    start + (target - start) * source_middle / target_middle
+}
+
+/// Marks pixels where any channel differs by more than threshold from a horizontal or vertical neighbour
+/// Both sides of a color boundary are marked
+fn edge_mask(pixels: &[u8], width: u32, height: u32, threshold: u8) -> Vec<bool> {
+    let differs = |a: usize, b: usize| {
+        (0..4).any(|c| pixels[a * 4 + c].abs_diff(pixels[b * 4 + c]) > threshold)
+    };
+    let (width, height) = (width as usize, height as usize);
+    (0..width * height)
+        .map(|i| {
+            let (x, y) = (i % width, i / width);
+            (x > 0 && differs(i, i - 1))
+                || (x + 1 < width && differs(i, i + 1))
+                || (y > 0 && differs(i, i - width))
+                || (y + 1 < height && differs(i, i + width))
+        })
+        .collect()
+}
+
+/// Averages every pixel with its neighbours within radius along step
+/// Samples outside the buffer are clamped to the edge
+fn box_blur_pass(src: &[u8], width: u32, height: u32, radius: u32, step: (i32, i32)) -> Vec<u8> {
+    let mut dst = vec![0; src.len()];
+    let samples = 2 * radius + 1;
+    for y in 0..height as i32 {
+        for x in 0..width as i32 {
+            let mut sum = [0u32; 4];
+            for i in -(radius as i32)..=radius as i32 {
+                let sx = (x + i * step.0).clamp(0, width as i32 - 1);
+                let sy = (y + i * step.1).clamp(0, height as i32 - 1);
+                let index = (sy as u32 * width + sx as u32) as usize * 4;
+                for (c, channel) in sum.iter_mut().enumerate() {
+                    *channel += src[index + c] as u32;
+                }
+            }
+            let index = (y as u32 * width + x as u32) as usize * 4;
+            for (c, channel) in sum.iter().enumerate() {
+                dst[index + c] = ((channel + samples / 2) / samples) as u8;
+            }
+        }
+    }
+    dst
+}
+
+/// Rectangle of changed pixels [min, max)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct DirtyRegion {
+    pub(crate) min_x: u32,
+    pub(crate) min_y: u32,
+    pub(crate) max_x: u32,
+    pub(crate) max_y: u32,
+}
+
+impl DirtyRegion {
+    /// Region covering a whole canvas
+    pub(crate) fn full(width: u32, height: u32) -> Self {
+        Self {
+            min_x: 0,
+            min_y: 0,
+            max_x: width,
+            max_y: height,
+        }
+    }
+
+    /// Smallest region containing both regions
+    pub(crate) fn union(&self, other: &Self) -> Self {
+        Self {
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+        }
+    }
+
+    pub(crate) fn width(&self) -> u32 {
+        self.max_x - self.min_x
+    }
+
+    pub(crate) fn height(&self) -> u32 {
+        self.max_y - self.min_y
+    }
+}
+
+/// Handle to a font loaded with ```load_font```
+#[cfg(feature = "text")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FontId(usize);
+
+/// Handle to an offscreen canvas created with ```create_offscreen```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CanvasId(pub(crate) usize);
+
+/// Saved copy of the canvas
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CanvasState {
+    /// Samples of the canvas
+    pixels: Vec<u8>,
+    /// Size in pixels
+    width: u32,
+    height: u32,
+    supersample: u32,
+}
+
+/// Image of RGBA pixels
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sprite {
+    pub(crate) pixels: Vec<u8>,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+impl Sprite {
+    /// Create sprite from pixels
+    ///
+    /// Stored as list of u8, chunks of 4 represent RGBA
+    ///
+    /// Panics if pixels does not contain width * height RGBA values
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        assert_eq!(
+            pixels.len(),
+            (width * height * 4) as usize,
+            "sprite pixels does not match size {}x{}",
+            width,
+            height
+        );
+        Self {
+            pixels,
+            width,
+            height,
+        }
+    }
+
+    /// Create sprite where all pixels have the same color
+    ///
+    /// Color: RGBA \[0,255\]
+    pub fn filled(width: u32, height: u32, color: &[u8; 4]) -> Self {
+        let pixels = color.repeat((width * height) as usize);
+        Self::new(width, height, pixels)
+    }
+
+    /// Sprite width
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Sprite height
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Pixels of the sprite
+    ///
+    /// Stored as list of u8, chunks of 4 represent RGBA
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Color at pixel (x, y)
+    ///
+    /// Color: RGBA \[0,255\]
+    ///
+    /// Panics if trying to access outside sprite
+    pub fn pixel_rgba(&self, x: u32, y: u32) -> [u8; 4] {
+        assert_pixel(x, y, self.width, self.height);
+
+        let index = (y * 4 * self.width + x * 4) as usize;
+        [
+            self.pixels[index],
+            self.pixels[index + 1],
+            self.pixels[index + 2],
+            self.pixels[index + 3],
+        ]
+    }
+
+    /// Overwrite color at pixel (x, y)
+    ///
+    /// Color: RGBA \[0,255\]
+    ///
+    /// Panics if trying to write outside sprite
+    pub fn write_pixel(&mut self, x: u32, y: u32, color: &[u8; 4]) {
+        assert_pixel(x, y, self.width, self.height);
+
+        let index = (y * 4 * self.width + x * 4) as usize;
+        self.pixels[index..index + 4].copy_from_slice(color);
+    }
+
+    /// Replace every pixel where all channels are within tolerance of from with to
+    ///
+    /// Tolerance 0 only replaces exact matches, use a transparent to for chroma keying
+    ///
+    /// Color: RGBA \[0,255\]
+    pub fn replace_color(&mut self, from: &[u8; 4], to: &[u8; 4], tolerance: u8) {
+        replace_pixels(&mut self.pixels, from, to, tolerance);
+    }
+}
+
+/// Owned RGBA pixels that can be filled on any thread
+///
+/// Send it to the main thread and copy it onto the canvas with ```apply_buffer```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PixelBuffer {
+    pixels: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+impl PixelBuffer {
+    /// Create transparent buffer with specified width and height
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            pixels: vec![0; (width * height * 4) as usize],
+            width,
+            height,
+        }
+    }
+
+    /// Buffer width
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Buffer height
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Mutable pixels of the buffer
+    ///
+    /// Stored as list of u8, chunks of 4 represent RGBA
+    pub fn pixels_mut(&mut self) -> &mut [u8] {
+        &mut self.pixels
+    }
+
+    /// Overwrite color at pixel (x, y)
+    ///
+    /// Color: RGBA \[0,255\]
+    ///
+    /// Panics if trying to write outside buffer
+    pub fn write_pixel(&mut self, x: u32, y: u32, color: &[u8; 4]) {
+        assert_pixel(x, y, self.width, self.height);
+
+        let index = (y * 4 * self.width + x * 4) as usize;
+        self.pixels[index..index + 4].copy_from_slice(color);
+    }
+}
+
+/// Sprites drawn together with ```draw_batch```
+///
+/// Borrows the sprites until the batch is dropped, reuse it by calling ```clear``` each frame
+#[derive(Debug, Clone, Default)]
+pub struct SpriteBatch<'a> {
+    entries: Vec<BatchEntry<'a>>,
+}
+
+#[derive(Debug, Clone)]
+struct BatchEntry<'a> {
+    sprite: &'a Sprite,
+    x: i32,
+    y: i32,
+    tint: [u8; 4],
+}
+
+impl<'a> SpriteBatch<'a> {
+    /// Create empty batch
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add sprite with top left corner at (x, y)
+    pub fn push(&mut self, sprite: &'a Sprite, x: i32, y: i32) {
+        self.push_tinted(sprite, x, y, &[255, 255, 255, 255]);
+    }
+
+    /// Add sprite with top left corner at (x, y) multiplied by tint, see ```draw_sprite_tinted```
+    ///
+    /// Tint: Non premultiplied RGBA \[0,255\]
+    pub fn push_tinted(&mut self, sprite: &'a Sprite, x: i32, y: i32, tint: &[u8; 4]) {
+        self.entries.push(BatchEntry {
+            sprite,
+            x,
+            y,
+            tint: *tint,
+        });
+    }
+
+    /// Remove all sprites
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Number of sprites in the batch
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the batch has no sprites
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Sequence of sprites shown for a duration each
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    frames: Vec<Sprite>,
+    durations: Vec<f32>,
+    looping: bool,
+    current: usize,
+    timer: f32,
+    finished: bool,
+}
+
+impl Animation {
+    /// Create animation where frame i is shown for durations\[i\] seconds
+    ///
+    /// Panics if there are no frames or the number of durations does not match
+    pub fn new(frames: Vec<Sprite>, durations: Vec<f32>, looping: bool) -> Self {
+        assert!(!frames.is_empty(), "animation must have at least one frame");
+        assert_eq!(
+            frames.len(),
+            durations.len(),
+            "animation has {} frames but {} durations",
+            frames.len(),
+            durations.len()
+        );
+        Self {
+            frames,
+            durations,
+            looping,
+            current: 0,
+            timer: 0.0,
+            finished: false,
+        }
+    }
+
+    /// Create animation where every frame is shown for frame_duration seconds
+    pub fn uniform(frames: Vec<Sprite>, frame_duration: f32, looping: bool) -> Self {
+        let durations = vec![frame_duration; frames.len()];
+        Self::new(frames, durations, looping)
+    }
+
+    /// Create animation from frames paired with their durations
+    ///
+    /// Matches the output of ```media::load_gif```
+    pub fn from_frames(frames: Vec<(Sprite, f32)>, looping: bool) -> Self {
+        let (frames, durations) = frames.into_iter().unzip();
+        Self::new(frames, durations, looping)
+    }
+
+    /// Move the animation forward by dt seconds
+    ///
+    /// One shot animations stop at the last frame
+    pub fn advance(&mut self, dt: f32) {
+        if self.finished {
+            return;
+        }
+        self.timer += dt;
+        while self.timer >= self.durations[self.current] {
+            self.timer -= self.durations[self.current];
+            if self.current + 1 < self.frames.len() {
+                self.current += 1;
+            } else if self.looping {
+                self.current = 0;
+            } else {
+                self.timer = 0.0;
+                self.finished = true;
+                return;
+            }
+            // Guard against frames without duration
+            if self.durations.iter().all(|d| *d <= 0.0) {
+                self.timer = 0.0;
+                return;
+            }
+        }
+    }
+
+    /// Start over from the first frame
+    pub fn reset(&mut self) {
+        self.current = 0;
+        self.timer = 0.0;
+        self.finished = false;
+    }
+
+    /// Index of the frame currently shown
+    pub fn frame_index(&self) -> usize {
+        self.current
+    }
+
+    /// Sprite currently shown
+    pub fn frame(&self) -> &Sprite {
+        &self.frames[self.current]
+    }
+
+    /// Returns true if a one shot animation has played its last frame
+    ///
+    /// Looping animations never finish
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+}
+
+/// Grayscale alpha mask stamped with ```stamp``` and ```stroke```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Brush {
+    alpha: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+impl Brush {
+    /// Create brush from alpha values
+    ///
+    /// Stored as list of u8, one per pixel
+    ///
+    /// Panics if alpha does not contain width * height values
+    pub fn new(width: u32, height: u32, alpha: Vec<u8>) -> Self {
+        assert_eq!(
+            alpha.len(),
+            (width * height) as usize,
+            "brush alpha does not match size {}x{}",
+            width,
+            height
+        );
+        Self {
+            alpha,
+            width,
+            height,
+        }
+    }
+
+    /// Create a round brush with soft edges
+    ///
+    /// Alpha falls off linearly from the center to radius
+    pub fn round(radius: u32) -> Self {
+        let size = 2 * radius + 1;
+        let mut alpha = Vec::with_capacity((size * size) as usize);
+        for y in 0..size {
+            for x in 0..size {
+                let dx = x as f32 - radius as f32;
+                let dy = y as f32 - radius as f32;
+                let distance = (dx * dx + dy * dy).sqrt() / (radius as f32 + 1.0);
+                alpha.push(((1.0 - distance).max(0.0) * 255.0) as u8);
+            }
+        }
+        Self::new(size, size, alpha)
+    }
+
+    /// Brush width
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Brush height
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Alpha values of the brush
+    pub fn alpha(&self) -> &[u8] {
+        &self.alpha
+    }
+}
+
+/// End of a span of len pixels from start, saturating instead of overflowing
+fn span_end(start: i32, len: u32) -> i32 {
+    start.saturating_add(len.min(i32::MAX as u32) as i32)
+}
+
+/// Rect (x, y, w, h) inset from a width x height area by margin_percent of its size on every side
+/// Margins are clamped to [0,50] percent
+fn inset_rect(width: u32, height: u32, margin_percent: f32) -> (u32, u32, u32, u32) {
+    let margin = margin_percent.clamp(0.0, 50.0) / 100.0;
+    let margin_x = ((width as f32 * margin).round() as u32).min(width / 2);
+    let margin_y = ((height as f32 * margin).round() as u32).min(height / 2);
+    (
+        margin_x,
+        margin_y,
+        width - 2 * margin_x,
+        height - 2 * margin_y,
+    )
+}
+
+/// Point (x, y) followed by its distinct copies under symmetry in a width x height area
+/// Copies are mirrored or rotated about the center, rotated copies outside the area are skipped
+fn symmetric_points(
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    symmetry: SymmetryMode,
+) -> impl Iterator<Item = (u32, u32)> {
+    // Bit 0 of a mirrored copy flips x, bit 1 flips y
+    let mirror = move |flips: u32| {
+        let x = if flips & 1 != 0 { width - 1 - x } else { x };
+        let y = if flips & 2 != 0 { height - 1 - y } else { y };
+        Some((x, y))
+    };
+    let rotate = move |i: u32, n: u32| {
+        if i == 0 {
+            return Some((x, y));
+        }
+        let (cx, cy) = ((width - 1) as f32 / 2.0, (height - 1) as f32 / 2.0);
+        let (ox, oy) = (x as f32 - cx, y as f32 - cy);
+        let (sin, cos) = (std::f32::consts::TAU * i as f32 / n as f32).sin_cos();
+        let rx = (cx + ox * cos - oy * sin).round();
+        let ry = (cy + ox * sin + oy * cos).round();
+        (rx >= 0.0 && ry >= 0.0 && rx < width as f32 && ry < height as f32)
+            .then_some((rx as u32, ry as u32))
+    };
+    let copy = move |i: u32| match symmetry {
+        SymmetryMode::None => mirror(0),
+        SymmetryMode::Horizontal => mirror(i),
+        SymmetryMode::Vertical => mirror(i * 2),
+        SymmetryMode::Quad => mirror(i),
+        SymmetryMode::RadialN(n) => rotate(i, n),
+    };
+    let count = match symmetry {
+        SymmetryMode::None => 1,
+        SymmetryMode::Horizontal | SymmetryMode::Vertical => 2,
+        SymmetryMode::Quad => 4,
+        SymmetryMode::RadialN(n) => n.max(1),
+    };
+    (0..count).filter_map(move |i| {
+        let point = copy(i)?;
+        // Points on an axis of symmetry are written once
+        (0..i).all(|j| copy(j) != Some(point)).then_some(point)
+    })
+}
+
+/// Returns buffer of size (width, height) moved right and down by offset, filled where exposed
+/// Each pixel is fill.len() values
+fn scroll_buffer<T: Copy>(
+    buffer: &[T],
+    size: (u32, u32),
+    offset: (i32, i32),
+    fill: &[T],
+) -> Vec<T> {
+    let (width, height) = (size.0 as i32, size.1 as i32);
+    let mut scrolled = fill.repeat((width * height) as usize);
+    let (min_x, max_x) = (offset.0.max(0), (width + offset.0).min(width));
+    if min_x >= max_x {
+        return scrolled;
+    }
+    let index = |x: i32, y: i32| (y * width + x) as usize * fill.len();
+    let len = (max_x - min_x) as usize * fill.len();
+    for y in offset.1.max(0)..(height + offset.1).min(height) {
+        let from = index(min_x - offset.0, y - offset.1);
+        let to = index(min_x, y);
+        scrolled[to..to + len].copy_from_slice(&buffer[from..from + len]);
+    }
+    scrolled
+}
+
+/// Sample coordinates covered by pixel (x, y)
+fn sample_block(x: u32, y: u32, supersample: u32) -> impl Iterator<Item = (u32, u32)> {
+    let (x, y) = (x * supersample, y * supersample);
+    (y..y + supersample).flat_map(move |sy| (x..x + supersample).map(move |sx| (sx, sy)))
+}
+
+/// Box filters RGBA samples of size width x height down by factor along each axis
+fn downsample(pixels: &[u8], width: u32, height: u32, factor: u32) -> Vec<u8> {
+    let (out_width, out_height) = (width / factor, height / factor);
+    let count = factor * factor;
+    let mut out = Vec::with_capacity((out_width * out_height * 4) as usize);
+    for y in 0..out_height {
+        for x in 0..out_width {
+            let mut sum = [0u32; 4];
+            for (sx, sy) in sample_block(x, y, factor) {
+                let index = ((sy * width + sx) * 4) as usize;
+                for (c, total) in sum.iter_mut().enumerate() {
+                    *total += pixels[index + c] as u32;
+                }
+            }
+            out.extend(sum.map(|total| ((total + count / 2) / count) as u8));
+        }
+    }
+    out
+}
+
+/// Asserts a pixel is inside the screen
+fn assert_pixel(x: u32, y: u32, width: u32, height: u32) {
+    debug_assert!(
+        x < width && y < height,
+        "pixel ({}, {}) not in range [{}, {})",
+        x,
+        y,
+        width,
+        height
+    );
+}
+
+/// Asserts color components are in range [0,1]
+fn assert_rgb(color: &[f32; 3]) {
+    debug_assert!(
+        (0.0..=1.0).contains(&color[0]),
+        "r ({}) not in range [0,1]",
+        color[0]
+    );
+    debug_assert!(
+        (0.0..=1.0).contains(&color[1]),
+        "g ({}) not in range [0,1]",
+        color[1]
+    );
+    debug_assert!(
+        (0.0..=1.0).contains(&color[2]),
+        "b ({}) not in range [0,1]",
+        color[2]
+    );
+}
+
+/// Asserts color components are in range [0,1]
+fn assert_rgba(color: &[f32; 4]) {
+    assert_rgb(&[color[0], color[1], color[2]]);
+    debug_assert!(
+        (0.0..=1.0).contains(&color[3]),
+        "a ({}) not in range [0,1]",
+        color[3]
+    );
+}
+
+// Commands
+
+/// Mutable reference to pixel array
+///
+/// Stored as list of u8, chunks of 4 represent RGBA
+pub fn pixels_ref(ctx: &mut Context) -> &mut Vec<u8> {
+    ctx.render.canvas.mark_all_dirty();
+    &mut ctx.render.canvas.pixels
+}
+
+/// Copy of pixel buffer
+///
+/// Stored as list of u8, chunks of 4 represent RGBA
+pub fn pixels_copy(ctx: &Context) -> Vec<u8> {
+    ctx.render.canvas.get_pixel_buffer()
+}
+
+/// Iterate over every pixel as (x, y, RGBA) in row major order
+///
+/// Allows modifying pixels without index math
+pub fn pixels_iter_mut(ctx: &mut Context) -> impl Iterator<Item = (u32, u32, &mut [u8; 4])> {
+    ctx.render.canvas.pixels_iter_mut()
+}
+
+/// Smallest rectangle (x, y, w, h) containing every pixel changed since ```clear_dirty```
+///
+/// None if nothing changed, the whole canvas on startup and after resizing or ```pixels_ref```
+pub fn dirty_rect(ctx: &Context) -> Option<(u32, u32, u32, u32)> {
+    ctx.render.canvas.user_dirty()
+}
+
+/// Reset the rectangle reported by ```dirty_rect```
+///
+/// Only affects ```dirty_rect```, changed pixels are still uploaded
+pub fn clear_dirty(ctx: &mut Context) {
+    ctx.render.canvas.clear_user_dirty();
+}
+
+/// Mark the pixels in rect (x, y, w, h) as changed, clipped to the canvas
+///
+/// Use after modifying pixels in a way the canvas can not track, they are uploaded next frame
+pub fn mark_dirty(ctx: &mut Context, rect: (u32, u32, u32, u32)) {
+    let (x, y, w, h) = rect;
+    ctx.render.canvas.mark_pixels_dirty(x, y, w, h);
+}
+
+/// Write color to pixel at (x, y) offset by the current transform
+///
+/// Color: Full opacity RGB \[0,255\]
+///
+/// Panics if trying to write outside canvas
+pub fn write_pixel_rgb(ctx: &mut Context, x: u32, y: u32, color: &[u8; 3]) {
+    let canvas = &mut ctx.render.canvas;
+    let (x, y) = canvas.translate_pixel(x, y);
+    for (x, y) in sample_block(x, y, canvas.supersample) {
+        canvas.write_pixel(x, y, color);
+    }
+}
+
+/// Write color to pixel at (x, y) offset by the current transform
+///
+/// Color: Full opacity RGB \[0,1\]
+///
+/// Panics if trying to write outside canvas
+pub fn write_pixel_rgb_f32(ctx: &mut Context, x: u32, y: u32, color: &[f32; 3]) {
+    let canvas = &mut ctx.render.canvas;
+    let (x, y) = canvas.translate_pixel(x, y);
+    for (x, y) in sample_block(x, y, canvas.supersample) {
+        canvas.write_pixel_f32(x, y, color);
+    }
+}
+
+/// Write color to pixel at (x, y) offset by the current transform
+///
+/// Color: Non premultiplied RGBA \[0,255\]
+///
+/// Panics if trying to write outside canvas
+pub fn write_pixel_rgba(ctx: &mut Context, x: u32, y: u32, color: &[u8; 4]) {
+    let canvas = &mut ctx.render.canvas;
+    let (x, y) = canvas.translate_pixel(x, y);
+    for (x, y) in sample_block(x, y, canvas.supersample) {
+        canvas.write_pixel_blend(x, y, color);
+    }
+}
+
+/// Write color to pixel at (x, y) offset by the current transform
+///
+/// Color: Non premultiplied RGBA \[0,1\]
+///
+/// Panics if trying to write outside canvas
+pub fn write_pixel_rgba_f32(ctx: &mut Context, x: u32, y: u32, color: &[f32; 4]) {
+    let canvas = &mut ctx.render.canvas;
+    let (x, y) = canvas.translate_pixel(x, y);
+    for (x, y) in sample_block(x, y, canvas.supersample) {
+        canvas.write_pixel_blend_f32(x, y, color);
+    }
+}
+
+/// Write color to pixel at (x, y) offset by the current transform if depth is nearer than the stored depth
+///
+/// Smaller depth is nearer, the depth buffer is independent of draw order
+///
+/// Color: Non premultiplied RGBA \[0,255\]
+///
+/// Panics if trying to write outside canvas
+pub fn write_pixel_depth(ctx: &mut Context, x: u32, y: u32, color: &[u8; 4], depth: f32) {
+    let canvas = &mut ctx.render.canvas;
+    let (x, y) = canvas.translate_pixel(x, y);
+    for (x, y) in sample_block(x, y, canvas.supersample) {
+        canvas.write_pixel_depth(x, y, color, depth);
+    }
+}
+
+/// Reset the depth buffer so the next depth tested writes always pass
+///
+/// Pixels are left unchanged
+pub fn clear_depth(ctx: &mut Context) {
+    ctx.render.canvas.clear_depth();
+}
+
+/// Overwrite all pixels with color and reset the depth buffer to depth
+///
+/// Color: Non premultiplied RGBA \[0,255\], alpha is written as is
+///
+/// Use f32::INFINITY so the next depth tested writes always pass, the same as ```clear_depth```
+///
+/// Without depth tested writes this is a clear to color
+pub fn clear_all(ctx: &mut Context, color: &[u8; 4], depth: f32) {
+    ctx.render.canvas.clear_all(color, depth);
+}
+
+/// Write color to pixel at normalized coordinates (u, v) offset by the current transform
+///
+/// (0, 0) is the top left pixel and (1, 1) the bottom right, pixels moved outside the canvas are skipped
+///
+/// Color: Full opacity RGB \[0,255\]
+pub fn write_pixel_norm(ctx: &mut Context, u: f32, v: f32, color: &[u8; 3]) {
+    let canvas = &mut ctx.render.canvas;
+    let (x, y) = canvas.to_pixel(u, v);
+    let (x, y) = canvas.translate_pixel(x, y);
+    let (width, height) = canvas.logical_size();
+    if x >= width || y >= height {
+        return;
+    }
+    for (x, y) in sample_block(x, y, canvas.supersample) {
+        canvas.write_pixel(x, y, color);
+    }
+}
+
+/// Map normalized coordinates (u, v) to a pixel
+///
+/// Rounds to the nearest pixel and clamps to the canvas
+pub fn to_pixel(ctx: &Context, u: f32, v: f32) -> (u32, u32) {
+    ctx.render.canvas.to_pixel(u, v)
+}
+
+/// Color at pixel (x, y)
+///
+/// Color: RGB \[0,255\]
+///
+/// Panics if trying to access outside canvas
+pub fn pixel_rgb(ctx: &Context, x: u32, y: u32) -> [u8; 3] {
+    ctx.render.canvas.pixel_rgb(x, y)
+}
+
+/// Color at pixel (x, y)
+///
+/// Color: RGB \[0,1\]
+///
+/// Panics if trying to access outside canvas
+pub fn pixel_rgb_f32(ctx: &Context, x: u32, y: u32) -> [f32; 3] {
+    ctx.render.canvas.pixel_rgb_f32(x, y)
+}
+
+/// Color at pixel (x, y)
+///
+/// Color: RGBA \[0,255\]
+///
+/// Panics if trying to access outside canvas
+pub fn pixel_rgba(ctx: &Context, x: u32, y: u32) -> [u8; 4] {
+    ctx.render.canvas.resolved_rgba(x, y)
+}
+
+/// Color at pixel (x, y)
+///
+/// Color: RGBA \[0,1\]
+///
+/// Panics if trying to access outside canvas
+pub fn pixel_rgba_f32(ctx: &Context, x: u32, y: u32) -> [f32; 4] {
+    ctx.render.canvas.pixel_rgba_f32(x, y)
+}
+
+/// Resizes the canvas
+///
+/// Clears screen to clear color
+///
+/// ```Callbacks::on_canvas_resize``` is called once the current callback returns
+///
+/// Ignored with ```Config::logical_resolution```
+///
+/// Ignored if the size exceeds the max texture dimension, see ```try_resize```
+pub fn resize(ctx: &mut Context, width: u32, height: u32) {
+    resize_with(ctx, width, height, ResizeMode::Clear);
+}
+
+/// Resizes the canvas
+///
+/// ```ResizeMode::Preserve``` keeps the current pixels and fills the new area with the resize fill color
+///
+/// ```Callbacks::on_canvas_resize``` is called once the current callback returns
+///
+/// Ignored with ```Config::logical_resolution```
+///
+/// Ignored if the size exceeds the max texture dimension, see ```try_resize```
+pub fn resize_with(ctx: &mut Context, width: u32, height: u32, mode: ResizeMode) {
+    if ctx.render.logical_scaling.is_some() {
+        log::warn!("canvas size is locked by Config::logical_resolution, resize ignored");
+        return;
+    }
+    let max = ctx.render.device.limits().max_texture_dimension_2d;
+    if let Err(err) = check_texture_size(width, height, max) {
+        log::warn!("{}, resize ignored", err);
+        return;
+    }
+    ctx.render.resize_canvas(width, height, mode);
+}
+
+/// Resizes the canvas if the GPU supports textures of that size
+///
+/// Clears screen to clear color, returns an error and leaves the canvas untouched otherwise
+pub fn try_resize(ctx: &mut Context, width: u32, height: u32) -> Result<(), ResizeError> {
+    if ctx.render.logical_scaling.is_some() {
+        return Err(ResizeError::Locked);
+    }
+    let max = ctx.render.device.limits().max_texture_dimension_2d;
+    check_texture_size(width, height, max)?;
+    resize(ctx, width, height);
+    Ok(())
+}
+
+/// Set how the color channels of blended colors are interpreted
+///
+/// Affects ```write_pixel_rgba```, sprites and all other blending draws
+///
+/// Straight (default) treats colors as independent of alpha, Premultiplied expects colors
+/// already multiplied by alpha, as in many composited or exported images
+pub fn set_alpha_mode(ctx: &mut Context, alpha_mode: AlphaMode) {
+    ctx.render.canvas.set_alpha_mode(alpha_mode);
+}
+
+/// Set the color of pixels exposed by ```resize_with``` in ```ResizeMode::Preserve```
+///
+/// Color: RGBA \[0,255\], default is transparent black
+pub fn set_resize_fill(ctx: &mut Context, color: &[u8; 4]) {
+    ctx.render.canvas.set_resize_fill(color);
+}
+
+/// Set canvas clear color
+///
+/// Color: RGB \[0,255\]
+pub fn set_clear_color(ctx: &mut Context, color: &[u8; 3]) {
+    ctx.render.canvas.set_clear_color(color);
+}
 
 /// Set canvas clear color
 ///
-/// Color: RGB \[0,1\]
-pub fn set_clear_color_f32(ctx: &mut Context, color: &[f32; 3]) {
-    ctx.render.canvas.set_clear_color_f32(color);
+/// Color: RGB \[0,1\]
+pub fn set_clear_color_f32(ctx: &mut Context, color: &[f32; 3]) {
+    ctx.render.canvas.set_clear_color_f32(color);
+}
+
+/// Clears all pixels to clear color
+pub fn clear_screen(ctx: &mut Context) {
+    ctx.render.canvas.clear_screen();
+}
+
+/// Clears all pixels to sprite tiled from the top left corner
+///
+/// Pixels are overwritten, not blended, and the current transform is ignored
+///
+/// A 1x1 sprite clears to a solid color
+pub fn clear_with_pattern(ctx: &mut Context, sprite: &Sprite) {
+    ctx.render.canvas.clear_with_pattern(sprite);
+}
+
+/// Replace every pixel where all channels are within tolerance of from with to
+///
+/// Tolerance 0 only replaces exact matches
+///
+/// Color: RGBA \[0,255\]
+pub fn replace_color(ctx: &mut Context, from: &[u8; 4], to: &[u8; 4], tolerance: u8) {
+    ctx.render.canvas.replace_color(from, to, tolerance);
+}
+
+/// Save a copy of the canvas
+///
+/// Can be restored later with ```restore_state```
+pub fn save_state(ctx: &Context) -> CanvasState {
+    ctx.render.canvas.save_state()
+}
+
+/// Restore the canvas from a saved copy
+///
+/// Resizes the canvas if the size differs
+///
+/// Ignored with a warning if the size differs and is locked by ```Config::logical_resolution```, see ```try_restore_state```
+pub fn restore_state(ctx: &mut Context, state: &CanvasState) {
+    if let Err(err) = try_restore_state(ctx, state) {
+        log::warn!("{}, restore ignored", err);
+    }
+}
+
+/// Restore the canvas from a saved copy, resizing it if the size differs
+///
+/// Returns an error and leaves the canvas untouched if the canvas can not be resized
+pub fn try_restore_state(ctx: &mut Context, state: &CanvasState) -> Result<(), ResizeError> {
+    if (state.width, state.height) != ctx.render.canvas.logical_size() {
+        try_resize(ctx, state.width, state.height)?;
+    }
+    ctx.render.canvas.restore_state(state);
+    Ok(())
+}
+
+/// Convert every pixel to grayscale using luminance weights
+///
+/// Alpha is left intact
+pub fn apply_grayscale(ctx: &mut Context) {
+    ctx.render.canvas.apply_grayscale();
+}
+
+/// Multiply every pixel with a color matrix
+///
+/// Each output channel is the dot product of a matrix row and the pixel (r,g,b,a) in range \[0,1\]
+///
+/// Results are clamped to \[0,1\]
+///
+/// The alpha column can be used as a constant for opaque pixels, e.g. invert red with [-1, 0, 0, 1]
+pub fn apply_color_matrix(ctx: &mut Context, matrix: &[[f32; 4]; 4]) {
+    ctx.render.canvas.apply_color_matrix(matrix);
+}
+
+/// Offset all following drawing by (dx, dy)
+///
+/// Nested transforms accumulate, undo with ```pop_transform```
+///
+/// Applies to writing pixels, shapes, text and brushes but not to reading pixels
+pub fn push_transform(ctx: &mut Context, dx: i32, dy: i32) {
+    ctx.render.canvas.push_transform(dx, dy);
+}
+
+/// Restore the transform before the last ```push_transform```
+///
+/// Does nothing if no transform has been pushed
+pub fn pop_transform(ctx: &mut Context) {
+    ctx.render.canvas.pop_transform();
+}
+
+/// Mirror or rotate everything drawn about the canvas center
+///
+/// Every pixel write also writes its copies, copies blend like the original
+///
+/// Filters, clears and ```apply_buffer``` are not mirrored
+pub fn set_symmetry(ctx: &mut Context, symmetry: SymmetryMode) {
+    ctx.render.canvas.set_symmetry(symmetry);
+}
+
+/// Rect (x, y, w, h) inset by margin_percent of the canvas size on every side
+///
+/// Content inside stays visible on displays with overscan, margins are clamped to \[0,50\] percent
+pub fn safe_rect(ctx: &Context, margin_percent: f32) -> (u32, u32, u32, u32) {
+    let (width, height) = ctx.render.canvas.logical_size();
+    inset_rect(width, height, margin_percent)
+}
+
+/// Draw a one pixel wide outline of ```safe_rect```
+///
+/// Color: Non premultiplied RGBA \[0,255\]
+///
+/// Always drawn in screen space, ignores the current transform
+pub fn draw_safe_area(ctx: &mut Context, margin_percent: f32, color: &[u8; 4]) {
+    ctx.render.canvas.draw_safe_area(margin_percent, color);
+}
+
+/// Sets a single channel of all pixels to value
+///
+/// Other channels are left intact
+pub fn clear_channel(ctx: &mut Context, channel: Channel, value: u8) {
+    ctx.render.canvas.clear_channel(channel, value);
+}
+
+/// Blend color modulated by the brush alpha, centered at (x, y)
+///
+/// Color: Non premultiplied RGBA \[0,255\]
+///
+/// Pixels outside the canvas are skipped
+pub fn stamp(ctx: &mut Context, brush: &Brush, x: i32, y: i32, color: &[u8; 4]) {
+    ctx.render.canvas.stamp(brush, x, y, color);
+}
+
+/// Stamp the brush along a path of points every spacing pixels
+///
+/// Color: Non premultiplied RGBA \[0,255\]
+///
+/// Panics if spacing is 0
+pub fn stroke(
+    ctx: &mut Context,
+    brush: &Brush,
+    points: &[(i32, i32)],
+    color: &[u8; 4],
+    spacing: u32,
+) {
+    ctx.render.canvas.stroke(brush, points, color, spacing);
+}
+
+/// Create a transparent width x height canvas which is never shown
+///
+/// Draw to it with the ```*_on``` functions and blend it onto the canvas with ```composite```
+pub fn create_offscreen(ctx: &mut Context, width: u32, height: u32) -> CanvasId {
+    ctx.render.offscreens.push(Canvas::new(width, height));
+    CanvasId(ctx.render.offscreens.len() - 1)
+}
+
+/// Blend offscreen canvas src with top left corner at (x, y)
+///
+/// Pixels outside the canvas are skipped
+pub fn composite(ctx: &mut Context, src: CanvasId, x: i32, y: i32) {
+    ctx.render
+        .canvas
+        .composite(&ctx.render.offscreens[src.0], x, y);
+}
+
+/// Width of offscreen canvas
+pub fn offscreen_width(ctx: &Context, id: CanvasId) -> u32 {
+    ctx.render.offscreens[id.0].width
+}
+
+/// Height of offscreen canvas
+pub fn offscreen_height(ctx: &Context, id: CanvasId) -> u32 {
+    ctx.render.offscreens[id.0].height
+}
+
+/// Set every pixel of offscreen canvas to color
+///
+/// Color: Non premultiplied RGBA \[0,255\], use alpha 0 for a transparent canvas
+pub fn clear_on(ctx: &mut Context, id: CanvasId, color: &[u8; 4]) {
+    let offscreen = &mut ctx.render.offscreens[id.0];
+    offscreen.pixels = color.repeat(offscreen.pixels.len() / 4);
+    offscreen.mark_all_dirty();
+}
+
+/// Blend color at (x, y) of offscreen canvas
+///
+/// Color: Non premultiplied RGBA \[0,255\]
+///
+/// Pixels outside the offscreen canvas are skipped
+pub fn write_pixel_on(ctx: &mut Context, id: CanvasId, x: i32, y: i32, color: &[u8; 4]) {
+    ctx.render.offscreens[id.0].plot(x, y, color);
+}
+
+/// Blend a filled rectangle with top left corner at (x, y) onto offscreen canvas
+///
+/// Color: Non premultiplied RGBA \[0,255\]
+///
+/// Pixels outside the offscreen canvas are skipped
+pub fn fill_rect_on(
+    ctx: &mut Context,
+    id: CanvasId,
+    x: i32,
+    y: i32,
+    w: u32,
+    h: u32,
+    color: &[u8; 4],
+) {
+    ctx.render.offscreens[id.0].fill_rect(x, y, w, h, color);
+}
+
+/// Blend sprite with top left corner at (x, y) onto offscreen canvas
+///
+/// Pixels outside the offscreen canvas are skipped
+pub fn draw_sprite_on(ctx: &mut Context, id: CanvasId, sprite: &Sprite, x: i32, y: i32) {
+    ctx.render.offscreens[id.0].draw_sprite(sprite, x, y);
+}
+
+/// Draw text with top left corner at (x, y) onto offscreen canvas
+///
+/// Pixels outside the offscreen canvas are skipped
+pub fn draw_text_on(ctx: &mut Context, id: CanvasId, text: &str, x: i32, y: i32, color: &[u8; 4]) {
+    ctx.render.offscreens[id.0].draw_text(text, x, y, color);
+}
+
+/// Copy the canvas rectangle src onto the rectangle dst, both as (x, y, w, h)
+///
+/// Nearest neighbor sampling scales src to fit dst, useful for minimaps and thumbnails
+///
+/// Pixels are overwritten, overlapping rectangles read the pixels from before the copy
+///
+/// Pixels outside the canvas are skipped
+pub fn blit_scaled(ctx: &mut Context, src: (i32, i32, u32, u32), dst: (i32, i32, u32, u32)) {
+    ctx.render.canvas.blit_scaled(src, dst);
+}
+
+/// Box blur the rectangle with top left corner at (x, y)
+///
+/// Blurs horizontally then vertically, radius 0 does nothing
+///
+/// Samples outside the rectangle are clamped to its edge, pixels outside the canvas are skipped
+pub fn blur(ctx: &mut Context, x: i32, y: i32, w: u32, h: u32, radius: u32) {
+    ctx.render.canvas.blur(x, y, w, h, radius);
+}
+
+/// Draw the edges of shapes on the canvas in color
+///
+/// A pixel is an edge if any channel differs from a horizontal or vertical neighbour by more than threshold,
+/// lower thresholds find softer edges
+///
+/// Color: Non premultiplied RGBA \[0,255\]
+///
+/// The rest of the canvas is cleared to transparent unless keep_original
+pub fn outline(ctx: &mut Context, threshold: u8, color: &[u8; 4], keep_original: bool) {
+    ctx.render.canvas.outline(threshold, color, keep_original);
+}
+
+/// Load a TrueType or OpenType font
+///
+/// Returns a handle used when drawing text
+#[cfg(feature = "text")]
+pub fn load_font(ctx: &mut Context, bytes: &[u8]) -> Result<FontId, &'static str> {
+    ctx.render.canvas.load_font(bytes)
+}
+
+/// Draw antialiased text with a loaded font
+///
+/// (x, y) is the top left corner of the text
+///
+/// Color: Non premultiplied RGBA \[0,255\]
+///
+/// Text outside the canvas is clipped
+#[cfg(feature = "text")]
+pub fn draw_text_ttf(
+    ctx: &mut Context,
+    font_id: FontId,
+    text: &str,
+    x: i32,
+    y: i32,
+    px_size: f32,
+    color: &[u8; 4],
+) {
+    ctx.render
+        .canvas
+        .draw_text_ttf(font_id, text, x, y, px_size, color);
+}
+
+/// Draw one pixel wide grid lines every spacing pixels
+///
+/// Lines pass through origin, move it along with a camera to keep the grid aligned
+///
+/// Spacing 0 or 1 draws nothing since it would cover the whole canvas
+///
+/// Color: Non premultiplied RGBA \[0,255\]
+pub fn draw_grid(ctx: &mut Context, spacing: u32, origin: (i32, i32), color: &[u8; 4]) {
+    ctx.render.canvas.draw_grid(spacing, origin, color);
+}
+
+/// Blend a filled rectangle with top left corner at (x, y)
+///
+/// Color: Non premultiplied RGBA \[0,255\]
+///
+/// Pixels outside the canvas are skipped
+pub fn fill_rect(ctx: &mut Context, x: i32, y: i32, w: u32, h: u32, color: &[u8; 4]) {
+    ctx.render.canvas.fill_rect(x, y, w, h, color);
+}
+
+/// Blend a one pixel wide line from (x0, y0) to (x1, y1), both ends included
+///
+/// Color: Non premultiplied RGBA \[0,255\]
+///
+/// Edges are smoothed with ```Config::supersample```
+///
+/// Pixels outside the canvas are skipped
+pub fn draw_line(ctx: &mut Context, x0: i32, y0: i32, x1: i32, y1: i32, color: &[u8; 4]) {
+    ctx.render.canvas.draw_line(x0, y0, x1, y1, color);
+}
+
+/// Fill a rectangle with top left corner at (x, y) using the depth buffer
+///
+/// Only pixels nearer than the stored depth are drawn, see ```write_pixel_depth```
+///
+/// Pixels outside the canvas are skipped
+pub fn fill_rect_depth(
+    ctx: &mut Context,
+    x: i32,
+    y: i32,
+    w: u32,
+    h: u32,
+    color: &[u8; 4],
+    depth: f32,
+) {
+    ctx.render.canvas.fill_rect_depth(x, y, w, h, color, depth);
+}
+
+/// Draw a pixel at every point
+///
+/// Color: Non premultiplied RGBA \[0,255\]
+///
+/// Points outside the canvas are skipped
+pub fn draw_points(ctx: &mut Context, points: &[(i32, i32)], color: &[u8; 4]) {
+    ctx.render.canvas.draw_points(points, color);
+}
+
+/// Draw a pixel at every point with its own color
+///
+/// Colors: Non premultiplied RGBA \[0,255\]
+///
+/// Points outside the canvas are skipped
+pub fn draw_points_colored(ctx: &mut Context, points: &[((i32, i32), [u8; 4])]) {
+    ctx.render.canvas.draw_points_colored(points);
+}
+
+/// Draw an antialiased circle outline centered at (cx, cy)
+///
+/// Color: Non premultiplied RGBA \[0,255\], alpha is scaled by the edge coverage
+///
+/// Pixels outside the canvas are skipped
+pub fn draw_circle_aa(ctx: &mut Context, cx: i32, cy: i32, radius: u32, color: &[u8; 4]) {
+    ctx.render.canvas.draw_circle_aa(cx, cy, radius, color);
+}
+
+/// Draw an antialiased filled circle centered at (cx, cy)
+///
+/// Color: Non premultiplied RGBA \[0,255\], alpha is scaled by the edge coverage
+///
+/// Pixels outside the canvas are skipped
+pub fn fill_circle_aa(ctx: &mut Context, cx: i32, cy: i32, radius: u32, color: &[u8; 4]) {
+    ctx.render.canvas.fill_circle_aa(cx, cy, radius, color);
+}
+
+/// Copy a buffer onto the canvas with top left corner at (x, y), follows the current transform
+///
+/// Pixels are overwritten, not blended
+///
+/// Pixels outside the canvas are skipped
+pub fn apply_buffer(ctx: &mut Context, buffer: &PixelBuffer, x: i32, y: i32) {
+    ctx.render.canvas.apply_buffer(buffer, x, y);
+}
+
+/// Draw a sprite with top left corner at (x, y)
+///
+/// Sprite pixels are alpha blended
+///
+/// Pixels outside the canvas are skipped
+pub fn draw_sprite(ctx: &mut Context, sprite: &Sprite, x: i32, y: i32) {
+    ctx.render.canvas.draw_sprite(sprite, x, y);
+}
+
+/// Draw a sprite with top left corner at (x, y) multiplied by tint
+///
+/// A white sprite becomes the tint color, tint alpha scales the sprite alpha
+///
+/// Tint: Non premultiplied RGBA \[0,255\], opaque white draws the sprite unchanged
+///
+/// Pixels outside the canvas are skipped
+pub fn draw_sprite_tinted(ctx: &mut Context, sprite: &Sprite, x: i32, y: i32, tint: &[u8; 4]) {
+    ctx.render.canvas.draw_sprite_tinted(sprite, x, y, tint);
+}
+
+/// Draw a sprite with top left corner at (x, y) using the depth buffer
+///
+/// Only pixels nearer than the stored depth are drawn, see ```write_pixel_depth```
+///
+/// Pixels outside the canvas are skipped
+pub fn draw_sprite_depth(ctx: &mut Context, sprite: &Sprite, x: i32, y: i32, depth: f32) {
+    ctx.render.canvas.draw_sprite_depth(sprite, x, y, depth);
+}
+
+/// Draw all sprites of a batch in the order they were pushed
+///
+/// Same result as drawing each sprite with ```draw_sprite``` or ```draw_sprite_tinted```
+///
+/// Pixels outside the canvas are skipped
+pub fn draw_batch(ctx: &mut Context, batch: &SpriteBatch) {
+    ctx.render.canvas.draw_batch(batch);
+}
+
+/// Draw the current frame of an animation with top left corner at (x, y)
+///
+/// Sprite pixels are alpha blended
+///
+/// Pixels outside the canvas are skipped
+pub fn draw_animation(ctx: &mut Context, animation: &Animation, x: i32, y: i32) {
+    ctx.render.canvas.draw_sprite(animation.frame(), x, y);
+}
+
+/// Draw a sprite scaled to w x h using nine slice scaling
+///
+/// Border is (left, top, right, bottom) in sprite pixels, these parts are never scaled
+///
+/// Corners are drawn 1:1, edges are stretched along one axis and the center along both
+///
+/// Panics if the border does not fit inside the sprite
+#[allow(clippy::too_many_arguments)]
+pub fn draw_nine_slice(
+    ctx: &mut Context,
+    sprite: &Sprite,
+    border: (u32, u32, u32, u32),
+    x: i32,
+    y: i32,
+    w: u32,
+    h: u32,
+) {
+    ctx.render
+        .canvas
+        .draw_nine_slice(sprite, border, x, y, w, h);
+}
+
+/// Draw text with the built in 5x7 bitmap font
+///
+/// (x, y) is the top left corner of the text, '\n' starts a new line
+///
+/// Color: Non premultiplied RGBA \[0,255\]
+///
+/// Pixels outside the canvas are skipped
+pub fn draw_text(ctx: &mut Context, text: &str, x: i32, y: i32, color: &[u8; 4]) {
+    ctx.render.canvas.draw_text(text, x, y, color);
+}
+
+/// Draw text at (x, y) wrapped to lines no wider than max_width
+///
+/// Lines break at spaces, words wider than max_width are broken between characters
+///
+/// Returns the height in pixels of the drawn lines, useful for sizing a background
+///
+/// Color: Non premultiplied RGBA \[0,255\]
+pub fn draw_text_wrapped(
+    ctx: &mut Context,
+    text: &str,
+    x: i32,
+    y: i32,
+    max_width: u32,
+    color: &[u8; 4],
+) -> u32 {
+    ctx.render
+        .canvas
+        .draw_text_wrapped(text, x, y, max_width, color)
+}
+
+/// Draw text on a background rectangle
+///
+/// The background covers the text plus padding on all sides, a transparent bg skips it
+///
+/// Colors: Non premultiplied RGBA \[0,255\]
+pub fn draw_label(
+    ctx: &mut Context,
+    text: &str,
+    x: i32,
+    y: i32,
+    fg: &[u8; 4],
+    bg: &[u8; 4],
+    padding: u32,
+) {
+    ctx.render.canvas.draw_label(text, x, y, fg, bg, padding);
+}
+
+/// Enable/Disable the debug overlay
+///
+/// When enabled the overlay is drawn after each update
+pub fn set_debug_overlay(ctx: &mut Context, enabled: bool) {
+    ctx.render.canvas.debug_overlay = enabled;
+}
+
+/// Draw fps, frame time, frame count, mouse position and pressed keys
+///
+/// Drawn in the top left corner of the canvas
+pub fn draw_debug_overlay(ctx: &mut Context) {
+    let (mouse_x, mouse_y) = input::mouse_pos_pixel(ctx);
+    let mut keys = ctx
+        .input
+        .keyboard
+        .pressed_keys()
+        .map(|key| format!("{:?}", key))
+        .collect::<Vec<_>>();
+    keys.sort();
+
+    let text = format!(
+        "FPS: {:.0}\nFrame: {:.2} ms\nFrame count: {}\nMouse: {}, {}\nKeys: {}",
+        time::fps(ctx),
+        time::raw_delta(ctx) * 1000.0,
+        time::frame_count(ctx),
+        mouse_x,
+        mouse_y,
+        keys.join(" "),
+    );
+    ctx.render.canvas.draw_debug_overlay(&text);
+}
+
+/// Width in pixels of text drawn with ```draw_text```
+pub fn text_width(text: &str) -> u32 {
+    font::text_width(text)
+}
+
+/// Height in pixels of text drawn with ```draw_text```
+pub fn text_height(text: &str) -> u32 {
+    font::text_height(text)
+}
+
+/// Returns true if the sprites overlap
+///
+/// Sprites collide when a pixel with non zero alpha in both sprites share the same position
+///
+/// Positions are the top left corners of the sprites
+pub fn sprites_collide(a: &Sprite, a_pos: (i32, i32), b: &Sprite, b_pos: (i32, i32)) -> bool {
+    // Bounding box intersection
+    let min_x = a_pos.0.max(b_pos.0);
+    let min_y = a_pos.1.max(b_pos.1);
+    let max_x = span_end(a_pos.0, a.width).min(span_end(b_pos.0, b.width));
+    let max_y = span_end(a_pos.1, a.height).min(span_end(b_pos.1, b.height));
+    if min_x >= max_x || min_y >= max_y {
+        return false;
+    }
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let a_alpha = a.pixel_rgba((x - a_pos.0) as u32, (y - a_pos.1) as u32)[3];
+            let b_alpha = b.pixel_rgba((x - b_pos.0) as u32, (y - b_pos.1) as u32)[3];
+            if a_alpha > 0 && b_alpha > 0 {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+/// Fast hash of the canvas size and pixels
+///
+/// Equal canvases have equal hashes, not suitable for security purposes
+pub fn content_hash(ctx: &Context) -> u64 {
+    ctx.render.canvas.content_hash()
+}
+
+/// Count how many pixels have each distinct color (r,g,b,a)
+pub fn color_histogram(ctx: &Context) -> HashMap<[u8; 4], u32> {
+    ctx.render.canvas.color_histogram()
+}
+
+/// Returns the n most common colors (r,g,b,a) with their pixel counts, most common first
+pub fn dominant_colors(ctx: &Context, n: usize) -> Vec<([u8; 4], u32)> {
+    ctx.render.canvas.dominant_colors(n)
+}
+
+/// Canvas width
+pub fn width(ctx: &Context) -> u32 {
+    ctx.render.canvas.logical_size().0
+}
+
+/// Canvas height
+pub fn height(ctx: &Context) -> u32 {
+    ctx.render.canvas.logical_size().1
 }
 
-/// Clears all pixels to clear color
-pub fn clear_screen(ctx: &mut Context) {
-    ctx.render.canvas.clear_screen();
-}
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_texture_size, clear_on, create_offscreen, inset_rect, push_transform, resize,
+        sprites_collide, tint_color, try_resize, write_pixel_norm, AlphaMode, Animation, Brush,
+        Canvas, CanvasFormat, Channel, DirtyRegion, PixelBuffer, ResizeError, ResizeMode, Sprite,
+        SpriteBatch, SymmetryMode,
+    };
+    use crate::{
+        app::{headless_canvas, headless_context},
+        media,
+        render::{self, Layout, RenderError},
+        Config,
+    };
+
+    #[test]
+    #[should_panic]
+    fn test_get_outside_canvas_panics() {
+        let canvas = Canvas::new(256, 256);
+        canvas.pixel_rgb(256, 10);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_write_outside_canvas_panics() {
+        let mut canvas = Canvas::new(256, 256);
+        canvas.write_pixel(256, 10, &[255, 255, 255]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_rgba() {
+        let mut canvas = Canvas::new(256, 256);
+        canvas.write_pixel_f32(256, 10, &[1.0, 1.2, 0.0]);
+    }
+
+    #[test]
+    fn test_write_then_get_pixel() {
+        let color = [255, 0, 255];
+        let mut canvas = Canvas::new(256, 256);
+        canvas.write_pixel(11, 10, &color);
+
+        let output = canvas.pixel_rgb(11, 10);
+
+        assert_eq!(color, output);
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut canvas = Canvas::new(256, 256);
+        canvas.write_pixel(255, 200, &[255, 255, 255]);
+        canvas.pixel_rgb(255, 200);
+
+        canvas.resize(512, 512);
+        canvas.write_pixel(500, 230, &[255, 255, 255]);
+        canvas.pixel_rgb(500, 230);
+    }
+
+    #[test]
+    fn test_resize_preserve_fill() {
+        let mut canvas = Canvas::new(2, 2);
+        canvas.write_pixel(1, 1, &[255, 0, 0]);
+        canvas.set_resize_fill(&[0, 0, 255, 255]);
+
+        canvas.resize_with(4, 3, ResizeMode::Preserve);
+
+        assert_eq!((canvas.width, canvas.height), (4, 3));
+        assert_eq!(canvas.pixel_rgba(1, 1), [255, 0, 0, 255]);
+        assert_eq!(canvas.pixel_rgba(3, 0), [0, 0, 255, 255]);
+        assert_eq!(canvas.pixel_rgba(0, 2), [0, 0, 255, 255]);
+        assert_eq!(canvas.take_resized(), Some((4, 3)));
+
+        canvas.resize_with(1, 1, ResizeMode::Preserve);
+
+        assert_eq!(canvas.pixels.len(), 4);
+    }
+
+    #[test]
+    fn test_check_texture_size() {
+        assert_eq!(check_texture_size(8192, 100, 8192), Ok(()));
+        assert_eq!(
+            check_texture_size(20000, 20000, 8192),
+            Err(ResizeError::TooLarge {
+                width: 20000,
+                height: 20000,
+                max: 8192
+            })
+        );
+    }
+
+    #[test]
+    fn test_resize_notification() {
+        let mut canvas = Canvas::new(256, 256);
+
+        assert_eq!(canvas.take_resized(), None);
+
+        canvas.resize(100, 50);
+
+        assert_eq!(canvas.take_resized(), Some((100, 50)));
+        assert_eq!(canvas.take_resized(), None);
+    }
+
+    #[test]
+    fn test_to_pixel() {
+        let canvas = Canvas::new(256, 256);
+
+        assert_eq!(canvas.to_pixel(0.5, 0.5), (128, 128));
+        assert_eq!(canvas.to_pixel(0.0, 0.0), (0, 0));
+        assert_eq!(canvas.to_pixel(1.0, 1.0), (255, 255));
+        assert_eq!(canvas.to_pixel(-1.0, 2.0), (0, 255));
+
+        // Empty canvases map everything to the origin instead of underflowing
+        let canvas = Canvas::new(0, 0);
+        assert_eq!(canvas.to_pixel(1.0, 1.0), (0, 0));
+    }
+
+    #[test]
+    fn test_canvas_format_texture_format() {
+        let config = Config {
+            canvas_width: 4,
+            canvas_height: 4,
+            canvas_format: CanvasFormat::Linear,
+            ..Default::default()
+        };
+        // Skip on machines without any adapter
+        let Some(mut ctx) = headless_context(&config, 4, 4) else {
+            return;
+        };
+        assert_eq!(ctx.render.texture.format(), wgpu::TextureFormat::Rgba8Unorm);
+
+        // Recreated textures keep the format
+        resize(&mut ctx, 8, 8);
+        assert_eq!(ctx.render.texture.format(), wgpu::TextureFormat::Rgba8Unorm);
+    }
+
+    #[test]
+    fn test_clear_channel() {
+        let mut canvas = Canvas::new(4, 4);
+        canvas.write_pixel(1, 2, &[10, 20, 30]);
+
+        canvas.clear_channel(Channel::A, 0);
+
+        assert_eq!(canvas.pixel_rgba(1, 2), [10, 20, 30, 0]);
+        assert_eq!(canvas.pixel_rgba(0, 0), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_blur_hard_edge() {
+        let mut canvas = Canvas::new(8, 1);
+        canvas.fill_rect(4, 0, 4, 1, &[255, 255, 255, 255]);
+
+        canvas.blur(0, 0, 8, 1, 1);
+
+        assert_eq!(canvas.pixel_rgb(2, 0), [0, 0, 0]);
+        assert_eq!(canvas.pixel_rgb(3, 0), [85, 85, 85]);
+        assert_eq!(canvas.pixel_rgb(4, 0), [170, 170, 170]);
+        assert_eq!(canvas.pixel_rgb(5, 0), [255, 255, 255]);
+    }
+
+    #[test]
+    fn test_blur_radius_zero() {
+        let mut canvas = Canvas::new(4, 4);
+        canvas.fill_rect(2, 0, 2, 4, &[255, 255, 255, 255]);
+        let before = canvas.pixels.clone();
+
+        canvas.blur(0, 0, 4, 4, 0);
+
+        assert_eq!(canvas.pixels, before);
+    }
+
+    #[test]
+    fn test_stamp_brush() {
+        let mut canvas = Canvas::new(8, 8);
+        canvas.fill_rect(0, 0, 8, 8, &[0, 0, 0, 255]);
+        let brush = Brush::new(3, 3, vec![0, 0, 0, 0, 128, 0, 0, 0, 0]);
+
+        canvas.stamp(&brush, 4, 4, &[255, 255, 255, 255]);
+
+        assert_eq!(canvas.pixel_rgba(4, 4), [128, 128, 128, 255]);
+        assert_eq!(canvas.pixel_rgba(3, 4), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_stroke_spacing() {
+        let mut canvas = Canvas::new(16, 1);
+        let brush = Brush::new(1, 1, vec![255]);
+
+        canvas.stroke(&brush, &[(0, 0), (5, 0), (10, 0)], &[255, 0, 0, 255], 4);
+
+        let stamped = (0..16)
+            .filter(|&x| canvas.pixel_rgb(x, 0) == [255, 0, 0])
+            .collect::<Vec<_>>();
+        assert_eq!(stamped, vec![0, 4, 8]);
+    }
+
+    #[test]
+    fn test_transform_stack() {
+        let mut canvas = Canvas::new(16, 16);
+
+        canvas.push_transform(3, 4);
+        canvas.plot(0, 0, &[255, 0, 0, 255]);
+        canvas.push_transform(2, -1);
+        canvas.fill_rect(0, 0, 1, 1, &[0, 255, 0, 255]);
+        canvas.pop_transform();
+        let (x, y) = canvas.translate_pixel(1, 1);
+        canvas.pop_transform();
+        canvas.pop_transform();
+
+        assert_eq!(canvas.pixel_rgb(3, 4), [255, 0, 0]);
+        assert_eq!(canvas.pixel_rgb(5, 3), [0, 255, 0]);
+        assert_eq!((x, y), (4, 5));
+        assert_eq!(canvas.offset(), (0, 0));
+    }
+
+    #[test]
+    fn test_apply_color_matrix_invert() {
+        let mut canvas = Canvas::new(2, 1);
+        canvas.write_pixel_blend(0, 0, &[255, 51, 0, 255]);
+        let invert = [
+            [-1.0, 0.0, 0.0, 1.0],
+            [0.0, -1.0, 0.0, 1.0],
+            [0.0, 0.0, -1.0, 1.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+
+        canvas.apply_color_matrix(&invert);
 
-/// Canvas width
-pub fn width(ctx: &Context) -> u32 {
-    ctx.render.canvas.width
-}
+        assert_eq!(canvas.pixel_rgba(0, 0), [0, 204, 255, 255]);
+    }
 
-/// Canvas height
-pub fn height(ctx: &Context) -> u32 {
-    ctx.render.canvas.height
-}
+    #[test]
+    fn test_apply_grayscale() {
+        let mut canvas = Canvas::new(1, 1);
+        canvas.write_pixel_blend(0, 0, &[0, 255, 0, 255]);
 
-// Tests
-#[cfg(test)]
-mod tests {
-    use super::Canvas;
+        canvas.apply_grayscale();
+
+        assert_eq!(canvas.pixel_rgba(0, 0), [182, 182, 182, 255]);
+    }
 
     #[test]
-    #[should_panic]
-    fn test_get_outside_canvas_panics() {
-        let canvas = Canvas::new(256, 256);
-        canvas.pixel_rgb(256, 10);
+    fn test_pixels_iter_mut() {
+        let mut canvas = Canvas::new(3, 2);
+
+        let mut count = 0;
+        for (x, y, pixel) in canvas.pixels_iter_mut() {
+            *pixel = [x as u8, y as u8, 0, 255];
+            count += 1;
+        }
+
+        assert_eq!(count, 6);
+        assert_eq!(canvas.pixel_rgba(2, 0), [2, 0, 0, 255]);
+        assert_eq!(canvas.pixel_rgba(1, 1), [1, 1, 0, 255]);
     }
 
     #[test]
-    #[should_panic]
-    fn test_write_outside_canvas_panics() {
-        let mut canvas = Canvas::new(256, 256);
-        canvas.write_pixel(256, 10, &[255, 255, 255]);
+    fn test_fill_circle_aa() {
+        let mut canvas = Canvas::new(16, 16);
+
+        canvas.fill_circle_aa(8, 8, 4, &[255, 255, 255, 255]);
+
+        // Interior is opaque
+        assert_eq!(canvas.pixel_rgba(8, 8), [255, 255, 255, 255]);
+        assert_eq!(canvas.pixel_rgba(10, 10), [255, 255, 255, 255]);
+        // Boundary is partially covered
+        let edge = canvas.pixel_rgba(11, 11)[3];
+        assert!(edge > 0 && edge < 255, "edge alpha {}", edge);
+        // Outside is untouched
+        assert_eq!(canvas.pixel_rgba(14, 8), [0, 0, 0, 0]);
     }
 
     #[test]
-    #[should_panic]
-    fn test_invalid_rgba() {
-        let mut canvas = Canvas::new(256, 256);
-        canvas.write_pixel_f32(256, 10, &[1.0, 1.2, 0.0]);
+    fn test_draw_circle_aa() {
+        let mut canvas = Canvas::new(16, 16);
+
+        canvas.draw_circle_aa(8, 8, 4, &[255, 255, 255, 255]);
+
+        assert_eq!(canvas.pixel_rgba(12, 8)[3], 255);
+        assert_eq!(canvas.pixel_rgba(8, 8)[3], 0);
+        let edge = canvas.pixel_rgba(11, 11)[3];
+        assert!(edge > 0 && edge < 255, "edge alpha {}", edge);
     }
 
     #[test]
-    fn test_write_then_get_pixel() {
-        let color = [255, 0, 255];
-        let mut canvas = Canvas::new(256, 256);
-        canvas.write_pixel(11, 10, &color);
+    fn test_draw_nine_slice() {
+        // 3x3 sprite with a unique color per slice
+        let mut pixels = Vec::new();
+        for i in 0..9 {
+            pixels.extend_from_slice(&[i * 20, 0, 0, 255]);
+        }
+        let sprite = Sprite::new(3, 3, pixels);
+        let mut canvas = Canvas::new(10, 10);
 
-        let output = canvas.pixel_rgb(11, 10);
+        canvas.draw_nine_slice(&sprite, (1, 1, 1, 1), 1, 1, 8, 6);
 
-        assert_eq!(color, output);
+        // Corners are unscaled
+        assert_eq!(canvas.pixel_rgb(1, 1), [0, 0, 0]);
+        assert_eq!(canvas.pixel_rgb(8, 1), [40, 0, 0]);
+        assert_eq!(canvas.pixel_rgb(1, 6), [120, 0, 0]);
+        assert_eq!(canvas.pixel_rgb(8, 6), [160, 0, 0]);
+        // Edges and center are stretched
+        assert_eq!(canvas.pixel_rgb(4, 1), [20, 0, 0]);
+        assert_eq!(canvas.pixel_rgb(1, 4), [60, 0, 0]);
+        assert_eq!(canvas.pixel_rgb(5, 3), [80, 0, 0]);
+        // Nothing outside the target
+        assert_eq!(canvas.pixel_rgba(9, 1)[3], 0);
     }
 
     #[test]
-    fn test_resize() {
-        let mut canvas = Canvas::new(256, 256);
-        canvas.write_pixel(255, 200, &[255, 255, 255]);
-        canvas.pixel_rgb(255, 200);
+    fn test_draw_points() {
+        let mut canvas = Canvas::new(8, 8);
+        canvas.take_dirty();
 
-        canvas.resize(512, 512);
-        canvas.write_pixel(500, 230, &[255, 255, 255]);
-        canvas.pixel_rgb(500, 230);
+        canvas.draw_points(&[(1, 1), (6, 3), (-1, 2), (8, 0)], &[255, 0, 0, 255]);
+        canvas.draw_points_colored(&[((2, 5), [0, 255, 0, 255]), ((2, 9), [0, 255, 0, 255])]);
+
+        assert_eq!(canvas.pixel_rgb(1, 1), [255, 0, 0]);
+        assert_eq!(canvas.pixel_rgb(6, 3), [255, 0, 0]);
+        assert_eq!(canvas.pixel_rgb(2, 5), [0, 255, 0]);
+        let set = canvas.pixels.chunks(4).filter(|p| p[3] > 0).count();
+        assert_eq!(set, 3);
+        assert_eq!(
+            canvas.take_dirty(),
+            Some(DirtyRegion {
+                min_x: 1,
+                min_y: 1,
+                max_x: 7,
+                max_y: 6
+            })
+        );
+    }
+
+    #[test]
+    fn test_dirty_region() {
+        let mut canvas = Canvas::new(8, 8);
+        assert_eq!(canvas.take_dirty(), Some(DirtyRegion::full(8, 8)));
+        assert_eq!(canvas.take_dirty(), None);
+
+        canvas.write_pixel(2, 3, &[255, 255, 255]);
+        canvas.plot(5, 1, &[255, 255, 255, 255]);
+
+        let dirty = canvas.take_dirty().unwrap();
+        assert_eq!(
+            (dirty.min_x, dirty.min_y, dirty.width(), dirty.height()),
+            (2, 1, 4, 3)
+        );
+
+        canvas.clear_screen();
+
+        assert_eq!(canvas.take_dirty(), Some(DirtyRegion::full(8, 8)));
+    }
+
+    #[test]
+    fn test_composite_offscreen() {
+        let mut canvas = Canvas::new(4, 4);
+        canvas.clear_screen();
+        let mut offscreen = Canvas::new(2, 2);
+        offscreen.fill_rect(0, 0, 1, 2, &[255, 0, 0, 255]);
+
+        canvas.composite(&offscreen, 2, 1);
+        assert_eq!(canvas.pixel_rgba(2, 1), [255, 0, 0, 255]);
+        assert_eq!(canvas.pixel_rgba(2, 2), [255, 0, 0, 255]);
+        // Transparent offscreen pixels leave the canvas untouched
+        assert_eq!(canvas.pixel_rgba(3, 1), canvas.pixel_rgba(0, 0));
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let mut a = Canvas::new(3, 3);
+        let b = Canvas::new(3, 3);
+        assert_eq!(a.content_hash(), b.content_hash());
+
+        a.write_pixel(2, 2, &[0, 0, 1]);
+        assert_ne!(a.content_hash(), b.content_hash());
+
+        // Same pixels with a different size
+        assert_ne!(
+            Canvas::new(1, 4).content_hash(),
+            Canvas::new(4, 1).content_hash()
+        );
+    }
+
+    #[test]
+    fn test_color_histogram() {
+        let mut canvas = Canvas::new(4, 2);
+        canvas.fill_rect(0, 0, 4, 2, &[255, 0, 0, 255]);
+        canvas.fill_rect(0, 0, 2, 1, &[0, 255, 0, 255]);
+        canvas.fill_rect(3, 1, 1, 1, &[0, 0, 255, 255]);
+
+        let histogram = canvas.color_histogram();
+        assert_eq!(histogram.len(), 3);
+        assert_eq!(histogram[&[255, 0, 0, 255]], 5);
+        assert_eq!(histogram[&[0, 255, 0, 255]], 2);
+        assert_eq!(histogram[&[0, 0, 255, 255]], 1);
+
+        assert_eq!(
+            canvas.dominant_colors(2),
+            vec![([255, 0, 0, 255], 5), ([0, 255, 0, 255], 2)]
+        );
+    }
+
+    #[test]
+    fn test_premultiplied_alpha_mode() {
+        let mut canvas = Canvas::new(2, 1);
+        canvas.write_pixel(0, 0, &[0, 0, 255]);
+        canvas.write_pixel(1, 0, &[0, 0, 255]);
+
+        // Half transparent red, premultiplied and straight
+        canvas.set_alpha_mode(AlphaMode::Premultiplied);
+        canvas.write_pixel_blend(0, 0, &[128, 0, 0, 128]);
+        canvas.set_alpha_mode(AlphaMode::Straight);
+        canvas.write_pixel_blend(1, 0, &[255, 0, 0, 128]);
+
+        let [r, g, b, a] = canvas.pixel_rgba(0, 0);
+        assert!(
+            r.abs_diff(128) <= 1 && b.abs_diff(127) <= 1,
+            "{:?}",
+            [r, g, b]
+        );
+        assert_eq!((g, a), (0, 255));
+        assert_eq!(canvas.pixel_rgba(0, 0), canvas.pixel_rgba(1, 0));
+    }
+
+    #[test]
+    fn test_draw_sprite_tinted() {
+        let mut canvas = Canvas::new(2, 1);
+        let white = Sprite::filled(1, 1, &[255, 255, 255, 255]);
+        let sprite = Sprite::filled(1, 1, &[10, 20, 30, 255]);
+
+        canvas.draw_sprite_tinted(&white, 0, 0, &[255, 0, 0, 255]);
+        canvas.draw_sprite_tinted(&sprite, 1, 0, &[255, 255, 255, 255]);
+
+        assert_eq!(canvas.pixel_rgba(0, 0), [255, 0, 0, 255]);
+        assert_eq!(canvas.pixel_rgba(1, 0), [10, 20, 30, 255]);
+        assert_eq!(
+            tint_color(&[255, 255, 255, 255], &[0, 0, 0, 128]),
+            [0, 0, 0, 128]
+        );
+    }
+
+    #[test]
+    fn test_draw_grid() {
+        let mut canvas = Canvas::new(8, 8);
+        canvas.draw_grid(4, (1, -4), &[255, 0, 0, 128]);
+
+        for y in 0..8 {
+            for x in 0..8 {
+                let on_line = x == 1 || x == 5 || y == 0 || y == 4;
+                let expected = if on_line {
+                    [255, 0, 0, 128]
+                } else {
+                    [0, 0, 0, 0]
+                };
+                assert_eq!(canvas.pixel_rgba(x, y), expected, "pixel ({}, {})", x, y);
+            }
+        }
+
+        let mut canvas = Canvas::new(4, 4);
+        canvas.draw_grid(1, (0, 0), &[255, 0, 0, 255]);
+        canvas.draw_grid(0, (0, 0), &[255, 0, 0, 255]);
+        assert!(canvas.pixels.iter().all(|p| *p == 0));
+    }
+
+    #[test]
+    fn test_blit_scaled() {
+        let mut canvas = Canvas::new(6, 4);
+        for y in 0..4 {
+            for x in 0..4 {
+                canvas.write_pixel(x, y, &[x as u8, y as u8, 0]);
+            }
+        }
+
+        canvas.blit_scaled((0, 0, 4, 4), (4, 0, 2, 2));
+        assert_eq!(canvas.pixel_rgb(4, 0), [0, 0, 0]);
+        assert_eq!(canvas.pixel_rgb(5, 0), [2, 0, 0]);
+        assert_eq!(canvas.pixel_rgb(4, 1), [0, 2, 0]);
+        assert_eq!(canvas.pixel_rgb(5, 1), [2, 2, 0]);
+
+        // Upscale overlapping the source
+        canvas.blit_scaled((0, 0, 2, 2), (0, 0, 4, 4));
+        assert_eq!(canvas.pixel_rgb(1, 1), [0, 0, 0]);
+        assert_eq!(canvas.pixel_rgb(2, 1), [1, 0, 0]);
+        assert_eq!(canvas.pixel_rgb(3, 3), [1, 1, 0]);
+    }
+
+    #[test]
+    fn test_depth_buffer() {
+        let mut canvas = Canvas::new(2, 1);
+
+        canvas.write_pixel_depth(0, 0, &[255, 0, 0, 255], 10.0);
+        canvas.write_pixel_depth(0, 0, &[0, 255, 0, 255], 1.0);
+        assert_eq!(canvas.pixel_rgba(0, 0), [0, 255, 0, 255]);
+
+        canvas.write_pixel_depth(0, 0, &[0, 0, 255, 255], 5.0);
+        assert_eq!(canvas.pixel_rgba(0, 0), [0, 255, 0, 255]);
+
+        canvas.clear_depth();
+        canvas.write_pixel_depth(0, 0, &[0, 0, 255, 255], 5.0);
+        assert_eq!(canvas.pixel_rgba(0, 0), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn test_draw_sprite_depth() {
+        let mut canvas = Canvas::new(2, 1);
+        let near = Sprite::filled(1, 1, &[255, 255, 255, 255]);
+        let far = Sprite::new(2, 1, vec![0, 0, 0, 0, 255, 0, 0, 255]);
+
+        canvas.draw_sprite_depth(&near, 0, 0, 0.0);
+        canvas.draw_sprite_depth(&far, 0, 0, 1.0);
+        assert_eq!(canvas.pixel_rgba(0, 0), [255, 255, 255, 255]);
+        assert_eq!(canvas.pixel_rgba(1, 0), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_animation_frames() {
+        let frames = (0..3)
+            .map(|i| Sprite::filled(1, 1, &[i, 0, 0, 255]))
+            .collect();
+        let mut animation = Animation::new(frames, vec![0.1, 0.2, 0.3], true);
+
+        assert_eq!(animation.frame_index(), 0);
+        animation.advance(0.15);
+        assert_eq!(animation.frame_index(), 1);
+        animation.advance(0.2);
+        assert_eq!(animation.frame_index(), 2);
+        assert_eq!(animation.frame().pixel_rgba(0, 0), [2, 0, 0, 255]);
+        animation.advance(0.3);
+        assert_eq!(animation.frame_index(), 0);
+        assert!(!animation.finished());
+    }
+
+    #[test]
+    fn test_animation_one_shot() {
+        let frames = vec![Sprite::filled(1, 1, &[0, 0, 0, 255]); 2];
+        let mut animation = Animation::uniform(frames, 0.5, false);
+
+        animation.advance(0.6);
+        assert_eq!(animation.frame_index(), 1);
+        assert!(!animation.finished());
+
+        animation.advance(1.0);
+        assert_eq!(animation.frame_index(), 1);
+        assert!(animation.finished());
+
+        animation.reset();
+        assert_eq!(animation.frame_index(), 0);
+        assert!(!animation.finished());
+    }
+
+    #[test]
+    fn test_sprites_collide() {
+        // Opaque pixel in top left and bottom right corner
+        let mut a = Sprite::filled(4, 4, &[0, 0, 0, 0]);
+        a.write_pixel(0, 0, &[255, 255, 255, 255]);
+        let mut b = Sprite::filled(4, 4, &[0, 0, 0, 0]);
+        b.write_pixel(3, 3, &[255, 255, 255, 255]);
+
+        // Bounding boxes overlap but opaque pixels do not
+        assert!(!sprites_collide(&a, (0, 0), &b, (1, 1)));
+
+        // Opaque pixels at (0, 0)
+        assert!(sprites_collide(&a, (0, 0), &b, (-3, -3)));
+
+        // Disjoint bounding boxes
+        assert!(!sprites_collide(&a, (0, 0), &b, (4, 0)));
+    }
+
+    #[test]
+    fn test_save_restore_state() {
+        let mut canvas = Canvas::new(4, 4);
+        canvas.write_pixel(1, 1, &[255, 0, 0]);
+
+        let state = canvas.save_state();
+        canvas.write_pixel(2, 2, &[0, 255, 0]);
+        canvas.resize(8, 8);
+        canvas.restore_state(&state);
+
+        assert_eq!((canvas.width, canvas.height), (4, 4));
+        assert_eq!(canvas.pixel_rgb(1, 1), [255, 0, 0]);
+        assert_eq!(canvas.pixel_rgb(2, 2), [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_restore_supersampled_state() {
+        let red = [255, 0, 0, 255];
+        let mut canvas = Canvas::with_supersample(4, 2, 2);
+        canvas.plot(3, 1, &red);
+        let state = canvas.save_state();
+
+        canvas.resize(6, 6);
+        canvas.restore_state(&state);
+        assert_eq!(canvas.logical_size(), (4, 2));
+        assert_eq!((canvas.width, canvas.height), (8, 4));
+        assert_eq!(canvas.resolved_rgba(3, 1), red);
+        assert_eq!(canvas.resolved().len(), 4 * 2 * 4);
+    }
+
+    #[test]
+    fn test_restore_state_resets_depth() {
+        let red = [255, 0, 0, 255];
+        let mut canvas = Canvas::new(8, 8);
+        let state = canvas.save_state();
+
+        canvas.resize(4, 4);
+        canvas.write_pixel_depth(0, 0, &red, 1.0);
+        canvas.restore_state(&state);
+        // Depth buffer of the old size would be indexed out of bounds
+        canvas.write_pixel_depth(7, 7, &red, 1.0);
+        assert_eq!(canvas.pixel_rgba(7, 7), red);
+        canvas.write_pixel_depth(0, 0, &red, 2.0);
+        assert_eq!(canvas.pixel_rgba(0, 0), red);
+    }
+
+    #[cfg(feature = "text")]
+    #[test]
+    fn test_draw_text_ttf() {
+        let mut canvas = Canvas::new(32, 32);
+        let font = canvas
+            .load_font(include_bytes!("../tests/fixtures/FiraSans-Regular.ttf"))
+            .unwrap();
+
+        canvas.draw_text_ttf(font, "A", 4, 4, 20.0, &[255, 255, 255, 255]);
+
+        let covered = canvas.pixels.chunks(4).filter(|p| p[0] > 0).count();
+        assert!(covered > 0);
+        // Nothing drawn left of the text
+        assert_eq!(canvas.pixel_rgb(0, 16), [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_fill_rect_clips() {
+        let mut canvas = Canvas::new(4, 4);
+
+        canvas.fill_rect(-2, 2, 4, 10, &[255, 0, 0, 255]);
+
+        assert_eq!(canvas.pixel_rgb(0, 2), [255, 0, 0]);
+        assert_eq!(canvas.pixel_rgb(1, 3), [255, 0, 0]);
+        assert_eq!(canvas.pixel_rgb(2, 2), [0, 0, 0]);
+        assert_eq!(canvas.pixel_rgb(0, 1), [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_fill_rect_saturates() {
+        let mut canvas = Canvas::new(4, 4);
+        let red = [255, 0, 0, 255];
+
+        // Sizes beyond i32 and rects ending past i32::MAX do not wrap around
+        canvas.fill_rect(2, 1, u32::MAX, 1, &red);
+        canvas.fill_rect(i32::MAX - 1, 0, 4, 4, &red);
+        canvas.fill_rect_depth(i32::MAX - 1, 0, 4, 1, &red, 0.5);
+        canvas.fill_rect_depth(3, 3, u32::MAX, 1, &red, 0.5);
+
+        let red_pixels = canvas.pixels.chunks(4).filter(|p| p[0] == 255).count();
+        assert_eq!(red_pixels, 3);
+        assert_eq!(canvas.pixel_rgb(2, 1), [255, 0, 0]);
+        assert_eq!(canvas.pixel_rgb(3, 1), [255, 0, 0]);
+        assert_eq!(canvas.pixel_rgb(3, 3), [255, 0, 0]);
+    }
+
+    #[test]
+    fn test_draw_label_background() {
+        let mut canvas = Canvas::new(64, 32);
+        let bg = [0, 0, 255, 255];
+
+        canvas.draw_label("Hi", 2, 3, &[255, 255, 255, 255], &bg, 2);
+
+        // "Hi" is 11x7 pixels, plus 2 padding on each side gives 15x11
+        let is_bg = |x, y| canvas.pixel_rgba(x, y) == bg;
+        assert!(is_bg(2, 3));
+        assert!(is_bg(16, 3));
+        assert!(is_bg(2, 13));
+        assert!(is_bg(16, 13));
+        assert!(!is_bg(17, 3));
+        assert!(!is_bg(2, 14));
+        assert!(!is_bg(1, 3));
+
+        // Text is drawn on top, 'H' has its left column set
+        assert_eq!(canvas.pixel_rgb(4, 5), [255, 255, 255]);
+    }
+
+    #[test]
+    fn test_draw_label_transparent_background() {
+        let mut canvas = Canvas::new(64, 32);
+
+        canvas.draw_label("Hi", 2, 3, &[255, 255, 255, 255], &[0, 0, 255, 0], 2);
+
+        assert_eq!(canvas.pixel_rgb(2, 3), [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_draw_batch_matches_individual_draws() {
+        let red = Sprite::filled(3, 2, &[255, 0, 0, 200]);
+        let green = Sprite::filled(2, 2, &[0, 255, 0, 100]);
+        let blue = Sprite::filled(4, 1, &[0, 0, 255, 255]);
+
+        let mut individual = Canvas::new(6, 4);
+        individual.draw_sprite(&red, -1, 0);
+        individual.draw_sprite_tinted(&green, 1, 1, &[128, 255, 255, 255]);
+        individual.draw_sprite(&blue, 4, 3);
+
+        let mut batch = SpriteBatch::new();
+        batch.push(&red, -1, 0);
+        batch.push_tinted(&green, 1, 1, &[128, 255, 255, 255]);
+        batch.push(&blue, 4, 3);
+        let mut batched = Canvas::new(6, 4);
+        batched.take_dirty();
+        batched.draw_batch(&batch);
+
+        assert_eq!(batched.pixels, individual.pixels);
+        assert_eq!(
+            batched.take_dirty(),
+            Some(DirtyRegion {
+                min_x: 0,
+                min_y: 0,
+                max_x: 6,
+                max_y: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn test_clear_with_pattern() {
+        let (black, white) = ([0, 0, 0, 255], [255, 255, 255, 255]);
+        let mut checker = Sprite::filled(2, 2, &black);
+        checker.write_pixel(1, 0, &white);
+        checker.write_pixel(0, 1, &white);
+
+        let mut canvas = Canvas::new(5, 3);
+        canvas.clear_with_pattern(&checker);
+        assert_eq!(canvas.pixel_rgba(0, 0), black);
+        assert_eq!(canvas.pixel_rgba(1, 0), white);
+        assert_eq!(canvas.pixel_rgba(4, 0), black);
+        assert_eq!(canvas.pixel_rgba(3, 1), black);
+        assert_eq!(canvas.pixel_rgba(4, 1), white);
+        assert_eq!(canvas.pixel_rgba(2, 2), black);
+
+        canvas.clear_with_pattern(&Sprite::filled(1, 1, &[1, 2, 3, 4]));
+        assert!(canvas.pixels.chunks(4).all(|p| p == [1, 2, 3, 4]));
+    }
+
+    #[test]
+    fn test_replace_color() {
+        let green = [0, 255, 0, 255];
+        let mut sprite = Sprite::filled(4, 1, &green);
+        sprite.write_pixel(1, 0, &[3, 250, 2, 255]);
+        sprite.write_pixel(2, 0, &[10, 255, 0, 255]);
+        sprite.write_pixel(3, 0, &[200, 30, 40, 255]);
+
+        let mut exact = sprite.clone();
+        exact.replace_color(&green, &[0, 0, 0, 0], 0);
+        assert_eq!(exact.pixel_rgba(0, 0), [0, 0, 0, 0]);
+        assert_eq!(exact.pixel_rgba(1, 0), [3, 250, 2, 255]);
+
+        sprite.replace_color(&green, &[0, 0, 0, 0], 5);
+        assert_eq!(sprite.pixel_rgba(0, 0), [0, 0, 0, 0]);
+        assert_eq!(sprite.pixel_rgba(1, 0), [0, 0, 0, 0]);
+        assert_eq!(sprite.pixel_rgba(2, 0), [10, 255, 0, 255]);
+        assert_eq!(sprite.pixel_rgba(3, 0), [200, 30, 40, 255]);
+
+        let mut canvas = Canvas::new(2, 1);
+        canvas.take_dirty();
+        canvas.replace_color(&[1, 2, 3, 4], &[5, 5, 5, 5], 0);
+        assert!(canvas.take_dirty().is_none());
+        canvas.replace_color(&[0, 0, 0, 0], &[5, 5, 5, 5], 0);
+        assert_eq!(canvas.pixel_rgba(1, 0), [5, 5, 5, 5]);
+        assert!(canvas.take_dirty().is_some());
+    }
+
+    #[test]
+    fn test_supersampled_line() {
+        let white = [255, 255, 255, 255];
+        let alphas = |canvas: &Canvas| {
+            canvas
+                .resolved()
+                .chunks(4)
+                .map(|pixel| pixel[3])
+                .collect::<Vec<_>>()
+        };
+
+        let mut aliased = Canvas::new(4, 2);
+        aliased.draw_line(0, 0, 3, 1, &white);
+        assert!(alphas(&aliased).iter().all(|&a| a == 0 || a == 255));
+
+        let mut canvas = Canvas::with_supersample(4, 2, 2);
+        assert_eq!(canvas.logical_size(), (4, 2));
+        assert_eq!(canvas.pixels.len(), 8 * 4 * 4);
+        canvas.draw_line(0, 0, 3, 1, &white);
+        let alphas = alphas(&canvas);
+        assert_eq!(alphas.len(), 8);
+        assert!(alphas.iter().any(|&a| a > 0 && a < 255));
+        // The end points are fully covered
+        assert_eq!(canvas.pixel_rgba_f32(0, 0), [1.0; 4]);
+        assert_eq!(canvas.resolved_rgba(3, 1), white);
+
+        canvas.resize(3, 3);
+        assert_eq!(canvas.logical_size(), (3, 3));
+        assert_eq!(canvas.take_resized(), Some((3, 3)));
+    }
+
+    #[test]
+    fn test_outline() {
+        let red = [255, 0, 0, 255];
+        let white = [255, 255, 255, 255];
+        let mut canvas = Canvas::new(10, 8);
+        canvas.fill_rect(2, 2, 5, 4, &red);
+
+        let mut kept = Canvas::new(10, 8);
+        kept.fill_rect(2, 2, 5, 4, &red);
+        kept.outline(100, &white, true);
+        canvas.outline(100, &white, false);
+
+        // Border of the rectangle
+        for (x, y) in [
+            (2, 2),
+            (4, 2),
+            (6, 2),
+            (2, 4),
+            (6, 4),
+            (2, 5),
+            (4, 5),
+            (6, 5),
+        ] {
+            assert_eq!(canvas.pixel_rgba(x, y), white);
+        }
+        // Interior and far outside
+        assert_eq!(canvas.pixel_rgba(4, 3), [0, 0, 0, 0]);
+        assert_eq!(canvas.pixel_rgba(5, 4), [0, 0, 0, 0]);
+        assert_eq!(canvas.pixel_rgba(9, 7), [0, 0, 0, 0]);
+        assert_eq!(kept.pixel_rgba(4, 3), red);
+        assert_eq!(kept.pixel_rgba(2, 2), white);
+
+        // Differences below the threshold are not edges
+        let mut soft = Canvas::new(4, 4);
+        soft.fill_rect(1, 1, 2, 2, &[0, 0, 0, 20]);
+        soft.outline(20, &white, false);
+        assert!(soft.pixels.iter().all(|&c| c == 0));
+    }
+
+    #[test]
+    fn test_apply_buffer_from_thread() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let mut buffer = PixelBuffer::new(3, 2);
+            for y in 0..2 {
+                for x in 0..3 {
+                    buffer.write_pixel(x, y, &[x as u8, y as u8, 7, 255]);
+                }
+            }
+            sender.send(buffer).unwrap();
+        });
+        let buffer = receiver.recv().unwrap();
+
+        let mut canvas = Canvas::new(4, 4);
+        canvas.fill_rect(0, 0, 4, 4, &[9, 9, 9, 255]);
+        canvas.take_dirty();
+        canvas.apply_buffer(&buffer, 2, -1);
+        assert_eq!(canvas.pixel_rgba(2, 0), [0, 1, 7, 255]);
+        assert_eq!(canvas.pixel_rgba(3, 0), [1, 1, 7, 255]);
+        assert_eq!(canvas.pixel_rgba(2, 1), [9, 9, 9, 255]);
+        assert_eq!(canvas.pixel_rgba(1, 0), [9, 9, 9, 255]);
+        let dirty = canvas.take_dirty().unwrap();
+        assert_eq!(
+            (dirty.min_x, dirty.min_y, dirty.max_x, dirty.max_y),
+            (2, 0, 4, 1)
+        );
+    }
+
+    #[test]
+    fn test_dirty_rect() {
+        let mut canvas = Canvas::new(8, 8);
+        assert_eq!(canvas.user_dirty(), Some((0, 0, 8, 8)));
+        canvas.clear_user_dirty();
+        canvas.take_dirty();
+        assert_eq!(canvas.user_dirty(), None);
+
+        canvas.write_pixel(3, 5, &[255, 0, 0]);
+        assert_eq!(canvas.user_dirty(), Some((3, 5, 1, 1)));
+
+        // Clearing does not skip the upload
+        canvas.clear_user_dirty();
+        assert!(canvas.take_dirty().is_some());
+
+        canvas.mark_pixels_dirty(6, 1, 10, 2);
+        assert_eq!(canvas.user_dirty(), Some((6, 1, 2, 2)));
+        canvas.clear_user_dirty();
+        canvas.mark_pixels_dirty(8, 0, 1, 1);
+        assert_eq!(canvas.user_dirty(), None);
+
+        let mut supersampled = Canvas::with_supersample(4, 4, 2);
+        supersampled.clear_user_dirty();
+        supersampled.plot(1, 2, &[255, 255, 255, 255]);
+        assert_eq!(supersampled.user_dirty(), Some((1, 2, 1, 1)));
+    }
+
+    #[test]
+    fn test_clear_all() {
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 255];
+        let clear = [10, 20, 30, 40];
+
+        // No depth buffer yet
+        let mut canvas = Canvas::new(4, 4);
+        canvas.clear_all(&clear, f32::INFINITY);
+        assert!(canvas.pixels.chunks(4).all(|p| p == clear));
+        assert!(canvas.depth.is_none());
+
+        canvas.plot_depth(1, 1, &red, 0.1);
+        canvas.plot_depth(1, 1, &blue, 0.5);
+        assert_eq!(canvas.pixel_rgba(1, 1), red);
+
+        canvas.clear_all(&clear, f32::INFINITY);
+        assert_eq!(canvas.pixel_rgba(1, 1), clear);
+        canvas.plot_depth(1, 1, &blue, 0.9);
+        assert_eq!(canvas.pixel_rgba(1, 1), blue);
+
+        // Clearing to a depth occludes anything further away
+        canvas.clear_all(&clear, 0.5);
+        canvas.plot_depth(2, 2, &red, 0.6);
+        assert_eq!(canvas.pixel_rgba(2, 2), clear);
+        canvas.plot_depth(2, 2, &red, 0.4);
+        assert_eq!(canvas.pixel_rgba(2, 2), red);
+    }
+
+    #[test]
+    fn test_safe_area() {
+        assert_eq!(inset_rect(100, 100, 10.0), (10, 10, 80, 80));
+        assert_eq!(inset_rect(320, 180, 5.0), (16, 9, 288, 162));
+        assert_eq!(inset_rect(100, 100, 0.0), (0, 0, 100, 100));
+        assert_eq!(inset_rect(100, 100, 80.0), (50, 50, 0, 0));
+
+        let white = [255, 255, 255, 255];
+        let mut canvas = Canvas::new(100, 100);
+        canvas.push_transform(5, 5);
+        canvas.draw_safe_area(10.0, &white);
+        for (x, y) in [(10, 10), (89, 10), (10, 89), (89, 89), (50, 10), (10, 50)] {
+            assert_eq!(canvas.pixel_rgba(x, y), white);
+        }
+        assert_eq!(canvas.pixel_rgba(50, 50), [0, 0, 0, 0]);
+        assert_eq!(canvas.pixel_rgba(9, 10), [0, 0, 0, 0]);
+        assert_eq!(canvas.pixel_rgba(90, 90), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_symmetry() {
+        let red = [255, 0, 0, 255];
+        let mut canvas = Canvas::new(8, 4);
+        canvas.set_symmetry(SymmetryMode::Horizontal);
+        canvas.write_pixel_blend(1, 2, &red);
+        assert_eq!(canvas.pixel_rgba(1, 2), red);
+        assert_eq!(canvas.pixel_rgba(6, 2), red);
+        assert_eq!(canvas.pixel_rgba(1, 1), [0, 0, 0, 0]);
+
+        let mut canvas = Canvas::new(8, 4);
+        canvas.set_symmetry(SymmetryMode::Quad);
+        canvas.plot(0, 0, &red);
+        for (x, y) in [(0, 0), (7, 0), (0, 3), (7, 3)] {
+            assert_eq!(canvas.pixel_rgba(x, y), red);
+        }
+
+        // Rotating a quarter turn around the center of a 5x5 canvas
+        let mut canvas = Canvas::new(5, 5);
+        canvas.set_symmetry(SymmetryMode::RadialN(4));
+        canvas.plot(2, 0, &red);
+        for (x, y) in [(2, 0), (4, 2), (2, 4), (0, 2)] {
+            assert_eq!(canvas.pixel_rgba(x, y), red);
+        }
+
+        // Copies on the axis blend once
+        let mut canvas = Canvas::new(5, 1);
+        canvas.set_symmetry(SymmetryMode::Horizontal);
+        canvas.write_pixel_blend(2, 0, &[255, 0, 0, 128]);
+        assert_eq!(canvas.pixel_rgba(2, 0)[3], 128);
+
+        // The center of a radial canvas is its only copy
+        let mut canvas = Canvas::new(5, 5);
+        canvas.set_symmetry(SymmetryMode::RadialN(6));
+        assert_eq!(canvas.symmetric(2, 2).collect::<Vec<_>>(), [(2, 2)]);
+    }
+
+    #[test]
+    fn test_scroll() {
+        let mut canvas = Canvas::new(3, 2);
+        canvas.set_clear_color(&[9, 9, 9]);
+        canvas.write_pixel(0, 1, &[1, 0, 0]);
+        canvas.write_pixel(1, 1, &[2, 0, 0]);
+        canvas.take_dirty();
+
+        canvas.scroll(1, -1);
+        let clear = [9, 9, 9, 255];
+        assert_eq!(canvas.pixel_rgba(0, 0), clear);
+        assert_eq!(canvas.pixel_rgba(1, 0), [1, 0, 0, 255]);
+        assert_eq!(canvas.pixel_rgba(2, 0), [2, 0, 0, 255]);
+        assert_eq!(canvas.pixel_rgba(1, 1), clear);
+        assert!(canvas.take_dirty().is_some());
+
+        // Scrolling past the edge leaves only the clear color
+        canvas.scroll(-3, 0);
+        assert!(canvas.pixels.chunks(4).all(|pixel| pixel == clear));
+    }
+
+    #[test]
+    fn test_symmetry_covers_all_writes() {
+        let mut canvas = Canvas::new(4, 1);
+        canvas.set_symmetry(SymmetryMode::Horizontal);
+        canvas.write_pixel_f32(0, 0, &[1.0, 0.0, 0.0]);
+        canvas.write_pixel_blend_f32(1, 0, &[0.0, 1.0, 0.0, 1.0]);
+        assert_eq!(canvas.pixel_rgba(3, 0), [255, 0, 0, 255]);
+        assert_eq!(canvas.pixel_rgba(2, 0), [0, 255, 0, 255]);
+
+        let mut canvas = Canvas::new(4, 1);
+        canvas.write_pixel(0, 0, &[0, 0, 255]);
+        canvas.set_symmetry(SymmetryMode::Horizontal);
+        canvas.blit_scaled((0, 0, 1, 1), (1, 0, 1, 1));
+        assert_eq!(canvas.pixel_rgba(1, 0), [0, 0, 255, 255]);
+        assert_eq!(canvas.pixel_rgba(2, 0), [0, 0, 255, 255]);
+
+        // A translucent rect straddling the axis covers its own copy
+        let mut canvas = Canvas::new(4, 1);
+        canvas.set_symmetry(SymmetryMode::Horizontal);
+        canvas.fill_rect(1, 0, 2, 1, &[255, 0, 0, 128]);
+        canvas.draw_line(0, 0, 3, 0, &[0, 255, 0, 128]);
+        let mut expected = Canvas::new(4, 1);
+        expected.fill_rect(1, 0, 2, 1, &[255, 0, 0, 128]);
+        expected.draw_line(0, 0, 3, 0, &[0, 255, 0, 128]);
+        assert_eq!(canvas.pixels, expected.pixels);
+    }
+
+    #[test]
+    fn test_draw_text_wrapped() {
+        let white = [255, 255, 255, 255];
+        let mut canvas = Canvas::new(40, 40);
+        // Two lines of "ab" and "cd"
+        let height = canvas.draw_text_wrapped("ab cd", 0, 0, 11, &white);
+        assert_eq!(height, 15);
+
+        let mut expected = Canvas::new(40, 40);
+        expected.draw_text("ab\ncd", 0, 0, &white);
+        assert_eq!(canvas.pixels, expected.pixels);
+        assert_eq!(canvas.draw_text_wrapped("", 0, 0, 11, &white), 0);
+    }
+
+    #[test]
+    fn test_failed_resize_keeps_canvas() {
+        // Skip on machines without any adapter
+        let Some(mut ctx) = headless_canvas((4, 4), (4, 4)) else {
+            return;
+        };
+
+        // Zero sized textures are invalid
+        resize(&mut ctx, 0, 3);
+
+        let errors = ctx.render.take_errors();
+        assert!(matches!(errors[..], [RenderError::Validation(_)]));
+        assert_eq!((ctx.render.canvas.width, ctx.render.canvas.height), (4, 4));
+        assert_eq!(ctx.render.canvas.pixels.len(), 4 * 4 * 4);
+
+        // The old texture still matches the canvas
+        render::present(&mut ctx).unwrap();
+        ctx.render.device.poll(wgpu::Maintain::Wait);
+        assert!(ctx.render.take_errors().is_empty());
+    }
+
+    #[test]
+    fn test_oversized_resize_is_ignored() {
+        // Skip on machines without any adapter
+        let Some(mut ctx) = headless_canvas((4, 4), (4, 4)) else {
+            return;
+        };
+        let max = ctx.render.device.limits().max_texture_dimension_2d;
+
+        resize(&mut ctx, max + 1, 4);
+        assert_eq!((ctx.render.canvas.width, ctx.render.canvas.height), (4, 4));
+        assert_eq!(
+            try_resize(&mut ctx, 4, max + 1),
+            Err(ResizeError::TooLarge {
+                width: 4,
+                height: max + 1,
+                max
+            })
+        );
+        assert_eq!((ctx.render.canvas.width, ctx.render.canvas.height), (4, 4));
+        assert!(ctx.render.take_errors().is_empty());
+
+        assert_eq!(try_resize(&mut ctx, 8, 2), Ok(()));
+        assert_eq!((ctx.render.canvas.width, ctx.render.canvas.height), (8, 2));
+    }
+
+    #[test]
+    fn test_write_pixel_norm_follows_transform() {
+        // Skip on machines without any adapter
+        let Some(mut ctx) = headless_canvas((4, 4), (4, 4)) else {
+            return;
+        };
+        let red = [255, 0, 0];
+
+        push_transform(&mut ctx, 1, 2);
+        write_pixel_norm(&mut ctx, 0.0, 0.0, &red);
+        assert_eq!(ctx.render.canvas.pixel_rgba(1, 2), [255, 0, 0, 255]);
+        assert_eq!(ctx.render.canvas.pixel_rgba(0, 0)[..3], [0, 0, 0]);
+
+        // The bottom right corner is moved off the canvas
+        let before = ctx.render.canvas.pixels.clone();
+        write_pixel_norm(&mut ctx, 1.0, 1.0, &red);
+        assert_eq!(ctx.render.canvas.pixels, before);
+    }
+
+    #[test]
+    fn test_clear_on_updates_layout() {
+        // Skip on machines without any adapter
+        let Some(mut ctx) = headless_canvas((1, 1), (2, 1)) else {
+            return;
+        };
+        let id = create_offscreen(&mut ctx, 1, 1);
+        render::set_layout(&mut ctx, Layout::SideBySide(id));
+        render::present(&mut ctx).unwrap();
+
+        clear_on(&mut ctx, id, &[0, 0, 255, 255]);
+        render::present(&mut ctx).unwrap();
+
+        let presented = media::read_surface(&ctx).unwrap();
+        assert_eq!(presented.pixels[4..], [0, 0, 255, 255]);
     }
 }
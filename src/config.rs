@@ -0,0 +1,138 @@
+use crate::{
+    canvas::{CanvasFormat, DEFAULT_CANVAS_HEIGHT, DEFAULT_CANVAS_WIDTH},
+    render::LogicalScaling,
+};
+
+/// Settings applied when starting the app
+///
+/// Override the wanted fields and use ```..Default::default()``` for the rest
+pub struct Config {
+    /// Color format of the canvas texture
+    pub canvas_format: CanvasFormat,
+    /// Width of the canvas before ```init```
+    pub canvas_width: u32,
+    /// Height of the canvas before ```init```
+    pub canvas_height: u32,
+    /// Seed for ```Context::rng```
+    ///
+    /// None seeds from the system time
+    pub seed: Option<u64>,
+    /// Let the desktop show through pixels with alpha below 255
+    ///
+    /// Requires platform support for transparent windows
+    pub transparent: bool,
+    /// Install ```env_logger``` as the global logger
+    ///
+    /// Disable to install your own logger, skipped if a logger is already set
+    pub init_logger: bool,
+    /// Surface format of the window
+    ///
+    /// None picks one matching ```canvas_format```, panics on startup if the format is not supported
+    pub surface_format: Option<wgpu::TextureFormat>,
+    /// Never draw to the window, the CPU canvas is the only output
+    ///
+    /// Canvas pixels are deterministic across drivers, use with ```media::matches_golden``` for golden tests
+    pub software_canvas_only: bool,
+    /// Start with the window maximized
+    pub maximized: bool,
+    /// Show the window on startup
+    ///
+    /// Hide to finish setup before showing it with ```window::set_visible```
+    pub visible: bool,
+    /// Limits requested from the GPU device
+    ///
+    /// None uses the wgpu defaults, or downlevel limits with ```compatibility_mode```
+    pub gpu_limits: Option<wgpu::Limits>,
+    /// Request downlevel limits supported by older and low end devices
+    ///
+    /// Ignored if ```gpu_limits``` is set
+    pub compatibility_mode: bool,
+    /// Fixed canvas size presented scaled to the window
+    ///
+    /// Overrides ```canvas_width``` and ```canvas_height```, the canvas can not be resized
+    ///
+    /// None stretches the canvas over the whole window
+    pub logical_resolution: Option<(u32, u32)>,
+    /// How the logical resolution is scaled to the window
+    pub logical_scaling: LogicalScaling,
+    /// Samples per canvas pixel along each axis, averaged with a box filter before upload and export
+    ///
+    /// Drawing still takes pixel coordinates, 1 disables supersampling
+    ///
+    /// ```pixels_ref```, ```pixels_iter_mut```, ```pixels_copy``` and the color histogram address the samples
+    pub supersample: u32,
+    /// Use the first GPU whose name contains this, ignoring case
+    ///
+    /// None or no matching GPU picks one by power preference
+    pub adapter_name_filter: Option<String>,
+}
+
+impl Config {
+    /// Size of the canvas on startup
+    pub(crate) fn canvas_size(&self) -> (u32, u32) {
+        self.logical_resolution
+            .unwrap_or((self.canvas_width, self.canvas_height))
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            canvas_format: CanvasFormat::Srgb,
+            canvas_width: DEFAULT_CANVAS_WIDTH,
+            canvas_height: DEFAULT_CANVAS_HEIGHT,
+            seed: None,
+            transparent: false,
+            init_logger: true,
+            surface_format: None,
+            software_canvas_only: false,
+            maximized: false,
+            visible: true,
+            gpu_limits: None,
+            compatibility_mode: false,
+            logical_resolution: None,
+            logical_scaling: LogicalScaling::Fit,
+            supersample: 1,
+            adapter_name_filter: None,
+        }
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::Config;
+    use crate::canvas::{Canvas, DEFAULT_CANVAS_HEIGHT, DEFAULT_CANVAS_WIDTH};
+
+    #[test]
+    fn test_configured_canvas_size() {
+        let default = Config::default();
+        assert_eq!(default.canvas_width, DEFAULT_CANVAS_WIDTH);
+        assert_eq!(default.canvas_height, DEFAULT_CANVAS_HEIGHT);
+
+        let config = Config {
+            canvas_width: 64,
+            canvas_height: 32,
+            ..Default::default()
+        };
+        let canvas = Canvas::new(config.canvas_width, config.canvas_height);
+
+        assert_eq!((canvas.width, canvas.height), (64, 32));
+        assert_eq!(canvas.pixels.len(), 64 * 32 * 4);
+    }
+
+    #[test]
+    fn test_logical_resolution_overrides_canvas_size() {
+        let config = Config {
+            canvas_width: 64,
+            canvas_height: 32,
+            logical_resolution: Some((320, 180)),
+            ..Default::default()
+        };
+        assert_eq!(config.canvas_size(), (320, 180));
+        assert_eq!(
+            Config::default().canvas_size(),
+            (DEFAULT_CANVAS_WIDTH, DEFAULT_CANVAS_HEIGHT)
+        );
+    }
+}
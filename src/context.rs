@@ -1,4 +1,7 @@
-use crate::{input::InputContext, render::RenderContext, time::TimeContext};
+use crate::{
+    input::InputContext, render::RenderContext, time::TimeContext, util::Rng,
+    widgets::WidgetContext, window::WindowContext,
+};
 
 /// Holds all the neccesary state for running the engine
 ///
@@ -7,4 +10,16 @@ pub struct Context {
     pub(crate) render: RenderContext,
     pub(crate) time: TimeContext,
     pub(crate) input: InputContext,
+    pub(crate) rng: Rng,
+    pub(crate) window: WindowContext,
+    pub(crate) widgets: WidgetContext,
+}
+
+impl Context {
+    /// Random number generator seeded at startup
+    ///
+    /// Seed can be set with ```Config::seed``` for reproducible runs
+    pub fn rng(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
 }
@@ -0,0 +1,212 @@
+// Built in 5x7 bitmap font
+
+/// Width of a glyph in pixels
+pub(crate) const GLYPH_WIDTH: u32 = 5;
+/// Height of a glyph in pixels
+pub(crate) const GLYPH_HEIGHT: u32 = 7;
+/// Horizontal distance between the start of two glyphs
+pub(crate) const GLYPH_ADVANCE: u32 = GLYPH_WIDTH + 1;
+/// Vertical distance between the start of two lines
+pub(crate) const LINE_HEIGHT: u32 = GLYPH_HEIGHT + 1;
+
+/// Returns true if pixel (x, y) of the glyph for c is set
+/// Characters outside printable ASCII are drawn as '?'
+pub(crate) fn glyph_pixel(c: char, x: u32, y: u32) -> bool {
+    let index = match c {
+        ' '..='~' => c as usize - ' ' as usize,
+        _ => '?' as usize - ' ' as usize,
+    };
+    let column = FONT[index][x as usize];
+    column & (1 << y) != 0
+}
+
+/// Width in pixels of the widest line in text
+pub(crate) fn text_width(text: &str) -> u32 {
+    text.lines()
+        .map(|line| {
+            let chars = line.chars().count() as u32;
+            (chars * GLYPH_ADVANCE).saturating_sub(1)
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Height in pixels of all lines in text
+pub(crate) fn text_height(text: &str) -> u32 {
+    let lines = text.lines().count() as u32;
+    (lines * LINE_HEIGHT).saturating_sub(1)
+}
+
+/// Splits text into lines no wider than max_width pixels
+/// Lines break at spaces, words wider than max_width are broken between characters
+/// Existing line breaks are kept, every line holds at least one character
+pub(crate) fn wrap_text(text: &str, max_width: u32) -> Vec<String> {
+    let max_chars = ((max_width + 1) / GLYPH_ADVANCE).max(1) as usize;
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split(' ').filter(|word| !word.is_empty()) {
+            let mut word: Vec<char> = word.chars().collect();
+            while word.len() > max_chars {
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                lines.push(word.drain(..max_chars).collect());
+            }
+            if word.is_empty() {
+                continue;
+            }
+            let line_chars = line.chars().count();
+            if line_chars == 0 {
+                line.extend(word);
+            } else if line_chars + 1 + word.len() <= max_chars {
+                line.push(' ');
+                line.extend(word);
+            } else {
+                lines.push(std::mem::replace(&mut line, word.into_iter().collect()));
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+// Glyphs for ASCII 32..=126
+// Each byte is a column, least significant bit is the top row
+#[rustfmt::skip]
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5F, 0x00, 0x00], // '!'
+    [0x00, 0x07, 0x00, 0x07, 0x00], // '"'
+    [0x14, 0x7F, 0x14, 0x7F, 0x14], // '#'
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12], // '$'
+    [0x23, 0x13, 0x08, 0x64, 0x62], // '%'
+    [0x36, 0x49, 0x55, 0x22, 0x50], // '&'
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '''
+    [0x00, 0x1C, 0x22, 0x41, 0x00], // '('
+    [0x00, 0x41, 0x22, 0x1C, 0x00], // ')'
+    [0x08, 0x2A, 0x1C, 0x2A, 0x08], // '*'
+    [0x08, 0x08, 0x3E, 0x08, 0x08], // '+'
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ','
+    [0x08, 0x08, 0x08, 0x08, 0x08], // '-'
+    [0x00, 0x60, 0x60, 0x00, 0x00], // '.'
+    [0x20, 0x10, 0x08, 0x04, 0x02], // '/'
+    [0x3E, 0x51, 0x49, 0x45, 0x3E], // '0'
+    [0x00, 0x42, 0x7F, 0x40, 0x00], // '1'
+    [0x42, 0x61, 0x51, 0x49, 0x46], // '2'
+    [0x21, 0x41, 0x45, 0x4B, 0x31], // '3'
+    [0x18, 0x14, 0x12, 0x7F, 0x10], // '4'
+    [0x27, 0x45, 0x45, 0x45, 0x39], // '5'
+    [0x3C, 0x4A, 0x49, 0x49, 0x30], // '6'
+    [0x01, 0x71, 0x09, 0x05, 0x03], // '7'
+    [0x36, 0x49, 0x49, 0x49, 0x36], // '8'
+    [0x06, 0x49, 0x49, 0x29, 0x1E], // '9'
+    [0x00, 0x36, 0x36, 0x00, 0x00], // ':'
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ';'
+    [0x08, 0x14, 0x22, 0x41, 0x00], // '<'
+    [0x14, 0x14, 0x14, 0x14, 0x14], // '='
+    [0x00, 0x41, 0x22, 0x14, 0x08], // '>'
+    [0x02, 0x01, 0x51, 0x09, 0x06], // '?'
+    [0x32, 0x49, 0x79, 0x41, 0x3E], // '@'
+    [0x7E, 0x11, 0x11, 0x11, 0x7E], // 'A'
+    [0x7F, 0x49, 0x49, 0x49, 0x36], // 'B'
+    [0x3E, 0x41, 0x41, 0x41, 0x22], // 'C'
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], // 'D'
+    [0x7F, 0x49, 0x49, 0x49, 0x41], // 'E'
+    [0x7F, 0x09, 0x09, 0x09, 0x01], // 'F'
+    [0x3E, 0x41, 0x49, 0x49, 0x7A], // 'G'
+    [0x7F, 0x08, 0x08, 0x08, 0x7F], // 'H'
+    [0x00, 0x41, 0x7F, 0x41, 0x00], // 'I'
+    [0x20, 0x40, 0x41, 0x3F, 0x01], // 'J'
+    [0x7F, 0x08, 0x14, 0x22, 0x41], // 'K'
+    [0x7F, 0x40, 0x40, 0x40, 0x40], // 'L'
+    [0x7F, 0x02, 0x0C, 0x02, 0x7F], // 'M'
+    [0x7F, 0x04, 0x08, 0x10, 0x7F], // 'N'
+    [0x3E, 0x41, 0x41, 0x41, 0x3E], // 'O'
+    [0x7F, 0x09, 0x09, 0x09, 0x06], // 'P'
+    [0x3E, 0x41, 0x51, 0x21, 0x5E], // 'Q'
+    [0x7F, 0x09, 0x19, 0x29, 0x46], // 'R'
+    [0x46, 0x49, 0x49, 0x49, 0x31], // 'S'
+    [0x01, 0x01, 0x7F, 0x01, 0x01], // 'T'
+    [0x3F, 0x40, 0x40, 0x40, 0x3F], // 'U'
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], // 'V'
+    [0x3F, 0x40, 0x38, 0x40, 0x3F], // 'W'
+    [0x63, 0x14, 0x08, 0x14, 0x63], // 'X'
+    [0x07, 0x08, 0x70, 0x08, 0x07], // 'Y'
+    [0x61, 0x51, 0x49, 0x45, 0x43], // 'Z'
+    [0x00, 0x7F, 0x41, 0x41, 0x00], // '['
+    [0x02, 0x04, 0x08, 0x10, 0x20], // '\'
+    [0x00, 0x41, 0x41, 0x7F, 0x00], // ']'
+    [0x04, 0x02, 0x01, 0x02, 0x04], // '^'
+    [0x40, 0x40, 0x40, 0x40, 0x40], // '_'
+    [0x00, 0x01, 0x02, 0x04, 0x00], // '`'
+    [0x20, 0x54, 0x54, 0x54, 0x78], // 'a'
+    [0x7F, 0x48, 0x44, 0x44, 0x38], // 'b'
+    [0x38, 0x44, 0x44, 0x44, 0x20], // 'c'
+    [0x38, 0x44, 0x44, 0x48, 0x7F], // 'd'
+    [0x38, 0x54, 0x54, 0x54, 0x18], // 'e'
+    [0x08, 0x7E, 0x09, 0x01, 0x02], // 'f'
+    [0x0C, 0x52, 0x52, 0x52, 0x3E], // 'g'
+    [0x7F, 0x08, 0x04, 0x04, 0x78], // 'h'
+    [0x00, 0x44, 0x7D, 0x40, 0x00], // 'i'
+    [0x20, 0x40, 0x44, 0x3D, 0x00], // 'j'
+    [0x7F, 0x10, 0x28, 0x44, 0x00], // 'k'
+    [0x00, 0x41, 0x7F, 0x40, 0x00], // 'l'
+    [0x7C, 0x04, 0x18, 0x04, 0x78], // 'm'
+    [0x7C, 0x08, 0x04, 0x04, 0x78], // 'n'
+    [0x38, 0x44, 0x44, 0x44, 0x38], // 'o'
+    [0x7C, 0x14, 0x14, 0x14, 0x08], // 'p'
+    [0x08, 0x14, 0x14, 0x18, 0x7C], // 'q'
+    [0x7C, 0x08, 0x04, 0x04, 0x08], // 'r'
+    [0x48, 0x54, 0x54, 0x54, 0x20], // 's'
+    [0x04, 0x3F, 0x44, 0x40, 0x20], // 't'
+    [0x3C, 0x40, 0x40, 0x20, 0x7C], // 'u'
+    [0x1C, 0x20, 0x40, 0x20, 0x1C], // 'v'
+    [0x3C, 0x40, 0x30, 0x40, 0x3C], // 'w'
+    [0x44, 0x28, 0x10, 0x28, 0x44], // 'x'
+    [0x0C, 0x50, 0x50, 0x50, 0x3C], // 'y'
+    [0x44, 0x64, 0x54, 0x4C, 0x44], // 'z'
+    [0x00, 0x08, 0x36, 0x41, 0x00], // '{'
+    [0x00, 0x00, 0x7F, 0x00, 0x00], // '|'
+    [0x00, 0x41, 0x36, 0x08, 0x00], // '}'
+    [0x08, 0x04, 0x08, 0x10, 0x08], // '~'
+];
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::{glyph_pixel, text_height, text_width, wrap_text};
+
+    #[test]
+    fn test_text_size() {
+        assert_eq!(text_width(""), 0);
+        assert_eq!(text_width("A"), 5);
+        assert_eq!(text_width("AB"), 11);
+        assert_eq!(text_width("A\nABC"), 17);
+        assert_eq!(text_height("A"), 7);
+        assert_eq!(text_height("A\nB"), 15);
+    }
+
+    #[test]
+    fn test_glyph_pixel() {
+        // '|' is a vertical line in the middle column
+        for y in 0..7 {
+            assert!(glyph_pixel('|', 2, y));
+            assert!(!glyph_pixel('|', 0, y));
+        }
+        assert!(!glyph_pixel(' ', 2, 3));
+    }
+
+    #[test]
+    fn test_wrap_text() {
+        // 23 pixels fit 4 characters
+        let lines = wrap_text("the quick brown fox", 23);
+        assert_eq!(lines, ["the", "quic", "k", "brow", "n", "fox"]);
+        assert!(lines.iter().all(|line| text_width(line) <= 23));
+
+        assert_eq!(wrap_text("a bc def", 29), ["a bc", "def"]);
+        assert_eq!(wrap_text("one\n\ntwo", 100), ["one", "", "two"]);
+        assert_eq!(wrap_text("abc", 0), ["a", "b", "c"]);
+        assert!(wrap_text("", 10).is_empty());
+    }
+}
@@ -1,14 +1,207 @@
 pub use winit::event::MouseButton;
+pub use winit::event::TouchPhase;
 pub use winit::event::VirtualKeyCode as KeyCode;
 
-use crate::{render::RenderContext, Context};
-use std::collections::HashSet;
-use winit::event::ModifiersState;
+#[cfg(feature = "gamepad")]
+pub use gilrs::GamepadId;
+
+use crate::{canvas::Canvas, render::RenderContext, Context};
+use std::{collections::HashSet, path::PathBuf, time::Instant};
+use winit::event::{ElementState, ModifiersState, WindowEvent};
 
 #[derive(Default)]
 pub(crate) struct InputContext {
     pub keyboard: KeyboardContext,
     pub mouse: MouseContext,
+    pub files: FileContext,
+    pub touch: TouchContext,
+    #[cfg(feature = "gamepad")]
+    pub gamepad: GamepadContext,
+    /// Key, mouse and text input received since the last key snapshot
+    events: Vec<InputEvent>,
+}
+
+/// Most events kept while snapshots are manual, older events are dropped
+const MAX_QUEUED_EVENTS: usize = 1024;
+
+impl InputContext {
+    /// Saves keyboard and mouse state at the end of a frame
+    /// Events are cleared together with the keys, see ```set_auto_snapshot```
+    pub(crate) fn end_frame(&mut self) {
+        if !self.keyboard.manual_snapshot {
+            self.events.clear();
+        }
+        self.keyboard.end_frame();
+        self.mouse.save_buttons();
+        self.mouse.set_mouse_delta((0.0, 0.0));
+    }
+
+    /// Saves the keys and clears the event queue
+    pub(crate) fn snapshot(&mut self) {
+        self.keyboard.snapshot();
+        self.events.clear();
+    }
+
+    /// Queues an event, dropping the oldest once ```MAX_QUEUED_EVENTS``` are queued
+    fn push_event(&mut self, event: InputEvent) {
+        if self.events.len() == MAX_QUEUED_EVENTS {
+            self.events.remove(0);
+        }
+        self.events.push(event);
+    }
+
+    /// Updates keyboard and mouse state from a window event
+    pub(crate) fn process_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.mouse.set_pos(position.x, position.y);
+                self.push_event(InputEvent::MouseMoved {
+                    x: position.x,
+                    y: position.y,
+                });
+            }
+            WindowEvent::CursorLeft { .. } => self.mouse.set_on_screen(false),
+            WindowEvent::CursorEntered { .. } => self.mouse.set_on_screen(true),
+            WindowEvent::MouseInput { state, button, .. } => match state {
+                ElementState::Pressed => {
+                    self.mouse.press_button(*button);
+                    self.push_event(InputEvent::MousePressed(*button));
+                }
+                ElementState::Released => {
+                    self.mouse.release_button(*button);
+                    self.push_event(InputEvent::MouseReleased(*button));
+                }
+            },
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (x, y) = match delta {
+                    winit::event::MouseScrollDelta::LineDelta(x, y) => (*x as f64, *y as f64),
+                    winit::event::MouseScrollDelta::PixelDelta(pos) => (pos.x, pos.y),
+                };
+                self.mouse.set_scroll_delta((x, y));
+            }
+            WindowEvent::ModifiersChanged(modifiers) => self.keyboard.modifiers_changed(*modifiers),
+            WindowEvent::KeyboardInput { input, .. } => {
+                let physical_key = PhysicalKey(input.scancode);
+                match input.state {
+                    ElementState::Pressed => self.keyboard.set_physical_key(physical_key),
+                    ElementState::Released => self.keyboard.release_physical_key(physical_key),
+                }
+                if let Some(keycode) = input.virtual_keycode {
+                    match input.state {
+                        ElementState::Pressed => {
+                            self.keyboard.set_key(keycode);
+                            self.push_event(InputEvent::KeyPressed(keycode));
+                        }
+                        ElementState::Released => {
+                            self.keyboard.release_key(keycode);
+                            self.push_event(InputEvent::KeyReleased(keycode));
+                        }
+                    }
+                }
+            }
+            WindowEvent::ReceivedCharacter(c) => self.push_event(InputEvent::Char(*c)),
+            WindowEvent::HoveredFile(path) => self.files.hover_file(path.clone()),
+            WindowEvent::HoveredFileCancelled => self.files.cancel_hover(),
+            WindowEvent::DroppedFile(path) => self.files.drop_file(path.clone()),
+            WindowEvent::Touch(touch) => {
+                let primary = self.touch.update(Touch {
+                    id: touch.id,
+                    phase: touch.phase,
+                    x: touch.location.x,
+                    y: touch.location.y,
+                });
+                if primary && self.touch.emulate_mouse {
+                    self.mouse.set_pos(touch.location.x, touch.location.y);
+                    match touch.phase {
+                        TouchPhase::Started => self.mouse.press_button(MouseButton::Left),
+                        TouchPhase::Moved => {}
+                        TouchPhase::Ended | TouchPhase::Cancelled => {
+                            self.mouse.release_button(MouseButton::Left)
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Touch point in physical window coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Touch {
+    /// Unique while the finger is down
+    pub id: u64,
+    /// Ended and Cancelled touches are kept until the end of the frame
+    pub phase: TouchPhase,
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Default)]
+pub(crate) struct TouchContext {
+    touches: Vec<Touch>,
+    emulate_mouse: bool,
+    /// Id of the touch that started while no other touch was active
+    /// Kept until all touches have ended
+    primary: Option<u64>,
+}
+
+impl TouchContext {
+    /// Starts, moves or ends a touch point
+    /// Returns true if it is the primary touch
+    pub(crate) fn update(&mut self, touch: Touch) -> bool {
+        let id = touch.id;
+        match self.touches.iter_mut().find(|t| t.id == id) {
+            Some(existing) => *existing = touch,
+            None => {
+                self.touches.push(touch);
+                if self.touches.len() == 1 {
+                    self.primary = Some(id);
+                }
+            }
+        }
+        self.primary == Some(id)
+    }
+
+    /// Removes ended and cancelled touches
+    /// Should be called each frame
+    pub(crate) fn clear_ended(&mut self) {
+        self.touches
+            .retain(|t| matches!(t.phase, TouchPhase::Started | TouchPhase::Moved));
+        if self.touches.is_empty() {
+            self.primary = None;
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct FileContext {
+    hovered: Vec<PathBuf>,
+    dropped: Vec<PathBuf>,
+}
+
+impl FileContext {
+    /// Adds a file currently hovering the window
+    pub(crate) fn hover_file(&mut self, path: PathBuf) {
+        self.hovered.push(path);
+    }
+
+    /// Clears hovered files
+    pub(crate) fn cancel_hover(&mut self) {
+        self.hovered.clear();
+    }
+
+    /// Adds a file dropped this frame
+    pub(crate) fn drop_file(&mut self, path: PathBuf) {
+        self.hovered.retain(|p| *p != path);
+        self.dropped.push(path);
+    }
+
+    /// Clears dropped files
+    /// Should be called each frame
+    pub(crate) fn clear_dropped(&mut self) {
+        self.dropped.clear();
+    }
 }
 
 #[derive(Default)]
@@ -19,24 +212,35 @@ pub(crate) struct MouseContext {
     pressed: HashSet<MouseButton>,
     previous_pressed: HashSet<MouseButton>,
     scroll_delta: (f64, f64),
+    /// Last two cursor positions with the time they were received, newest last
+    samples: [Option<(Instant, (f64, f64))>; 2],
+    /// Buttons and scroll were handled this frame, queries report nothing
+    consumed: bool,
 }
 
 impl MouseContext {
     /// Returns true if Button is down
     /// Accepts repeating
     fn button_pressed(&self, keycode: MouseButton) -> bool {
-        self.pressed.contains(&keycode)
+        !self.consumed && self.pressed.contains(&keycode)
     }
 
     /// Returns true if Button was pressed this frame
     /// Does not accept repeating
     fn button_just_pressed(&self, keycode: MouseButton) -> bool {
-        self.pressed.contains(&keycode) && !self.previous_pressed.contains(&keycode)
+        !self.consumed && self.button_transition(keycode).0
     }
 
     /// Returns true is MouseButton was released this frame
     fn button_released(&self, keycode: MouseButton) -> bool {
-        !self.pressed.contains(&keycode) && self.previous_pressed.contains(&keycode)
+        !self.consumed && self.button_transition(keycode).1
+    }
+
+    /// Returns (just pressed, released) this frame, even if the mouse is consumed
+    pub(crate) fn button_transition(&self, keycode: MouseButton) -> (bool, bool) {
+        let pressed = self.pressed.contains(&keycode);
+        let previous = self.previous_pressed.contains(&keycode);
+        (pressed && !previous, !pressed && previous)
     }
 
     /// Returns if mouse is on screen or not
@@ -51,14 +255,21 @@ impl MouseContext {
 
     /// Returns the current pixel under the mouse
     fn mouse_pos_pixel(&self, ctx: &RenderContext) -> (u32, u32) {
-        // When holding the mouse button down pos can get bigger than physical size
-        // So clamp to avoid out of bounds
-        let window_size = ctx.window.inner_size();
-        let relative_x = self.pos.0 / window_size.width as f64;
-        let relative_y = self.pos.1 / window_size.height as f64;
-        let pixel_x = relative_x * ctx.canvas.width as f64;
-        let pixel_y = relative_y * ctx.canvas.height as f64;
-        (pixel_x as u32, pixel_y as u32)
+        physical_to_pixel(self.pos, ctx)
+    }
+
+    /// Returns the color of the canvas pixel under the mouse, canvas is shown in viewport (x, y, w, h)
+    /// None if the mouse is off screen or outside the viewport
+    fn pixel_under_cursor(
+        &self,
+        viewport: (u32, u32, u32, u32),
+        canvas: &Canvas,
+    ) -> Option<[u8; 4]> {
+        if !self.on_screen {
+            return None;
+        }
+        let (x, y) = physical_to_pixel_checked(self.pos, viewport, canvas.logical_size())?;
+        Some(canvas.resolved_rgba(x, y))
     }
 
     /// Returns the (dx, dy) change in mouse position
@@ -67,7 +278,29 @@ impl MouseContext {
     }
 
     fn scroll_delta(&self) -> (f64, f64) {
-        self.scroll_delta
+        if self.consumed {
+            (0.0, 0.0)
+        } else {
+            self.scroll_delta
+        }
+    }
+
+    /// Returns the physical position extrapolated from the last two samples to now
+    /// Extrapolates at most the time between the samples, falls back to the raw position
+    fn mouse_pos_smoothed(&self, now: Instant) -> (f64, f64) {
+        let [Some((previous_time, previous)), Some((last_time, last))] = self.samples else {
+            return self.pos;
+        };
+        let interval = last_time.duration_since(previous_time).as_secs_f64();
+        if interval <= 0.0 {
+            return self.pos;
+        }
+        let elapsed = now.duration_since(last_time).as_secs_f64().min(interval);
+        let t = elapsed / interval;
+        (
+            last.0 + (last.0 - previous.0) * t,
+            last.1 + (last.1 - previous.1) * t,
+        )
     }
 }
 
@@ -79,7 +312,13 @@ impl MouseContext {
 
     // Sets the current position of the mouse
     pub(crate) fn set_pos(&mut self, x: f64, y: f64) {
+        self.set_pos_at(x, y, Instant::now());
+    }
+
+    /// Sets the current position of the mouse received at time
+    pub(crate) fn set_pos_at(&mut self, x: f64, y: f64, time: Instant) {
         self.pos = (x, y);
+        self.samples = [self.samples[1], Some((time, (x, y)))];
     }
 
     /// Sets the (dx, dy) change in mouse position
@@ -104,10 +343,46 @@ impl MouseContext {
     /// Save current buttons in previous
     /// Should be called each frame
     pub(crate) fn save_buttons(&mut self) {
+        self.consumed = false;
         self.previous_pressed = self.pressed.clone()
     }
 }
 
+/// Maps physical window coordinates to fractional canvas coordinates
+/// Canvas of size (width, height) is shown in viewport (x, y, w, h)
+fn physical_to_canvas(
+    pos: (f64, f64),
+    viewport: (u32, u32, u32, u32),
+    size: (u32, u32),
+) -> (f64, f64) {
+    let (x, y, w, h) = viewport;
+    let relative_x = (pos.0 - x as f64) / w as f64;
+    let relative_y = (pos.1 - y as f64) / h as f64;
+    (relative_x * size.0 as f64, relative_y * size.1 as f64)
+}
+
+/// Maps physical window coordinates to the pixel under them
+fn physical_to_pixel(pos: (f64, f64), ctx: &RenderContext) -> (u32, u32) {
+    // When holding the mouse button down pos can get bigger than physical size
+    // So clamp to avoid out of bounds
+    let (pixel_x, pixel_y) =
+        physical_to_canvas(pos, ctx.canvas_viewport(), ctx.canvas.logical_size());
+    (pixel_x as u32, pixel_y as u32)
+}
+
+/// Maps physical window coordinates to the pixel under them
+/// None outside the viewport the canvas of size (width, height) is shown in
+fn physical_to_pixel_checked(
+    pos: (f64, f64),
+    viewport: (u32, u32, u32, u32),
+    size: (u32, u32),
+) -> Option<(u32, u32)> {
+    let (pixel_x, pixel_y) = physical_to_canvas(pos, viewport, size);
+    let inside =
+        pixel_x >= 0.0 && pixel_y >= 0.0 && pixel_x < size.0 as f64 && pixel_y < size.1 as f64;
+    inside.then_some((pixel_x as u32, pixel_y as u32))
+}
+
 //
 // Mouse commands
 //
@@ -133,6 +408,34 @@ pub fn mouse_pos_pixel(ctx: &Context) -> (u32, u32) {
     ctx.input.mouse.mouse_pos_pixel(&ctx.render)
 }
 
+/// Returns the color of the canvas pixel under the mouse
+///
+/// Accounts for letterboxing, None if the mouse is off screen or over the bars around the canvas
+///
+/// Color: Non premultiplied RGBA \[0,255\]
+pub fn pixel_under_cursor(ctx: &Context) -> Option<[u8; 4]> {
+    let viewport = ctx.render.canvas_viewport();
+    ctx.input
+        .mouse
+        .pixel_under_cursor(viewport, &ctx.render.canvas)
+}
+
+/// Returns the pixel position under the mouse extrapolated to the current time
+///
+/// Continues the motion between the last two cursor events, smoother than ```mouse_pos_pixel```
+/// at high refresh rates, useful for custom cursors drawn on the canvas
+///
+/// Returns fractional pixel coordinates, the raw position if only one event has been received
+pub fn mouse_pos_smoothed(ctx: &Context) -> (f32, f32) {
+    let pos = ctx.input.mouse.mouse_pos_smoothed(Instant::now());
+    let (x, y) = physical_to_canvas(
+        pos,
+        ctx.render.canvas_viewport(),
+        ctx.render.canvas.logical_size(),
+    );
+    (x as f32, y as f32)
+}
+
 /// Returns if MouseButton is pressed
 /// Accepts repeating
 pub fn mouse_button_pressed(ctx: &Context, keycode: MouseButton) -> bool {
@@ -155,12 +458,261 @@ pub fn scroll_delta(ctx: &Context) -> (f32, f32) {
     (dx as f32, dy as f32)
 }
 
+/// Mark the mouse buttons and scroll as handled for the rest of the frame
+///
+/// Later button queries return false and the scroll delta is zero, the position is still reported
+///
+/// Lets UI drawn on top of the game prevent clicks reaching it, resets every frame
+pub fn consume_mouse(ctx: &mut Context) {
+    ctx.input.mouse.consumed = true;
+}
+
+/// Returns true if the mouse has been consumed this frame, see ```consume_mouse```
+pub fn is_mouse_consumed(ctx: &Context) -> bool {
+    ctx.input.mouse.consumed
+}
+
+//
+// Touch commands
+//
+
+/// Returns the active touch points
+///
+/// Touches that ended or were cancelled this frame are included with their final phase
+pub fn touches(ctx: &Context) -> &[Touch] {
+    &ctx.input.touch.touches
+}
+
+/// Returns the pixel under a touch point
+pub fn touch_pos_pixel(ctx: &Context, touch: &Touch) -> (u32, u32) {
+    physical_to_pixel((touch.x, touch.y), &ctx.render)
+}
+
+/// Enable/Disable driving the mouse with the first touch point
+///
+/// The touch moves the mouse and presses the left button, default is disabled
+///
+/// Other touches are ignored until all touches have ended, even after the first one ends
+pub fn set_touch_mouse_emulation(ctx: &mut Context, enabled: bool) {
+    ctx.input.touch.emulate_mouse = enabled;
+}
+
+//
+// File commands
+//
+
+/// Returns the files dropped onto the window this frame
+pub fn dropped_files(ctx: &Context) -> &[PathBuf] {
+    &ctx.input.files.dropped
+}
+
+/// Returns the files currently hovering the window
+pub fn hovered_files(ctx: &Context) -> &[PathBuf] {
+    &ctx.input.files.hovered
+}
+
+#[cfg(feature = "gamepad")]
+pub(crate) struct GamepadContext {
+    gilrs: Option<gilrs::Gilrs>,
+    /// Effects stop when dropped so keep them until they are done
+    effects: Vec<(gilrs::ff::Effect, std::time::Instant)>,
+}
+
+#[cfg(feature = "gamepad")]
+impl Default for GamepadContext {
+    fn default() -> Self {
+        let gilrs = match gilrs::Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(err) => {
+                log::warn!("could not initialize gamepads: {}", err);
+                None
+            }
+        };
+        Self {
+            gilrs,
+            effects: Vec::new(),
+        }
+    }
+}
+
+#[cfg(feature = "gamepad")]
+impl GamepadContext {
+    /// Processes pending gamepad events and drops finished effects
+    /// Should be called each frame
+    pub(crate) fn poll(&mut self) {
+        if let Some(gilrs) = &mut self.gilrs {
+            while gilrs.next_event().is_some() {}
+        }
+        let now = std::time::Instant::now();
+        self.effects.retain(|(_, end)| *end > now);
+    }
+
+    /// Returns the ids of all connected gamepads
+    fn gamepads(&self) -> Vec<GamepadId> {
+        match &self.gilrs {
+            Some(gilrs) => gilrs.gamepads().map(|(id, _)| id).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Plays a strong and a weak rumble on a gamepad
+    fn rumble(
+        &mut self,
+        id: GamepadId,
+        strong: f32,
+        weak: f32,
+        duration_ms: u32,
+    ) -> Result<(), RumbleError> {
+        use gilrs::ff::{BaseEffect, BaseEffectType, EffectBuilder, Repeat, Replay, Ticks};
+
+        let gilrs = self.gilrs.as_mut().ok_or(RumbleError::Unavailable)?;
+        let gamepad = gilrs
+            .connected_gamepad(id)
+            .ok_or(RumbleError::NotConnected)?;
+        if !gamepad.is_ff_supported() {
+            return Err(RumbleError::Unsupported);
+        }
+
+        let duration = Ticks::from_ms(duration_ms);
+        let scheduling = Replay {
+            play_for: duration,
+            ..Default::default()
+        };
+        let magnitude = |strength: f32| (strength.clamp(0.0, 1.0) * u16::MAX as f32) as u16;
+        let effect = EffectBuilder::new()
+            .add_effect(BaseEffect {
+                kind: BaseEffectType::Strong {
+                    magnitude: magnitude(strong),
+                },
+                scheduling,
+                ..Default::default()
+            })
+            .add_effect(BaseEffect {
+                kind: BaseEffectType::Weak {
+                    magnitude: magnitude(weak),
+                },
+                scheduling,
+                ..Default::default()
+            })
+            .repeat(Repeat::For(duration))
+            .gamepads(&[id])
+            .finish(gilrs)
+            .map_err(RumbleError::Effect)?;
+        effect.play().map_err(RumbleError::Effect)?;
+
+        let end = std::time::Instant::now() + std::time::Duration::from_millis(duration_ms as u64);
+        self.effects.push((effect, end));
+        Ok(())
+    }
+}
+
+/// Error returned by ```gamepad_rumble```
+#[cfg(feature = "gamepad")]
+#[derive(Debug)]
+pub enum RumbleError {
+    /// Gamepad support could not be initialized on this platform
+    Unavailable,
+    /// No connected gamepad has the id
+    NotConnected,
+    /// The gamepad or its driver does not support force feedback
+    Unsupported,
+    /// The effect could not be created or played
+    Effect(gilrs::ff::Error),
+}
+
+#[cfg(feature = "gamepad")]
+impl std::fmt::Display for RumbleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RumbleError::Unavailable => write!(f, "gamepads are not available"),
+            RumbleError::NotConnected => write!(f, "gamepad is not connected"),
+            RumbleError::Unsupported => write!(f, "gamepad does not support rumble"),
+            RumbleError::Effect(err) => write!(f, "could not play rumble: {}", err),
+        }
+    }
+}
+
+#[cfg(feature = "gamepad")]
+impl std::error::Error for RumbleError {}
+
+//
+// Gamepad commands
+//
+
+/// Returns the ids of all connected gamepads
+#[cfg(feature = "gamepad")]
+pub fn gamepads(ctx: &Context) -> Vec<GamepadId> {
+    ctx.input.gamepad.gamepads()
+}
+
+/// Rumble a gamepad for duration_ms milliseconds
+///
+/// strong and weak are the strengths of the low and high frequency motors in range \[0,1\]
+///
+/// Returns an error if the gamepad, driver or platform does not support force feedback
+#[cfg(feature = "gamepad")]
+pub fn gamepad_rumble(
+    ctx: &mut Context,
+    gamepad_id: GamepadId,
+    strong: f32,
+    weak: f32,
+    duration_ms: u32,
+) -> Result<(), RumbleError> {
+    ctx.input
+        .gamepad
+        .rumble(gamepad_id, strong, weak, duration_ms)
+}
+
+/// Key, mouse or text input in the order it was received
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputEvent {
+    /// Repeats while the key is held
+    KeyPressed(KeyCode),
+    KeyReleased(KeyCode),
+    MousePressed(MouseButton),
+    MouseReleased(MouseButton),
+    /// Cursor position in physical window coordinates
+    MouseMoved {
+        x: f64,
+        y: f64,
+    },
+    /// Character typed, depends on the keyboard layout
+    Char(char),
+}
+
 #[derive(Default)]
 pub(crate) struct KeyboardContext {
     pressed: HashSet<KeyCode>,
     previous_pressed: HashSet<KeyCode>,
+    pressed_physical: HashSet<PhysicalKey>,
+    previous_pressed_physical: HashSet<PhysicalKey>,
     pressed_modifiers: HashSet<KeyModifier>,
     previous_pressed_modifiers: HashSet<KeyModifier>,
+    /// Keys are only saved by ```snapshot_keys``` instead of every frame
+    manual_snapshot: bool,
+    /// Keys were handled this frame, key queries report nothing
+    consumed: bool,
+}
+
+/// Position of a key on the keyboard, independent of keyboard layout
+///
+/// Wraps the platform specific scancode, use for movement controls such as WASD
+///
+/// Use ```KeyCode``` for shortcuts that should follow the printed key
+#[derive(Debug, Hash, Ord, PartialOrd, PartialEq, Eq, Clone, Copy)]
+pub struct PhysicalKey(pub u32);
+
+/// Positions of common game keys on a US QWERTY keyboard
+///
+/// Scancodes match Windows and Linux, other platforms may report different values
+impl PhysicalKey {
+    pub const Q: PhysicalKey = PhysicalKey(16);
+    pub const W: PhysicalKey = PhysicalKey(17);
+    pub const E: PhysicalKey = PhysicalKey(18);
+    pub const A: PhysicalKey = PhysicalKey(30);
+    pub const S: PhysicalKey = PhysicalKey(31);
+    pub const D: PhysicalKey = PhysicalKey(32);
+    pub const SPACE: PhysicalKey = PhysicalKey(57);
 }
 
 /// Describes key modifiers
@@ -177,18 +729,48 @@ impl KeyboardContext {
     /// Returns if KeyCode is down
     /// Accepts repeating
     fn key_pressed(&self, keycode: KeyCode) -> bool {
-        self.pressed.contains(&keycode)
+        !self.consumed && self.pressed.contains(&keycode)
     }
 
     /// Returns if KeyCode was pressed this frame
     /// Does not accepts repeating
     fn key_just_pressed(&self, keycode: KeyCode) -> bool {
-        self.pressed.contains(&keycode) && !self.previous_pressed.contains(&keycode)
+        !self.consumed
+            && self.pressed.contains(&keycode)
+            && !self.previous_pressed.contains(&keycode)
     }
 
     /// Returns is KeyCode was released this frame
     fn key_released(&self, keycode: KeyCode) -> bool {
-        !self.pressed.contains(&keycode) && self.previous_pressed.contains(&keycode)
+        !self.consumed
+            && !self.pressed.contains(&keycode)
+            && self.previous_pressed.contains(&keycode)
+    }
+
+    /// Returns all keys currently down
+    pub(crate) fn pressed_keys(&self) -> impl Iterator<Item = &KeyCode> {
+        self.pressed.iter()
+    }
+
+    /// Returns if PhysicalKey is down
+    /// Accepts repeating
+    fn physical_key_pressed(&self, key: PhysicalKey) -> bool {
+        !self.consumed && self.pressed_physical.contains(&key)
+    }
+
+    /// Returns if PhysicalKey was pressed this frame
+    /// Does not accepts repeating
+    fn physical_key_just_pressed(&self, key: PhysicalKey) -> bool {
+        !self.consumed
+            && self.pressed_physical.contains(&key)
+            && !self.previous_pressed_physical.contains(&key)
+    }
+
+    /// Returns is PhysicalKey was released this frame
+    fn physical_key_released(&self, key: PhysicalKey) -> bool {
+        !self.consumed
+            && !self.pressed_physical.contains(&key)
+            && self.previous_pressed_physical.contains(&key)
     }
 
     fn modifier_pressed(&self, modifier: KeyModifier) -> bool {
@@ -217,6 +799,16 @@ impl KeyboardContext {
         self.pressed.remove(&keycode);
     }
 
+    /// Sets physical key for current frame
+    pub(crate) fn set_physical_key(&mut self, key: PhysicalKey) {
+        self.pressed_physical.insert(key);
+    }
+
+    /// Release physical key
+    pub(crate) fn release_physical_key(&mut self, key: PhysicalKey) {
+        self.pressed_physical.remove(&key);
+    }
+
     pub fn modifiers_changed(&mut self, state: ModifiersState) {
         self.pressed_modifiers.clear();
         if state.shift() {
@@ -237,6 +829,7 @@ impl KeyboardContext {
     /// Should be called each frame
     pub(crate) fn save_keys(&mut self) {
         self.previous_pressed = self.pressed.clone();
+        self.previous_pressed_physical = self.pressed_physical.clone();
     }
 
     /// Save current keys modifiers in previous
@@ -244,6 +837,21 @@ impl KeyboardContext {
     pub(crate) fn save_modifiers(&mut self) {
         self.previous_pressed_modifiers = self.pressed_modifiers.clone();
     }
+
+    /// Save keys and modifiers unless snapshots are manual
+    /// Called at the end of each frame
+    pub(crate) fn end_frame(&mut self) {
+        if !self.manual_snapshot {
+            self.snapshot();
+        }
+    }
+
+    /// Save keys and modifiers
+    pub(crate) fn snapshot(&mut self) {
+        self.save_keys();
+        self.save_modifiers();
+        self.consumed = false;
+    }
 }
 
 //
@@ -267,6 +875,314 @@ pub fn key_released(ctx: &Context, keycode: KeyCode) -> bool {
     ctx.input.keyboard.key_released(keycode)
 }
 
+/// Mark the keys as handled until the next key snapshot
+///
+/// Later key and physical key queries return false, modifiers and ```events_this_frame``` are unaffected
+///
+/// Lets UI such as a focused text field keep keys from reaching the game
+pub fn consume_keyboard(ctx: &mut Context) {
+    ctx.input.keyboard.consumed = true;
+}
+
+/// Returns true if the keyboard has been consumed this frame, see ```consume_keyboard```
+pub fn is_keyboard_consumed(ctx: &Context) -> bool {
+    ctx.input.keyboard.consumed
+}
+
+/// Save the keys at the end of every frame, enabled by default
+///
+/// Disable when stepping logic yourself and call ```snapshot_keys``` after each step,
+/// otherwise just pressed and released keys can be missed or seen twice
+pub fn set_auto_snapshot(ctx: &mut Context, enabled: bool) {
+    ctx.input.keyboard.manual_snapshot = !enabled;
+}
+
+/// Save the current keys so the next step compares against them
+///
+/// Only needed when auto snapshot is disabled, see ```set_auto_snapshot```
+pub fn snapshot_keys(ctx: &mut Context) {
+    ctx.input.snapshot();
+}
+
+/// Key, mouse and text events received since the last frame, oldest first
+///
+/// Keeps the order and count of presses within a frame, unlike ```key_just_pressed```
+///
+/// Cleared together with the keys, see ```set_auto_snapshot```
+///
+/// Without auto snapshots only the latest 1024 events are kept
+pub fn events_this_frame(ctx: &Context) -> &[InputEvent] {
+    &ctx.input.events
+}
+
+/// Returns a human readable name for a key, such as "Space", "Left Arrow" or "A"
+///
+/// Symbol keys are named by their US layout symbol
+pub fn key_name(keycode: KeyCode) -> &'static str {
+    match keycode {
+        KeyCode::Key1 => "1",
+        KeyCode::Key2 => "2",
+        KeyCode::Key3 => "3",
+        KeyCode::Key4 => "4",
+        KeyCode::Key5 => "5",
+        KeyCode::Key6 => "6",
+        KeyCode::Key7 => "7",
+        KeyCode::Key8 => "8",
+        KeyCode::Key9 => "9",
+        KeyCode::Key0 => "0",
+        KeyCode::A => "A",
+        KeyCode::B => "B",
+        KeyCode::C => "C",
+        KeyCode::D => "D",
+        KeyCode::E => "E",
+        KeyCode::F => "F",
+        KeyCode::G => "G",
+        KeyCode::H => "H",
+        KeyCode::I => "I",
+        KeyCode::J => "J",
+        KeyCode::K => "K",
+        KeyCode::L => "L",
+        KeyCode::M => "M",
+        KeyCode::N => "N",
+        KeyCode::O => "O",
+        KeyCode::P => "P",
+        KeyCode::Q => "Q",
+        KeyCode::R => "R",
+        KeyCode::S => "S",
+        KeyCode::T => "T",
+        KeyCode::U => "U",
+        KeyCode::V => "V",
+        KeyCode::W => "W",
+        KeyCode::X => "X",
+        KeyCode::Y => "Y",
+        KeyCode::Z => "Z",
+        KeyCode::Escape => "Escape",
+        KeyCode::F1 => "F1",
+        KeyCode::F2 => "F2",
+        KeyCode::F3 => "F3",
+        KeyCode::F4 => "F4",
+        KeyCode::F5 => "F5",
+        KeyCode::F6 => "F6",
+        KeyCode::F7 => "F7",
+        KeyCode::F8 => "F8",
+        KeyCode::F9 => "F9",
+        KeyCode::F10 => "F10",
+        KeyCode::F11 => "F11",
+        KeyCode::F12 => "F12",
+        KeyCode::F13 => "F13",
+        KeyCode::F14 => "F14",
+        KeyCode::F15 => "F15",
+        KeyCode::F16 => "F16",
+        KeyCode::F17 => "F17",
+        KeyCode::F18 => "F18",
+        KeyCode::F19 => "F19",
+        KeyCode::F20 => "F20",
+        KeyCode::F21 => "F21",
+        KeyCode::F22 => "F22",
+        KeyCode::F23 => "F23",
+        KeyCode::F24 => "F24",
+        KeyCode::Snapshot => "Print Screen",
+        KeyCode::Scroll => "Scroll Lock",
+        KeyCode::Pause => "Pause",
+        KeyCode::Insert => "Insert",
+        KeyCode::Home => "Home",
+        KeyCode::Delete => "Delete",
+        KeyCode::End => "End",
+        KeyCode::PageDown => "Page Down",
+        KeyCode::PageUp => "Page Up",
+        KeyCode::Left => "Left Arrow",
+        KeyCode::Up => "Up Arrow",
+        KeyCode::Right => "Right Arrow",
+        KeyCode::Down => "Down Arrow",
+        KeyCode::Back => "Backspace",
+        KeyCode::Return => "Enter",
+        KeyCode::Space => "Space",
+        KeyCode::Compose => "Compose",
+        KeyCode::Caret => "^",
+        KeyCode::Numlock => "Num Lock",
+        KeyCode::Numpad0 => "Numpad 0",
+        KeyCode::Numpad1 => "Numpad 1",
+        KeyCode::Numpad2 => "Numpad 2",
+        KeyCode::Numpad3 => "Numpad 3",
+        KeyCode::Numpad4 => "Numpad 4",
+        KeyCode::Numpad5 => "Numpad 5",
+        KeyCode::Numpad6 => "Numpad 6",
+        KeyCode::Numpad7 => "Numpad 7",
+        KeyCode::Numpad8 => "Numpad 8",
+        KeyCode::Numpad9 => "Numpad 9",
+        KeyCode::NumpadAdd => "Numpad +",
+        KeyCode::NumpadDivide => "Numpad /",
+        KeyCode::NumpadDecimal => "Numpad .",
+        KeyCode::NumpadComma => "Numpad ,",
+        KeyCode::NumpadEnter => "Numpad Enter",
+        KeyCode::NumpadEquals => "Numpad =",
+        KeyCode::NumpadMultiply => "Numpad *",
+        KeyCode::NumpadSubtract => "Numpad -",
+        KeyCode::AbntC1 => "ABNT C1",
+        KeyCode::AbntC2 => "ABNT C2",
+        KeyCode::Apostrophe => "'",
+        KeyCode::Apps => "Menu",
+        KeyCode::Asterisk => "*",
+        KeyCode::At => "@",
+        KeyCode::Ax => "AX",
+        KeyCode::Backslash => "\\",
+        KeyCode::Calculator => "Calculator",
+        KeyCode::Capital => "Caps Lock",
+        KeyCode::Colon => ":",
+        KeyCode::Comma => ",",
+        KeyCode::Convert => "Convert",
+        KeyCode::Equals => "=",
+        KeyCode::Grave => "`",
+        KeyCode::Kana => "Kana",
+        KeyCode::Kanji => "Kanji",
+        KeyCode::LAlt => "Left Alt",
+        KeyCode::LBracket => "[",
+        KeyCode::LControl => "Left Ctrl",
+        KeyCode::LShift => "Left Shift",
+        KeyCode::LWin => "Left Super",
+        KeyCode::Mail => "Mail",
+        KeyCode::MediaSelect => "Media Select",
+        KeyCode::MediaStop => "Media Stop",
+        KeyCode::Minus => "-",
+        KeyCode::Mute => "Mute",
+        KeyCode::MyComputer => "My Computer",
+        KeyCode::NavigateForward => "Navigate Forward",
+        KeyCode::NavigateBackward => "Navigate Backward",
+        KeyCode::NextTrack => "Next Track",
+        KeyCode::NoConvert => "No Convert",
+        KeyCode::OEM102 => "OEM 102",
+        KeyCode::Period => ".",
+        KeyCode::PlayPause => "Play Pause",
+        KeyCode::Plus => "+",
+        KeyCode::Power => "Power",
+        KeyCode::PrevTrack => "Prev Track",
+        KeyCode::RAlt => "Right Alt",
+        KeyCode::RBracket => "]",
+        KeyCode::RControl => "Right Ctrl",
+        KeyCode::RShift => "Right Shift",
+        KeyCode::RWin => "Right Super",
+        KeyCode::Semicolon => ";",
+        KeyCode::Slash => "/",
+        KeyCode::Sleep => "Sleep",
+        KeyCode::Stop => "Stop",
+        KeyCode::Sysrq => "SysRq",
+        KeyCode::Tab => "Tab",
+        KeyCode::Underline => "_",
+        KeyCode::Unlabeled => "Unlabeled",
+        KeyCode::VolumeDown => "Volume Down",
+        KeyCode::VolumeUp => "Volume Up",
+        KeyCode::Wake => "Wake",
+        KeyCode::WebBack => "Web Back",
+        KeyCode::WebFavorites => "Web Favorites",
+        KeyCode::WebForward => "Web Forward",
+        KeyCode::WebHome => "Web Home",
+        KeyCode::WebRefresh => "Web Refresh",
+        KeyCode::WebSearch => "Web Search",
+        KeyCode::WebStop => "Web Stop",
+        KeyCode::Yen => "Yen",
+        KeyCode::Copy => "Copy",
+        KeyCode::Paste => "Paste",
+        KeyCode::Cut => "Cut",
+    }
+}
+
+/// Returns the character typed by a key on a US layout
+///
+/// None for keys that do not type a character, such as arrows and modifiers
+pub fn keycode_to_char(keycode: KeyCode, shift: bool) -> Option<char> {
+    let letter = |c: char| Some(if shift { c.to_ascii_uppercase() } else { c });
+    let pick = |normal: char, shifted: char| Some(if shift { shifted } else { normal });
+    match keycode {
+        KeyCode::A => letter('a'),
+        KeyCode::B => letter('b'),
+        KeyCode::C => letter('c'),
+        KeyCode::D => letter('d'),
+        KeyCode::E => letter('e'),
+        KeyCode::F => letter('f'),
+        KeyCode::G => letter('g'),
+        KeyCode::H => letter('h'),
+        KeyCode::I => letter('i'),
+        KeyCode::J => letter('j'),
+        KeyCode::K => letter('k'),
+        KeyCode::L => letter('l'),
+        KeyCode::M => letter('m'),
+        KeyCode::N => letter('n'),
+        KeyCode::O => letter('o'),
+        KeyCode::P => letter('p'),
+        KeyCode::Q => letter('q'),
+        KeyCode::R => letter('r'),
+        KeyCode::S => letter('s'),
+        KeyCode::T => letter('t'),
+        KeyCode::U => letter('u'),
+        KeyCode::V => letter('v'),
+        KeyCode::W => letter('w'),
+        KeyCode::X => letter('x'),
+        KeyCode::Y => letter('y'),
+        KeyCode::Z => letter('z'),
+        KeyCode::Key1 => pick('1', '!'),
+        KeyCode::Key2 => pick('2', '@'),
+        KeyCode::Key3 => pick('3', '#'),
+        KeyCode::Key4 => pick('4', '$'),
+        KeyCode::Key5 => pick('5', '%'),
+        KeyCode::Key6 => pick('6', '^'),
+        KeyCode::Key7 => pick('7', '&'),
+        KeyCode::Key8 => pick('8', '*'),
+        KeyCode::Key9 => pick('9', '('),
+        KeyCode::Key0 => pick('0', ')'),
+        KeyCode::Grave => pick('`', '~'),
+        KeyCode::Minus => pick('-', '_'),
+        KeyCode::Equals => pick('=', '+'),
+        KeyCode::LBracket => pick('[', '{'),
+        KeyCode::RBracket => pick(']', '}'),
+        KeyCode::Backslash => pick('\\', '|'),
+        KeyCode::Semicolon => pick(';', ':'),
+        KeyCode::Apostrophe => pick('\'', '"'),
+        KeyCode::Comma => pick(',', '<'),
+        KeyCode::Period => pick('.', '>'),
+        KeyCode::Slash => pick('/', '?'),
+        KeyCode::Space => Some(' '),
+        KeyCode::Numpad0 => Some('0'),
+        KeyCode::Numpad1 => Some('1'),
+        KeyCode::Numpad2 => Some('2'),
+        KeyCode::Numpad3 => Some('3'),
+        KeyCode::Numpad4 => Some('4'),
+        KeyCode::Numpad5 => Some('5'),
+        KeyCode::Numpad6 => Some('6'),
+        KeyCode::Numpad7 => Some('7'),
+        KeyCode::Numpad8 => Some('8'),
+        KeyCode::Numpad9 => Some('9'),
+        KeyCode::NumpadAdd | KeyCode::Plus => Some('+'),
+        KeyCode::NumpadSubtract => Some('-'),
+        KeyCode::NumpadMultiply | KeyCode::Asterisk => Some('*'),
+        KeyCode::NumpadDivide => Some('/'),
+        KeyCode::NumpadDecimal => Some('.'),
+        KeyCode::NumpadComma => Some(','),
+        KeyCode::NumpadEquals => Some('='),
+        KeyCode::At => Some('@'),
+        KeyCode::Colon => Some(':'),
+        KeyCode::Caret => Some('^'),
+        KeyCode::Underline => Some('_'),
+        _ => None,
+    }
+}
+
+/// Returns if the key at a physical position is pressed
+///
+/// Independent of keyboard layout, accepts repeating
+pub fn physical_key_pressed(ctx: &Context, key: PhysicalKey) -> bool {
+    ctx.input.keyboard.physical_key_pressed(key)
+}
+
+/// Returns if the key at a physical position was pressed this frame
+pub fn physical_key_just_pressed(ctx: &Context, key: PhysicalKey) -> bool {
+    ctx.input.keyboard.physical_key_just_pressed(key)
+}
+
+/// Returns if the key at a physical position was released this frame
+pub fn physical_key_released(ctx: &Context, key: PhysicalKey) -> bool {
+    ctx.input.keyboard.physical_key_released(key)
+}
+
 /// Returns if KeyModifer is pressed
 ///
 /// Accepts repeating
@@ -286,11 +1202,173 @@ pub fn modifer_released(ctx: &Context, key_modifier: KeyModifier) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use winit::event::ModifiersState;
+    use std::path::PathBuf;
+    use winit::event::{DeviceId, ElementState, ModifiersState, WindowEvent};
 
+    use crate::canvas::Canvas;
+    use crate::input::InputContext;
+    use crate::input::InputEvent;
     use crate::input::KeyCode;
     use crate::input::KeyModifier;
     use crate::input::KeyboardContext;
+    use crate::input::MouseButton;
+    use crate::input::MouseContext;
+    use crate::input::PhysicalKey;
+    use crate::input::TouchPhase;
+    use crate::input::{key_name, keycode_to_char, physical_to_pixel_checked, MAX_QUEUED_EVENTS};
+
+    #[test]
+    #[allow(deprecated)]
+    fn event_queue_test() {
+        let mut ic = InputContext::default();
+        let device_id = unsafe { DeviceId::dummy() };
+        let key = |state| WindowEvent::KeyboardInput {
+            device_id,
+            input: winit::event::KeyboardInput {
+                scancode: PhysicalKey::Q.0,
+                state,
+                virtual_keycode: Some(KeyCode::Q),
+                modifiers: ModifiersState::empty(),
+            },
+            is_synthetic: false,
+        };
+
+        // Two taps within one frame
+        ic.process_event(&key(ElementState::Pressed));
+        ic.process_event(&WindowEvent::ReceivedCharacter('q'));
+        ic.process_event(&key(ElementState::Released));
+        ic.process_event(&key(ElementState::Pressed));
+        ic.process_event(&key(ElementState::Released));
+        assert_eq!(
+            ic.events,
+            [
+                InputEvent::KeyPressed(KeyCode::Q),
+                InputEvent::Char('q'),
+                InputEvent::KeyReleased(KeyCode::Q),
+                InputEvent::KeyPressed(KeyCode::Q),
+                InputEvent::KeyReleased(KeyCode::Q),
+            ]
+        );
+        assert!(!ic.keyboard.key_pressed(KeyCode::Q));
+
+        ic.end_frame();
+        assert!(ic.events.is_empty());
+
+        // Kept until the manual snapshot
+        ic.keyboard.manual_snapshot = true;
+        ic.process_event(&key(ElementState::Pressed));
+        ic.end_frame();
+        assert_eq!(ic.events, [InputEvent::KeyPressed(KeyCode::Q)]);
+        ic.snapshot();
+        assert!(ic.events.is_empty());
+
+        // Only the latest events are kept without snapshots
+        for i in 0..MAX_QUEUED_EVENTS + 1 {
+            ic.process_event(&WindowEvent::CursorMoved {
+                device_id,
+                position: (i as f64, 0.0).into(),
+                modifiers: ModifiersState::empty(),
+            });
+            ic.end_frame();
+        }
+        assert_eq!(ic.events.len(), MAX_QUEUED_EVENTS);
+        assert_eq!(ic.events[0], InputEvent::MouseMoved { x: 1.0, y: 0.0 });
+    }
+
+    #[test]
+    fn pixel_under_cursor_test() {
+        let mut canvas = Canvas::new(4, 2);
+        canvas.write_pixel(3, 1, &[10, 20, 30]);
+        // Canvas letterboxed into 400x200 with a 50 pixel bar on the left
+        let viewport = (50, 0, 400, 200);
+
+        let mut mc = MouseContext::default();
+        mc.set_pos(420.0, 150.0);
+        assert_eq!(mc.pixel_under_cursor(viewport, &canvas), None);
+        mc.set_on_screen(true);
+        assert_eq!(
+            mc.pixel_under_cursor(viewport, &canvas),
+            Some([10, 20, 30, 255])
+        );
+        mc.set_pos(60.0, 10.0);
+        assert_eq!(mc.pixel_under_cursor(viewport, &canvas), Some([0, 0, 0, 0]));
+
+        // Over the bars
+        mc.set_pos(20.0, 150.0);
+        assert_eq!(mc.pixel_under_cursor(viewport, &canvas), None);
+        mc.set_pos(460.0, 150.0);
+        assert_eq!(mc.pixel_under_cursor(viewport, &canvas), None);
+    }
+
+    #[test]
+    fn physical_to_pixel_checked_test() {
+        let viewport = (50, 0, 400, 200);
+        assert_eq!(
+            physical_to_pixel_checked((50.0, 0.0), viewport, (4, 2)),
+            Some((0, 0))
+        );
+        assert_eq!(
+            physical_to_pixel_checked((449.9, 199.9), viewport, (4, 2)),
+            Some((3, 1))
+        );
+        // Truncating would map the bar just left of the canvas to column 0
+        assert_eq!(
+            physical_to_pixel_checked((49.5, 10.0), viewport, (4, 2)),
+            None
+        );
+        assert_eq!(
+            physical_to_pixel_checked((450.0, 10.0), viewport, (4, 2)),
+            None
+        );
+    }
+
+    #[test]
+    fn consumed_input_test() {
+        let mut mc = MouseContext::default();
+        mc.press_button(MouseButton::Left);
+        mc.set_scroll_delta((0.0, 2.0));
+        assert!(mc.button_just_pressed(MouseButton::Left));
+
+        mc.consumed = true;
+        assert!(!mc.button_just_pressed(MouseButton::Left));
+        assert!(!mc.button_pressed(MouseButton::Left));
+        assert_eq!(mc.scroll_delta(), (0.0, 0.0));
+
+        // Next frame the button is held and queries work again
+        mc.save_buttons();
+        assert!(mc.button_pressed(MouseButton::Left));
+        assert!(!mc.button_just_pressed(MouseButton::Left));
+
+        let mut kc = KeyboardContext::default();
+        kc.set_key(KeyCode::Space);
+        kc.consumed = true;
+        assert!(!kc.key_just_pressed(KeyCode::Space));
+        assert!(!kc.key_pressed(KeyCode::Space));
+        kc.end_frame();
+        assert!(kc.key_pressed(KeyCode::Space));
+    }
+
+    #[test]
+    fn manual_snapshot_test() {
+        let mut kc = KeyboardContext {
+            manual_snapshot: true,
+            ..Default::default()
+        };
+
+        kc.set_key(KeyCode::A);
+        kc.end_frame();
+        assert!(kc.key_just_pressed(KeyCode::A));
+        kc.end_frame();
+        assert!(kc.key_just_pressed(KeyCode::A));
+
+        kc.save_keys();
+        assert!(!kc.key_just_pressed(KeyCode::A));
+
+        kc.manual_snapshot = false;
+        kc.release_key(KeyCode::A);
+        kc.end_frame();
+        assert!(!kc.key_released(KeyCode::A));
+    }
 
     #[test]
     fn key_pressed_test() {
@@ -401,6 +1479,194 @@ mod tests {
         assert!(kc.modifier_released(KeyModifier::Shift));
         assert!(!kc.modifier_released(KeyModifier::Ctrl));
     }
+
+    #[test]
+    fn dropped_file_test() {
+        let mut ic = InputContext::default();
+        let path = PathBuf::from("image.png");
+
+        ic.process_event(&WindowEvent::HoveredFile(path.clone()));
+
+        assert_eq!(ic.files.hovered, vec![path.clone()]);
+
+        ic.process_event(&WindowEvent::DroppedFile(path.clone()));
+
+        assert!(ic.files.hovered.is_empty());
+        assert_eq!(ic.files.dropped, vec![path]);
+
+        ic.files.clear_dropped();
+
+        assert!(ic.files.dropped.is_empty());
+    }
+
+    #[test]
+    fn key_name_test() {
+        assert_eq!(key_name(KeyCode::A), "A");
+        assert_eq!(key_name(KeyCode::Key7), "7");
+        assert_eq!(key_name(KeyCode::Space), "Space");
+        assert_eq!(key_name(KeyCode::Left), "Left Arrow");
+        assert_eq!(key_name(KeyCode::PageDown), "Page Down");
+
+        assert_eq!(keycode_to_char(KeyCode::A, false), Some('a'));
+        assert_eq!(keycode_to_char(KeyCode::A, true), Some('A'));
+        assert_eq!(keycode_to_char(KeyCode::Key7, false), Some('7'));
+        assert_eq!(keycode_to_char(KeyCode::Key7, true), Some('&'));
+        assert_eq!(keycode_to_char(KeyCode::Space, true), Some(' '));
+        assert_eq!(keycode_to_char(KeyCode::Left, false), None);
+    }
+
+    #[test]
+    fn mouse_pos_smoothed_test() {
+        let mut mc = MouseContext::default();
+        let start = std::time::Instant::now();
+        let ms = std::time::Duration::from_millis;
+
+        mc.set_pos_at(10.0, 20.0, start);
+        assert_eq!(mc.mouse_pos_smoothed(start + ms(5)), (10.0, 20.0));
+
+        mc.set_pos_at(20.0, 10.0, start + ms(10));
+        assert_eq!(mc.mouse_pos_smoothed(start + ms(10)), (20.0, 10.0));
+        assert_eq!(mc.mouse_pos_smoothed(start + ms(15)), (25.0, 5.0));
+        // Extrapolation stops after one sample interval
+        assert_eq!(mc.mouse_pos_smoothed(start + ms(100)), (30.0, 0.0));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn physical_key_test() {
+        let mut ic = InputContext::default();
+        let device_id = unsafe { DeviceId::dummy() };
+        // W position on an AZERTY layout
+        let key = |state| WindowEvent::KeyboardInput {
+            device_id,
+            input: winit::event::KeyboardInput {
+                scancode: PhysicalKey::W.0,
+                state,
+                virtual_keycode: Some(KeyCode::Z),
+                modifiers: ModifiersState::empty(),
+            },
+            is_synthetic: false,
+        };
+
+        ic.process_event(&key(ElementState::Pressed));
+        assert!(ic.keyboard.physical_key_pressed(PhysicalKey::W));
+        assert!(ic.keyboard.physical_key_just_pressed(PhysicalKey::W));
+        assert!(!ic.keyboard.physical_key_pressed(PhysicalKey::Q));
+        assert!(ic.keyboard.key_pressed(KeyCode::Z));
+
+        ic.keyboard.save_keys();
+        assert!(!ic.keyboard.physical_key_just_pressed(PhysicalKey::W));
+
+        ic.process_event(&key(ElementState::Released));
+        assert!(ic.keyboard.physical_key_released(PhysicalKey::W));
+        assert!(!ic.keyboard.physical_key_pressed(PhysicalKey::W));
+    }
+
+    #[test]
+    fn touch_test() {
+        let mut ic = InputContext::default();
+        let device_id = unsafe { DeviceId::dummy() };
+        let touch = |id, phase, x| {
+            WindowEvent::Touch(winit::event::Touch {
+                device_id,
+                phase,
+                location: winit::dpi::PhysicalPosition::new(x, 10.0),
+                force: None,
+                id,
+            })
+        };
+
+        ic.process_event(&touch(1, TouchPhase::Started, 5.0));
+        ic.process_event(&touch(2, TouchPhase::Started, 50.0));
+        ic.process_event(&touch(1, TouchPhase::Moved, 8.0));
+
+        assert_eq!(ic.touch.touches.len(), 2);
+        assert_eq!(ic.touch.touches[0].x, 8.0);
+        assert_eq!(ic.touch.touches[1].phase, TouchPhase::Started);
+        // Mouse emulation is disabled by default
+        assert!(!ic.mouse.button_pressed(MouseButton::Left));
+
+        ic.process_event(&touch(2, TouchPhase::Ended, 50.0));
+        assert_eq!(ic.touch.touches[1].phase, TouchPhase::Ended);
+
+        ic.touch.clear_ended();
+
+        assert_eq!(ic.touch.touches.len(), 1);
+        assert_eq!(ic.touch.touches[0].id, 1);
+    }
+
+    #[test]
+    fn touch_mouse_emulation_test() {
+        let mut ic = InputContext::default();
+        ic.touch.emulate_mouse = true;
+        let device_id = unsafe { DeviceId::dummy() };
+        let touch = |phase| {
+            WindowEvent::Touch(winit::event::Touch {
+                device_id,
+                phase,
+                location: winit::dpi::PhysicalPosition::new(3.0, 4.0),
+                force: None,
+                id: 7,
+            })
+        };
+
+        ic.process_event(&touch(TouchPhase::Started));
+
+        assert!(ic.mouse.button_pressed(MouseButton::Left));
+        assert_eq!(ic.mouse.mouse_pos_physical(), (3.0, 4.0));
+
+        ic.process_event(&touch(TouchPhase::Ended));
+
+        assert!(!ic.mouse.button_pressed(MouseButton::Left));
+    }
+
+    #[test]
+    fn primary_touch_test() {
+        let mut ic = InputContext::default();
+        ic.touch.emulate_mouse = true;
+        let device_id = unsafe { DeviceId::dummy() };
+        let touch = |id, phase, x| {
+            WindowEvent::Touch(winit::event::Touch {
+                device_id,
+                phase,
+                location: winit::dpi::PhysicalPosition::new(x, 10.0),
+                force: None,
+                id,
+            })
+        };
+
+        ic.process_event(&touch(1, TouchPhase::Started, 5.0));
+        ic.process_event(&touch(2, TouchPhase::Started, 50.0));
+        ic.process_event(&touch(1, TouchPhase::Ended, 5.0));
+        ic.touch.clear_ended();
+        ic.mouse.save_buttons();
+
+        // The second touch does not take over the mouse
+        ic.process_event(&touch(2, TouchPhase::Moved, 60.0));
+        assert_eq!(ic.mouse.mouse_pos_physical(), (5.0, 10.0));
+        assert!(!ic.mouse.button_pressed(MouseButton::Left));
+
+        // Until all touches have ended
+        ic.process_event(&touch(2, TouchPhase::Ended, 60.0));
+        ic.touch.clear_ended();
+        ic.process_event(&touch(3, TouchPhase::Started, 20.0));
+        assert_eq!(ic.mouse.mouse_pos_physical(), (20.0, 10.0));
+        assert!(ic.mouse.button_pressed(MouseButton::Left));
+    }
+
+    #[test]
+    fn process_event_test() {
+        let mut ic = InputContext::default();
+        let device_id = unsafe { DeviceId::dummy() };
+
+        ic.process_event(&WindowEvent::CursorEntered { device_id });
+
+        assert!(ic.mouse.on_screen());
+
+        ic.process_event(&WindowEvent::CursorLeft { device_id });
+
+        assert!(!ic.mouse.on_screen());
+    }
 }
 // use winit::event::MouseButton;
 // pub use winit::event::VirtualKeyCode as KeyCode;
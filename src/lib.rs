@@ -1,13 +1,20 @@
 pub mod canvas;
 pub mod input;
 pub mod media;
+#[cfg(feature = "particles")]
+pub mod particles;
+pub mod render;
 pub mod time;
+pub mod util;
+pub mod widgets;
 pub mod window;
 
 mod app;
+mod config;
 mod context;
-mod render;
+mod font;
 
 // Re-exports
-pub use app::{run, Callbacks};
+pub use app::{run, run_boxed, run_with_config, run_with_window, Callbacks};
+pub use config::Config;
 pub use context::Context;
@@ -1,7 +1,46 @@
 // Upload screenshots
 
-use crate::Context;
-use image::{ImageResult, RgbaImage};
+use crate::{
+    canvas::{self, Sprite},
+    render::RenderContext,
+    Context,
+};
+use image::{
+    codecs::gif::GifDecoder,
+    error::{ParameterError, ParameterErrorKind},
+    AnimationDecoder, ImageError, ImageResult, RgbaImage,
+};
+use std::{
+    fmt,
+    fs::File,
+    future::Future,
+    io::BufReader,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{self, Poll},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Characters from dark to bright used by ```canvas_to_ascii```
+const ASCII_RAMP: &[u8] = b" .:-=+*#%@";
+
+/// Format name stored in project documents
+#[cfg(feature = "serde")]
+const PROJECT_FORMAT: &str = "pixelated-project";
+
+/// Version written by ```save_project```
+#[cfg(feature = "serde")]
+const PROJECT_VERSION: u32 = 1;
+
+/// First line of a GIMP palette file
+const GPL_MAGIC: &str = "GIMP Palette";
+
+/// Color of differing pixels in the diff of ```compare```
+const DIFF_COLOR: [u8; 4] = [255, 0, 255, 255];
+
+/// Terminal characters are roughly twice as tall as they are wide
+const ASCII_CHAR_ASPECT: f32 = 2.0;
 
 /// Can take screenshots of a canvas
 pub(crate) struct ScreenshotUploader {
@@ -21,6 +60,7 @@ impl ScreenshotUploader {
     }
 
     /// Export current state of canvas to a image at the specified path
+    /// Alpha is written as is
     pub(crate) fn export_to_file(&self, pixels: &[u8], path: &str) -> ImageResult<()> {
         let mut img = RgbaImage::new(self.width, self.height);
 
@@ -28,6 +68,444 @@ impl ScreenshotUploader {
 
         img.save(path)
     }
+
+    /// Starts reading back the last presented frame
+    pub(crate) fn capture_presented(render: &RenderContext) -> Result<CaptureFuture, CaptureError> {
+        let presented = render.presented.as_ref().ok_or(CaptureError::Suspended)?;
+        let encoder = render
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Capture Encoder"),
+            });
+        Ok(read_texture(
+            &render.device,
+            &render.queue,
+            encoder,
+            &presented.texture,
+        ))
+    }
+}
+
+/// Copies texture into a buffer after the commands in encoder and starts mapping it
+///
+/// Texture must have ```COPY_SRC``` usage and an 8 bit RGBA or BGRA format
+pub(crate) fn read_texture(
+    device: &Arc<wgpu::Device>,
+    queue: &wgpu::Queue,
+    mut encoder: wgpu::CommandEncoder,
+    texture: &wgpu::Texture,
+) -> CaptureFuture {
+    let size = texture.size();
+    let (width, height) = (size.width, size.height);
+    let padded_bytes_per_row = padded_bytes_per_row(width);
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Capture Buffer"),
+        size: (padded_bytes_per_row * height) as u64,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(padded_bytes_per_row),
+                rows_per_image: std::num::NonZeroU32::new(height),
+            },
+        },
+        size,
+    );
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let result = Arc::new(Mutex::new(None));
+    let callback_result = result.clone();
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, move |mapped| {
+            *callback_result.lock().unwrap() = Some(mapped);
+        });
+
+    CaptureFuture {
+        device: device.clone(),
+        buffer,
+        result,
+        width,
+        height,
+        padded_bytes_per_row,
+        bgra: matches!(
+            texture.format(),
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        ),
+    }
+}
+
+/// Resampling used when exporting at a different size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFilter {
+    /// Copies the closest pixel, keeps pixel art crisp
+    Nearest,
+    /// Interpolates the four closest pixels, smooth for moderate upscales
+    Bilinear,
+    /// Averages every covered pixel, best for downscaling
+    Box,
+}
+
+/// File format used by ```export_palette```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteFormat {
+    /// GIMP palette, one "r g b" line per color, alpha is dropped
+    Gpl,
+    /// One RRGGBB line per color, RRGGBBAA for translucent colors
+    Hex,
+    /// JSON object with a "colors" array of "#rrggbbaa" strings
+    Json,
+}
+
+/// Ordered list of colors
+///
+/// Color: Non premultiplied RGBA \[0,255\]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Palette {
+    pub colors: Vec<[u8; 4]>,
+}
+
+/// Differences between two images, returned by ```compare```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompareResult {
+    /// Both images have the same width and height, nothing else is compared otherwise
+    pub size_matches: bool,
+    /// Pixels where a channel differs by more than the tolerance
+    pub differing_pixels: u32,
+    /// Largest difference of any channel, including differences within the tolerance
+    pub max_delta: u8,
+    /// Differing pixels in magenta over a dimmed copy of the first image, None if the sizes differ
+    pub diff: Option<Sprite>,
+}
+
+impl CompareResult {
+    /// Returns true if the sizes match and no pixel differs by more than the tolerance
+    pub fn matches(&self) -> bool {
+        self.size_matches && self.differing_pixels == 0
+    }
+}
+
+/// Error returned when capturing the presented image fails
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaptureError {
+    /// The readback buffer could not be mapped
+    Map(wgpu::BufferAsyncError),
+    /// Nothing is presented while the app is suspended
+    Suspended,
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::Map(err) => write!(f, "could not map capture buffer: {}", err),
+            CaptureError::Suspended => write!(f, "no frame is presented while suspended"),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+/// Error returned when exporting a screenshot with a generated name fails
+#[derive(Debug)]
+pub enum ExportError {
+    /// The directory could not be created
+    Io(std::io::Error),
+    /// The image could not be encoded or written
+    Image(ImageError),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Io(err) => write!(f, "could not create screenshot directory: {}", err),
+            ExportError::Image(err) => write!(f, "could not write screenshot: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+impl From<std::io::Error> for ExportError {
+    fn from(err: std::io::Error) -> Self {
+        ExportError::Io(err)
+    }
+}
+
+impl From<ImageError> for ExportError {
+    fn from(err: ImageError) -> Self {
+        ExportError::Image(err)
+    }
+}
+
+/// Error returned when saving or loading a project fails
+#[derive(Debug)]
+pub enum ProjectError {
+    /// The file could not be read or written
+    Io(std::io::Error),
+    /// The file is not a valid project
+    Corrupt(String),
+    /// The file was written by a newer version
+    UnsupportedVersion(u32),
+    /// The canvas size is not supported by the GPU
+    Resize(canvas::ResizeError),
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectError::Io(err) => write!(f, "could not access project file: {}", err),
+            ProjectError::Corrupt(reason) => write!(f, "corrupt project file: {}", reason),
+            ProjectError::UnsupportedVersion(version) => {
+                write!(f, "unsupported project version {}", version)
+            }
+            ProjectError::Resize(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ProjectError {}
+
+impl From<std::io::Error> for ProjectError {
+    fn from(err: std::io::Error) -> Self {
+        ProjectError::Io(err)
+    }
+}
+
+/// Error returned when loading a palette or an animation fails
+#[derive(Debug)]
+pub enum LoadError {
+    /// The file could not be read
+    Io(std::io::Error),
+    /// The file is not a valid palette
+    Malformed(String),
+    /// The file is not a valid image
+    Image(ImageError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(err) => write!(f, "could not read file: {}", err),
+            LoadError::Malformed(reason) => write!(f, "malformed palette file: {}", reason),
+            LoadError::Image(err) => write!(f, "could not decode image: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<std::io::Error> for LoadError {
+    fn from(err: std::io::Error) -> Self {
+        LoadError::Io(err)
+    }
+}
+
+impl From<ImageError> for LoadError {
+    fn from(err: ImageError) -> Self {
+        LoadError::Image(err)
+    }
+}
+
+/// Resolves to the presented image once the GPU has finished reading it back
+pub(crate) struct CaptureFuture {
+    device: Arc<wgpu::Device>,
+    buffer: wgpu::Buffer,
+    result: Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    bgra: bool,
+}
+
+impl Future for CaptureFuture {
+    type Output = Result<Sprite, CaptureError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        self.device.poll(wgpu::Maintain::Poll);
+
+        let result = self.result.lock().unwrap().take();
+        match result {
+            None => {
+                // Map callbacks only fire while polling the device so poll again
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Some(Err(err)) => Poll::Ready(Err(CaptureError::Map(err))),
+            Some(Ok(())) => {
+                let mut pixels = {
+                    let data = self.buffer.slice(..).get_mapped_range();
+                    unpad_rows(&data, self.width, self.height, self.padded_bytes_per_row)
+                };
+                self.buffer.unmap();
+                if self.bgra {
+                    for pixel in pixels.chunks_mut(4) {
+                        pixel.swap(0, 2);
+                    }
+                }
+                Poll::Ready(Ok(Sprite::new(self.width, self.height, pixels)))
+            }
+        }
+    }
+}
+
+/// Bytes per row of an RGBA copy rounded up to the required copy alignment
+pub(crate) fn padded_bytes_per_row(width: u32) -> u32 {
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    (width * 4).div_ceil(align) * align
+}
+
+/// Removes the row padding of a texture copy
+pub(crate) fn unpad_rows(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+) -> Vec<u8> {
+    let bytes_per_row = (width * 4) as usize;
+    data.chunks(padded_bytes_per_row as usize)
+        .take(height as usize)
+        .flat_map(|row| &row[..bytes_per_row])
+        .copied()
+        .collect()
+}
+
+/// Returns true if img has the size and exact RGBA bytes of pixels
+fn pixels_match(pixels: &[u8], width: u32, height: u32, img: &RgbaImage) -> bool {
+    img.width() == width && img.height() == height && img.as_raw().as_slice() == pixels
+}
+
+/// Formats seconds since the unix epoch as a UTC "YYYY-MM-DD_HH-MM-SS" timestamp
+fn format_timestamp(secs: u64) -> String {
+    let (days, rest) = (secs / 86400, secs % 86400);
+    // Civil date from days, see <https://howardhinnant.github.io/date_algorithms.html>
+    let z = days + 719468;
+    let (era, doe) = (z / 146097, z % 146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as u64;
+    format!(
+        "{:04}-{:02}-{:02}_{:02}-{:02}-{:02}",
+        year,
+        month,
+        day,
+        rest / 3600,
+        rest / 60 % 60,
+        rest % 60
+    )
+}
+
+/// Writes a png named after prefix and time to dir with write, creating dir if missing
+/// A counter is appended when the file already exists
+fn write_unique(
+    dir: &Path,
+    prefix: &str,
+    time: SystemTime,
+    write: impl FnOnce(&Path) -> ImageResult<()>,
+) -> Result<PathBuf, ExportError> {
+    std::fs::create_dir_all(dir)?;
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let stem = format!("{}_{}", prefix, format_timestamp(secs));
+    let mut path = dir.join(format!("{}.png", stem));
+    let mut counter = 1;
+    while path.exists() {
+        path = dir.join(format!("{}_{}.png", stem, counter));
+        counter += 1;
+    }
+    write(&path)?;
+    Ok(path)
+}
+
+/// Resamples RGBA pixels of size width x height to out_width x out_height
+fn resample(
+    pixels: &[u8],
+    (width, height): (u32, u32),
+    (out_width, out_height): (u32, u32),
+    filter: ExportFilter,
+) -> Vec<u8> {
+    let pixel = |x: u32, y: u32| {
+        let index = ((y * width + x) * 4) as usize;
+        &pixels[index..index + 4]
+    };
+    let mut out = Vec::with_capacity((out_width * out_height * 4) as usize);
+    for y in 0..out_height {
+        for x in 0..out_width {
+            match filter {
+                ExportFilter::Nearest => {
+                    // Sample under the center of the output pixel
+                    let sx = ((2 * x + 1) as u64 * width as u64 / (2 * out_width) as u64) as u32;
+                    let sy = ((2 * y + 1) as u64 * height as u64 / (2 * out_height) as u64) as u32;
+                    out.extend_from_slice(pixel(sx, sy));
+                }
+                ExportFilter::Bilinear => {
+                    let source = |v: u32, size: u32, out_size: u32| {
+                        let v = ((v as f32 + 0.5) * size as f32 / out_size as f32 - 0.5)
+                            .clamp(0.0, (size - 1) as f32);
+                        let low = v.floor() as u32;
+                        (low, (low + 1).min(size - 1), v - low as f32)
+                    };
+                    let (x0, x1, tx) = source(x, width, out_width);
+                    let (y0, y1, ty) = source(y, height, out_height);
+                    for c in 0..4 {
+                        let lerp = |a: u8, b: u8, t: f32| a as f32 + (b as f32 - a as f32) * t;
+                        let top = lerp(pixel(x0, y0)[c], pixel(x1, y0)[c], tx);
+                        let bottom = lerp(pixel(x0, y1)[c], pixel(x1, y1)[c], tx);
+                        out.push((top + (bottom - top) * ty).round() as u8);
+                    }
+                }
+                ExportFilter::Box => {
+                    // Source pixels overlapping the output pixel, at least one
+                    let span = |v: u32, size: u32, out_size: u32| {
+                        let start = (v as u64 * size as u64 / out_size as u64) as u32;
+                        let end = ((v + 1) as u64 * size as u64).div_ceil(out_size as u64) as u32;
+                        start..end.max(start + 1)
+                    };
+                    let mut sum = [0u32; 4];
+                    let mut count = 0;
+                    for sy in span(y, height, out_height) {
+                        for sx in span(x, width, out_width) {
+                            for (total, &c) in sum.iter_mut().zip(pixel(sx, sy)) {
+                                *total += c as u32;
+                            }
+                            count += 1;
+                        }
+                    }
+                    out.extend(sum.map(|total| ((total + count / 2) / count) as u8));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Blends every pixel over an opaque background color
+fn flatten(pixels: &[u8], background: &[u8; 3]) -> Vec<u8> {
+    pixels
+        .chunks_exact(4)
+        .flat_map(|pixel| {
+            let alpha = pixel[3] as u32;
+            let blend =
+                |c: u8, b: u8| ((c as u32 * alpha + b as u32 * (255 - alpha) + 127) / 255) as u8;
+            [
+                blend(pixel[0], background[0]),
+                blend(pixel[1], background[1]),
+                blend(pixel[2], background[2]),
+                255,
+            ]
+        })
+        .collect()
 }
 
 //
@@ -35,8 +513,877 @@ impl ScreenshotUploader {
 //
 
 /// Export the current canvas to a png at the desired path
+///
+/// Alpha is preserved, transparent pixels stay transparent in the file
 pub fn export_screenshot(ctx: &Context, path: &str) -> ImageResult<()> {
     ctx.render
         .screenshot_uploader
-        .export_to_file(&ctx.render.canvas.pixels, path)
+        .export_to_file(&ctx.render.canvas.resolved(), path)
+}
+
+/// Export the current canvas to a new png in dir named prefix followed by the UTC time
+///
+/// Captures within the same second get an incrementing counter, existing files are never overwritten
+///
+/// Creates dir if missing, returns the path written
+pub fn export_screenshot_auto<P: AsRef<Path>>(
+    ctx: &Context,
+    dir: P,
+    prefix: &str,
+) -> Result<PathBuf, ExportError> {
+    let canvas = &ctx.render.canvas;
+    let (width, height) = canvas.logical_size();
+    write_unique(dir.as_ref(), prefix, SystemTime::now(), |path| {
+        RgbaImage::from_raw(width, height, canvas.resolved().into_owned())
+            .expect("canvas buffer matches its size")
+            .save(path)
+    })
+}
+
+/// Export the current canvas to a png at the desired path blended over background
+///
+/// Every pixel in the file is opaque
+///
+/// Background: RGB \[0,255\]
+pub fn export_screenshot_opaque(
+    ctx: &Context,
+    path: &str,
+    background: &[u8; 3],
+) -> ImageResult<()> {
+    ctx.render
+        .screenshot_uploader
+        .export_to_file(&flatten(&ctx.render.canvas.resolved(), background), path)
+}
+
+/// Export the current canvas to a png of size width x height at the desired path
+///
+/// Filter controls the resampling, see ```ExportFilter```
+///
+/// Returns an error if width or height is 0
+pub fn export_screenshot_scaled(
+    ctx: &Context,
+    path: &str,
+    width: u32,
+    height: u32,
+    filter: ExportFilter,
+) -> ImageResult<()> {
+    if width == 0 || height == 0 {
+        return Err(ImageError::Parameter(ParameterError::from_kind(
+            ParameterErrorKind::DimensionMismatch,
+        )));
+    }
+    let canvas = &ctx.render.canvas;
+    let pixels = resample(
+        &canvas.resolved(),
+        canvas.logical_size(),
+        (width, height),
+        filter,
+    );
+    RgbaImage::from_raw(width, height, pixels)
+        .expect("resampled buffer matches the export size")
+        .save(path)
+}
+
+/// Returns true if the canvas is byte identical to the image at path
+///
+/// Intended for golden tests, store the reference with ```export_screenshot```
+///
+/// Compares the CPU canvas which does not depend on the GPU, see ```Config::software_canvas_only```
+pub fn matches_golden<P: AsRef<Path>>(ctx: &Context, path: P) -> ImageResult<bool> {
+    let img = image::open(path)?.to_rgba8();
+    let canvas = &ctx.render.canvas;
+    let (width, height) = canvas.logical_size();
+    Ok(pixels_match(&canvas.resolved(), width, height, &img))
+}
+
+/// Compare two images pixel by pixel, such as a canvas copy against a golden image
+///
+/// Pixels where every channel is within tolerance of the other image count as equal
+pub fn compare(a: &Sprite, b: &Sprite, tolerance: u8) -> CompareResult {
+    if (a.width, a.height) != (b.width, b.height) {
+        return CompareResult {
+            size_matches: false,
+            differing_pixels: 0,
+            max_delta: 0,
+            diff: None,
+        };
+    }
+
+    let mut differing_pixels = 0;
+    let mut max_delta = 0;
+    let mut diff = Vec::with_capacity(a.pixels.len());
+    for (pa, pb) in a.pixels.chunks_exact(4).zip(b.pixels.chunks_exact(4)) {
+        let delta = pa
+            .iter()
+            .zip(pb)
+            .map(|(ca, cb)| ca.abs_diff(*cb))
+            .max()
+            .unwrap();
+        max_delta = max_delta.max(delta);
+        if delta > tolerance {
+            differing_pixels += 1;
+            diff.extend_from_slice(&DIFF_COLOR);
+        } else {
+            let gray = ((pa[0] as u32 + pa[1] as u32 + pa[2] as u32) / 9) as u8;
+            diff.extend_from_slice(&[gray, gray, gray, 255]);
+        }
+    }
+
+    CompareResult {
+        size_matches: true,
+        differing_pixels,
+        max_delta,
+        diff: Some(Sprite::new(a.width, a.height, diff)),
+    }
+}
+
+/// Capture the last frame presented to the window including shader effects
+///
+/// Has the size of the window, resolves once the GPU has read back the image
+///
+/// Includes the passes of ```Callbacks::render```, changes since the last present are not included
+///
+/// Awaiting polls the device until the readback is done
+pub fn capture_presented(ctx: &Context) -> impl Future<Output = Result<Sprite, CaptureError>> {
+    let capture = ScreenshotUploader::capture_presented(&ctx.render);
+    async move { capture?.await }
+}
+
+/// Read back the pixels of the last frame presented to the window, blocking until the GPU is done
+///
+/// Has the size of the window and includes letterboxing, filtering, shader effects
+/// and the passes of ```Callbacks::render```
+///
+/// Blocking version of ```capture_presented```, meant for tests of the full pipeline
+pub fn read_surface(ctx: &Context) -> Result<Sprite, CaptureError> {
+    pollster::block_on(capture_presented(ctx))
+}
+
+/// Load an image onto the canvas
+///
+/// Resizes the canvas to the size of the image
+///
+/// With ```Config::logical_resolution``` the image must match the locked canvas size
+pub fn load_onto_canvas<P: AsRef<Path>>(ctx: &mut Context, path: P) -> ImageResult<()> {
+    let img = image::open(path)?.to_rgba8();
+
+    canvas::resize(ctx, img.width(), img.height());
+    if ctx.render.canvas.logical_size() != img.dimensions() {
+        return Err(ImageError::Parameter(ParameterError::from_kind(
+            ParameterErrorKind::DimensionMismatch,
+        )));
+    }
+    ctx.render.canvas.set_resolved(img.as_raw());
+
+    Ok(())
+}
+
+/// Load all frames of an animated gif
+///
+/// Returns each frame paired with its delay in seconds
+///
+/// Frames are fully composited, disposal methods have already been applied
+pub fn load_gif<P: AsRef<Path>>(path: P) -> Result<Vec<(Sprite, f32)>, LoadError> {
+    let file = BufReader::new(File::open(path)?);
+    let frames = GifDecoder::new(file)?.into_frames().collect_frames()?;
+
+    let frames = frames
+        .into_iter()
+        .map(|frame| {
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            let delay = numer as f32 / denom as f32 / 1000.0;
+            let buffer = frame.into_buffer();
+            let sprite = Sprite::new(buffer.width(), buffer.height(), buffer.into_raw());
+            (sprite, delay)
+        })
+        .collect();
+
+    Ok(frames)
+}
+
+/// Convert the canvas to ascii art with ```columns``` characters per line
+///
+/// Rows are derived from the canvas aspect, corrected for the height of terminal characters
+pub fn canvas_to_ascii(ctx: &Context, columns: u32) -> String {
+    let canvas = &ctx.render.canvas;
+    let (width, height) = canvas.logical_size();
+    ascii_art(&canvas.resolved(), width, height, columns)
+}
+
+/// Export the canvas as ascii art to a text file at the desired path
+///
+/// See ```canvas_to_ascii```
+pub fn export_ascii<P: AsRef<Path>>(ctx: &Context, path: P, columns: u32) -> std::io::Result<()> {
+    std::fs::write(path, canvas_to_ascii(ctx, columns))
+}
+
+/// Save the canvas as a versioned JSON project document at path
+///
+/// The document stores the format name, version, size and RGBA pixels of the canvas
+#[cfg(feature = "serde")]
+pub fn save_project<P: AsRef<Path>>(ctx: &Context, path: P) -> Result<(), ProjectError> {
+    let canvas = &ctx.render.canvas;
+    let (width, height) = canvas.logical_size();
+    let sprite = Sprite::new(width, height, canvas.resolved().into_owned());
+    std::fs::write(path, encode_project(&sprite))?;
+    Ok(())
+}
+
+/// Load a project saved with ```save_project``` onto the canvas
+///
+/// Resizes the canvas to the size of the project, the canvas is untouched if the file is invalid
+#[cfg(feature = "serde")]
+pub fn load_project<P: AsRef<Path>>(ctx: &mut Context, path: P) -> Result<(), ProjectError> {
+    let sprite = decode_project(&std::fs::read_to_string(path)?)?;
+    canvas::try_resize(ctx, sprite.width, sprite.height).map_err(ProjectError::Resize)?;
+    ctx.render.canvas.set_resolved(&sprite.pixels);
+    Ok(())
+}
+
+/// Write the colors of the canvas to path, most used first
+pub fn export_palette<P: AsRef<Path>>(
+    ctx: &Context,
+    path: P,
+    format: PaletteFormat,
+) -> std::io::Result<()> {
+    let colors = ctx.render.canvas.dominant_colors(usize::MAX);
+    let palette = Palette {
+        colors: colors.into_iter().map(|(color, _)| color).collect(),
+    };
+    std::fs::write(path, encode_palette(&palette, format))
+}
+
+/// Load a palette written by ```export_palette``` or another tool
+///
+/// The format is detected from the contents, colors without alpha are opaque
+pub fn import_palette<P: AsRef<Path>>(path: P) -> Result<Palette, LoadError> {
+    decode_palette(&std::fs::read_to_string(path)?)
+}
+
+/// Writes palette in format
+fn encode_palette(palette: &Palette, format: PaletteFormat) -> String {
+    let hex = |c: &[u8; 4]| format!("{:02x}{:02x}{:02x}{:02x}", c[0], c[1], c[2], c[3]);
+    let mut text = String::new();
+    match format {
+        PaletteFormat::Gpl => {
+            text.push_str(&format!("{}\nName: pixelated\nColumns: 0\n#\n", GPL_MAGIC));
+            for c in &palette.colors {
+                text.push_str(&format!(
+                    "{:3} {:3} {:3}\t{}\n",
+                    c[0],
+                    c[1],
+                    c[2],
+                    &hex(c)[..6]
+                ));
+            }
+        }
+        PaletteFormat::Hex => {
+            for c in &palette.colors {
+                let hex = hex(c);
+                text.push_str(if c[3] == 255 { &hex[..6] } else { &hex });
+                text.push('\n');
+            }
+        }
+        PaletteFormat::Json => {
+            let colors: Vec<String> = palette
+                .colors
+                .iter()
+                .map(|c| format!("    \"#{}\"", hex(c)))
+                .collect();
+            text.push_str(&format!(
+                "{{\n  \"colors\": [\n{}\n  ]\n}}\n",
+                colors.join(",\n")
+            ));
+        }
+    }
+    text
+}
+
+/// Parses a palette in any ```PaletteFormat```
+fn decode_palette(text: &str) -> Result<Palette, LoadError> {
+    let malformed = |reason: &str| LoadError::Malformed(reason.to_string());
+    let text = text.trim();
+    let mut colors = Vec::new();
+    if let Some(body) = text.strip_prefix(GPL_MAGIC) {
+        for line in body.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') || line.contains(':') {
+                continue;
+            }
+            let channels: Vec<u8> = line
+                .split_whitespace()
+                .take(3)
+                .map(|c| c.parse().map_err(|_| malformed("invalid channel")))
+                .collect::<Result<_, _>>()?;
+            let [r, g, b] = channels[..] else {
+                return Err(malformed("missing channels"));
+            };
+            colors.push([r, g, b, 255]);
+        }
+    } else if text.starts_with('{') {
+        let list = text
+            .split_once("\"colors\"")
+            .and_then(|(_, rest)| rest.split_once('['))
+            .and_then(|(_, rest)| rest.split_once(']'))
+            .map(|(list, _)| list.trim())
+            .ok_or_else(|| malformed("missing colors array"))?;
+        for entry in list.split(',').filter(|_| !list.is_empty()) {
+            let entry = entry.trim();
+            let hex = entry
+                .strip_prefix('"')
+                .and_then(|entry| entry.strip_suffix('"'))
+                .ok_or_else(|| malformed("color is not a string"))?;
+            colors.push(parse_hex_color(hex).ok_or_else(|| malformed("invalid color"))?);
+        }
+    } else {
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            colors.push(parse_hex_color(line).ok_or_else(|| malformed("invalid color"))?);
+        }
+    }
+    Ok(Palette { colors })
+}
+
+/// Parses RRGGBB or RRGGBBAA with an optional leading #
+fn parse_hex_color(hex: &str) -> Option<[u8; 4]> {
+    let hex = hex.strip_prefix('#').unwrap_or(hex);
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+        return None;
+    }
+    let mut color = [255; 4];
+    for (i, channel) in color.iter_mut().take(hex.len() / 2).enumerate() {
+        *channel = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(color)
+}
+
+/// Fields every project version starts with
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct ProjectHeader {
+    format: String,
+    version: u32,
+}
+
+/// Project document written by ```save_project```
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ProjectDocument {
+    format: String,
+    version: u32,
+    canvas: Sprite,
+}
+
+/// Writes the canvas pixels as a project document
+#[cfg(feature = "serde")]
+fn encode_project(canvas: &Sprite) -> String {
+    let document = ProjectDocument {
+        format: PROJECT_FORMAT.to_string(),
+        version: PROJECT_VERSION,
+        canvas: canvas.clone(),
+    };
+    serde_json::to_string_pretty(&document).expect("project document is serializable")
+}
+
+/// Parses a project document
+/// The header is checked first so newer versions report their version instead of a parse error
+#[cfg(feature = "serde")]
+fn decode_project(text: &str) -> Result<Sprite, ProjectError> {
+    let corrupt = |err: serde_json::Error| ProjectError::Corrupt(err.to_string());
+    let header: ProjectHeader = serde_json::from_str(text).map_err(corrupt)?;
+    if header.format != PROJECT_FORMAT {
+        return Err(ProjectError::Corrupt(format!(
+            "unknown format {:?}",
+            header.format
+        )));
+    }
+    if header.version > PROJECT_VERSION {
+        return Err(ProjectError::UnsupportedVersion(header.version));
+    }
+
+    let document: ProjectDocument = serde_json::from_str(text).map_err(corrupt)?;
+    let canvas = document.canvas;
+    if canvas.pixels.len() as u64 != canvas.width as u64 * canvas.height as u64 * 4 {
+        return Err(ProjectError::Corrupt(
+            "pixels do not match the size".to_string(),
+        ));
+    }
+    Ok(canvas)
+}
+
+/// Downsamples pixels to a grid of characters by average luminance
+/// Empty for an empty canvas
+fn ascii_art(pixels: &[u8], width: u32, height: u32, columns: u32) -> String {
+    if width == 0 || height == 0 {
+        return String::new();
+    }
+    let columns = columns.clamp(1, width);
+    let rows = ((columns as f32 * height as f32 / width as f32 / ASCII_CHAR_ASPECT).round() as u32)
+        .clamp(1, height);
+
+    let mut text = String::with_capacity(((columns + 1) * rows) as usize);
+    for row in 0..rows {
+        let min_y = row * height / rows;
+        let max_y = ((row + 1) * height / rows).max(min_y + 1);
+        for column in 0..columns {
+            let min_x = column * width / columns;
+            let max_x = ((column + 1) * width / columns).max(min_x + 1);
+
+            let mut luminance = 0.0;
+            for y in min_y..max_y {
+                for x in min_x..max_x {
+                    let index = ((y * width + x) * 4) as usize;
+                    luminance += 0.2126 * pixels[index] as f32
+                        + 0.7152 * pixels[index + 1] as f32
+                        + 0.0722 * pixels[index + 2] as f32;
+                }
+            }
+            luminance /= ((max_x - min_x) * (max_y - min_y)) as f32 * 255.0;
+
+            let index = (luminance * (ASCII_RAMP.len() - 1) as f32).round() as usize;
+            text.push(ASCII_RAMP[index.min(ASCII_RAMP.len() - 1)] as char);
+        }
+        text.push('\n');
+    }
+    text
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::{
+        ascii_art, capture_presented, compare, decode_palette, encode_palette, flatten,
+        format_timestamp, import_palette, load_gif, padded_bytes_per_row, pixels_match,
+        read_surface, read_texture, resample, unpad_rows, write_unique, ExportFilter, LoadError,
+        Palette, PaletteFormat, ScreenshotUploader, DIFF_COLOR,
+    };
+    use crate::{
+        app::headless_canvas,
+        canvas::{Canvas, Sprite},
+        render::{self, test_device},
+        util::Rng,
+    };
+    use image::{codecs::gif::GifEncoder, Delay, Frame, Rgba, RgbaImage};
+    use std::fs::File;
+
+    #[test]
+    fn test_export_preserves_alpha() {
+        let path = std::env::temp_dir().join("pixelated_test_export_alpha.png");
+        let pixels = [255, 0, 0, 128, 0, 255, 0, 0];
+        ScreenshotUploader::new(2, 1)
+            .export_to_file(&pixels, path.to_str().unwrap())
+            .unwrap();
+
+        let img = image::open(&path).unwrap().to_rgba8();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(img.as_raw().as_slice(), &pixels);
+    }
+
+    #[test]
+    fn test_golden_render_is_deterministic() {
+        let render = || {
+            let mut canvas = Canvas::new(16, 16);
+            let mut rng = Rng::seed(7);
+            canvas.clear_screen();
+            for _ in 0..32 {
+                let (x, y) = (rng.range(-4, 16), rng.range(-4, 16));
+                canvas.fill_circle_aa(x, y, 3, &[rng.next_u32() as u8, 100, 200, 180]);
+            }
+            canvas.blur(0, 0, 16, 16, 1);
+            canvas
+        };
+        let first = render();
+        let second = render();
+        assert_eq!(first.pixels, second.pixels);
+
+        let path = std::env::temp_dir().join("pixelated_test_golden.png");
+        ScreenshotUploader::new(16, 16)
+            .export_to_file(&first.pixels, path.to_str().unwrap())
+            .unwrap();
+        let golden = image::open(&path).unwrap().to_rgba8();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(pixels_match(&second.pixels, 16, 16, &golden));
+        assert!(!pixels_match(&second.pixels, 16, 8, &golden));
+    }
+
+    #[test]
+    fn test_flatten() {
+        let pixels = [255, 0, 0, 128, 0, 255, 0, 0, 0, 0, 255, 255];
+
+        assert_eq!(
+            flatten(&pixels, &[0, 0, 255]),
+            vec![128, 0, 127, 255, 0, 0, 255, 255, 0, 0, 255, 255]
+        );
+    }
+
+    #[test]
+    fn test_load_gif() {
+        let path = std::env::temp_dir().join("pixelated_test_load_gif.gif");
+        {
+            let file = File::create(&path).unwrap();
+            let mut encoder = GifEncoder::new(file);
+            let red = RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 255]));
+            let blue = RgbaImage::from_pixel(2, 2, Rgba([0, 0, 255, 255]));
+            encoder
+                .encode_frames(vec![
+                    Frame::from_parts(red, 0, 0, Delay::from_numer_denom_ms(100, 1)),
+                    Frame::from_parts(blue, 0, 0, Delay::from_numer_denom_ms(250, 1)),
+                ])
+                .unwrap();
+        }
+
+        let frames = load_gif(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(frames.len(), 2);
+        assert!((frames[0].1 - 0.1).abs() < 1e-6);
+        assert!((frames[1].1 - 0.25).abs() < 1e-6);
+        assert_eq!(frames[0].0.pixel_rgba(1, 1), [255, 0, 0, 255]);
+        assert_eq!(frames[1].0.pixel_rgba(1, 1), [0, 0, 255, 255]);
+
+        assert!(matches!(load_gif(&path), Err(LoadError::Io(_))));
+        std::fs::write(&path, b"not a gif").unwrap();
+        assert!(matches!(load_gif(&path), Err(LoadError::Image(_))));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_load_gif_disposal() {
+        use gif::DisposalMethod::{Background, Keep};
+
+        let path = std::env::temp_dir().join("pixelated_test_load_gif_disposal.gif");
+        {
+            let file = File::create(&path).unwrap();
+            // Red, blue and a transparent black
+            let palette = [255, 0, 0, 0, 0, 255, 0, 0, 0];
+            let mut encoder = gif::Encoder::new(file, 2, 2, &palette).unwrap();
+            let frame = |left, top, size: u16, index: u8, dispose| gif::Frame {
+                left,
+                top,
+                width: size,
+                height: size,
+                dispose,
+                transparent: Some(2),
+                buffer: vec![index; size as usize * size as usize].into(),
+                ..Default::default()
+            };
+            encoder.write_frame(&frame(0, 0, 2, 0, Keep)).unwrap();
+            encoder.write_frame(&frame(0, 0, 1, 1, Background)).unwrap();
+            encoder.write_frame(&frame(1, 1, 1, 1, Keep)).unwrap();
+        }
+
+        let frames = load_gif(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let (red, blue) = ([255, 0, 0, 255], [0, 0, 255, 255]);
+        let pixels = |sprite: &Sprite| {
+            [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(x, y)| sprite.pixel_rgba(x, y))
+        };
+        // Kept frames stay under later frames
+        assert_eq!(pixels(&frames[1].0), [blue, red, red, red]);
+        // Background disposal clears the area of the disposed frame
+        assert_eq!(pixels(&frames[2].0), [[0; 4], red, red, blue]);
+    }
+
+    #[test]
+    fn test_ascii_art_split() {
+        // Left half black, right half white
+        let (width, height) = (8, 4);
+        let mut pixels = Vec::new();
+        for _ in 0..height {
+            for x in 0..width {
+                let value = if x < width / 2 { 0 } else { 255 };
+                pixels.extend_from_slice(&[value, value, value, 255]);
+            }
+        }
+
+        let text = ascii_art(&pixels, width, height, 4);
+
+        // 4 columns of an 8x4 canvas gives 1 row after aspect correction
+        assert_eq!(text, "  @@\n");
+    }
+
+    #[test]
+    fn test_ascii_art_empty() {
+        assert_eq!(ascii_art(&[], 0, 4, 4), "");
+        assert_eq!(ascii_art(&[], 4, 0, 4), "");
+    }
+
+    #[test]
+    fn test_padded_bytes_per_row() {
+        assert_eq!(padded_bytes_per_row(1), 256);
+        assert_eq!(padded_bytes_per_row(64), 256);
+        assert_eq!(padded_bytes_per_row(65), 512);
+    }
+
+    #[test]
+    fn test_unpad_rows() {
+        let padded = padded_bytes_per_row(2) as usize;
+        let mut data = vec![0; padded * 2];
+        data[..8].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        data[padded..padded + 8].copy_from_slice(&[9, 10, 11, 12, 13, 14, 15, 16]);
+
+        let pixels = unpad_rows(&data, 2, 2, padded as u32);
+
+        assert_eq!(pixels, (1..=16).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn test_capture_presented_shaded() {
+        // Skip on machines without any adapter
+        let Some(mut ctx) = headless_canvas((2, 1), (2, 1)) else {
+            return;
+        };
+        ctx.render.canvas.write_pixel(0, 0, &[255, 0, 0]);
+        ctx.render.canvas.write_pixel(1, 0, &[0, 0, 255]);
+        // Panning half the canvas in the shader swaps the pixels on screen only
+        render::set_address_mode(&mut ctx, render::AddressMode::Repeat);
+        render::set_pan(&mut ctx, 0.5, 0.0);
+        render::present(&mut ctx).unwrap();
+
+        let captured = pollster::block_on(capture_presented(&ctx)).unwrap();
+        assert_eq!(
+            captured.pixels,
+            [[0, 0, 255, 255], [255, 0, 0, 255]].concat()
+        );
+        assert_eq!(&ctx.render.canvas.pixels[..4], &[255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_read_surface_scaled() {
+        // Skip on machines without any adapter
+        let Some(mut ctx) = headless_canvas((2, 1), (4, 2)) else {
+            return;
+        };
+        ctx.render.canvas.write_pixel(0, 0, &[255, 0, 0]);
+        ctx.render.canvas.write_pixel(1, 0, &[0, 0, 255]);
+        render::present(&mut ctx).unwrap();
+
+        // Each canvas pixel covers 2x2 window pixels with nearest filtering
+        let presented = read_surface(&ctx).unwrap();
+        assert_eq!((presented.width, presented.height), (4, 2));
+        let (red, blue) = ([255, 0, 0, 255], [0, 0, 255, 255]);
+        let expected: Vec<u8> = [red, red, blue, blue, red, red, blue, blue].concat();
+        assert_eq!(presented.pixels, expected);
+    }
+
+    #[test]
+    fn test_read_texture() {
+        // Skip on machines without any adapter
+        let (device, queue) = match test_device() {
+            Some(device) => device,
+            None => return,
+        };
+
+        // Odd width so rows are padded, BGRA so channels are swapped back
+        let (width, height) = (3, 2);
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Bgra8Unorm,
+            usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let rgba: Vec<u8> = (0..width * height * 4).map(|i| i as u8 * 10).collect();
+        let bgra: Vec<u8> = rgba
+            .chunks(4)
+            .flat_map(|p| [p[2], p[1], p[0], p[3]])
+            .collect();
+        queue.write_texture(
+            texture.as_image_copy(),
+            &bgra,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(width * 4),
+                rows_per_image: std::num::NonZeroU32::new(height),
+            },
+            size,
+        );
+
+        let encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        let sprite = pollster::block_on(read_texture(&device, &queue, encoder, &texture)).unwrap();
+
+        assert_eq!((sprite.width, sprite.height), (width, height));
+        assert_eq!(sprite.pixels, rgba);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_project_round_trip() {
+        use super::{decode_project, encode_project, ProjectError};
+
+        let mut rng = Rng::seed(3);
+        let pixels: Vec<u8> = (0..3 * 2 * 4).map(|_| rng.next_u32() as u8).collect();
+
+        let canvas = Sprite::new(3, 2, pixels);
+        let text = encode_project(&canvas);
+        assert!(text.contains("\"version\": 1"));
+        assert_eq!(decode_project(&text).unwrap(), canvas);
+
+        let truncated = &text[..text.len() - 3];
+        assert!(matches!(
+            decode_project(truncated),
+            Err(ProjectError::Corrupt(_))
+        ));
+        let newer = r#"{"format": "pixelated-project", "version": 99, "layers": []}"#;
+        assert!(matches!(
+            decode_project(newer),
+            Err(ProjectError::UnsupportedVersion(99))
+        ));
+        let wrong_size = r#"{"format": "pixelated-project", "version": 1,
+            "canvas": {"pixels": [1, 2, 3], "width": 1, "height": 1}}"#;
+        assert!(matches!(
+            decode_project(wrong_size),
+            Err(ProjectError::Corrupt(_))
+        ));
+        for text in ["not a project", r#"{"format": "other", "version": 1}"#] {
+            assert!(matches!(
+                decode_project(text),
+                Err(ProjectError::Corrupt(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_resample_filters() {
+        // Every 2x2 block holds the values v, v + 4, v + 8 and v + 12
+        let pixels = (0..16u8)
+            .flat_map(|i| {
+                let (x, y) = (i % 4, i / 4);
+                let v = (y / 2 * 2 + x / 2) * 40 + (x % 2) * 4 + (y % 2) * 8;
+                [v, v, v, 255]
+            })
+            .collect::<Vec<_>>();
+        let pixel = |pixels: &[u8], i: usize| pixels[i * 4..i * 4 + 4].to_vec();
+
+        let boxed = resample(&pixels, (4, 4), (2, 2), ExportFilter::Box);
+        assert_eq!(boxed.len(), 2 * 2 * 4);
+        for (i, base) in [0, 40, 80, 120].into_iter().enumerate() {
+            assert_eq!(pixel(&boxed, i), [base + 6, base + 6, base + 6, 255]);
+        }
+
+        let nearest = resample(&pixels, (4, 4), (2, 2), ExportFilter::Nearest);
+        for (i, base) in [0, 40, 80, 120].into_iter().enumerate() {
+            assert_eq!(pixel(&nearest, i), [base + 12, base + 12, base + 12, 255]);
+        }
+
+        // Same size is a copy for every filter
+        for filter in [
+            ExportFilter::Nearest,
+            ExportFilter::Bilinear,
+            ExportFilter::Box,
+        ] {
+            assert_eq!(resample(&pixels, (4, 4), (4, 4), filter), pixels);
+        }
+        let upscaled = resample(&pixels, (4, 4), (8, 8), ExportFilter::Bilinear);
+        assert_eq!(upscaled.len(), 8 * 8 * 4);
+        assert_eq!(pixel(&upscaled, 0), pixel(&pixels, 0));
+    }
+
+    #[test]
+    fn test_compare() {
+        let a = Sprite::filled(3, 2, &[90, 90, 90, 255]);
+        let same = compare(&a, &a.clone(), 0);
+        assert!(same.matches());
+        assert_eq!((same.differing_pixels, same.max_delta), (0, 0));
+        assert_eq!(same.diff.unwrap().pixel_rgba(0, 0), [30, 30, 30, 255]);
+
+        let mut b = a.clone();
+        b.write_pixel(2, 1, &[90, 100, 90, 255]);
+        let different = compare(&a, &b, 0);
+        assert!(!different.matches());
+        assert_eq!((different.differing_pixels, different.max_delta), (1, 10));
+        let diff = different.diff.unwrap();
+        assert_eq!(diff.pixel_rgba(2, 1), DIFF_COLOR);
+        assert_ne!(diff.pixel_rgba(1, 1), DIFF_COLOR);
+
+        // Within tolerance
+        let near = compare(&a, &b, 10);
+        assert!(near.matches());
+        assert_eq!(near.max_delta, 10);
+
+        let resized = compare(&a, &Sprite::filled(2, 3, &[90, 90, 90, 255]), 255);
+        assert!(!resized.size_matches && !resized.matches());
+        assert!(resized.diff.is_none());
+    }
+
+    #[test]
+    fn test_unique_screenshot_paths() {
+        assert_eq!(format_timestamp(0), "1970-01-01_00-00-00");
+        assert_eq!(format_timestamp(951_782_400 + 3661), "2000-02-29_01-01-01");
+
+        let dir = std::env::temp_dir().join("pixelated_test_auto_screenshots");
+        let _ = std::fs::remove_dir_all(&dir);
+        let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        let capture = || {
+            write_unique(&dir, "shot", time, |path| {
+                RgbaImage::from_raw(1, 1, vec![1, 2, 3, 255])
+                    .unwrap()
+                    .save(path)
+            })
+            .unwrap()
+        };
+
+        let first = capture();
+        let second = capture();
+        assert_ne!(first, second);
+        assert!(first.exists() && second.exists());
+        assert_eq!(first.file_name().unwrap(), "shot_2023-11-14_22-13-20.png");
+        assert_eq!(
+            second.file_name().unwrap(),
+            "shot_2023-11-14_22-13-20_1.png"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_palette_round_trip() {
+        let palette = Palette {
+            colors: vec![[255, 0, 0, 255], [16, 32, 48, 255], [0, 0, 0, 255]],
+        };
+        let path = std::env::temp_dir().join("pixelated_test_palette.hex");
+        std::fs::write(&path, encode_palette(&palette, PaletteFormat::Hex)).unwrap();
+        assert_eq!(import_palette(&path).unwrap(), palette);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            encode_palette(&palette, PaletteFormat::Hex),
+            "ff0000\n102030\n000000\n"
+        );
+        let translucent = Palette {
+            colors: vec![[1, 2, 3, 4], [250, 251, 252, 255]],
+        };
+        for format in [PaletteFormat::Hex, PaletteFormat::Json] {
+            let text = encode_palette(&translucent, format);
+            assert_eq!(decode_palette(&text).unwrap(), translucent);
+        }
+        // GIMP palettes have no alpha
+        let text = encode_palette(&palette, PaletteFormat::Gpl);
+        assert!(text.starts_with("GIMP Palette\n"));
+        assert_eq!(decode_palette(&text).unwrap(), palette);
+        let empty = encode_palette(&Palette::default(), PaletteFormat::Json);
+        assert_eq!(decode_palette(&empty).unwrap(), Palette::default());
+
+        for text in [
+            "ff00",
+            "gg0000",
+            "GIMP Palette\n1 2",
+            "GIMP Palette\n1 2 300",
+            "{}",
+        ] {
+            assert!(matches!(decode_palette(text), Err(LoadError::Malformed(_))));
+        }
+        assert!(matches!(
+            import_palette("missing_palette.hex"),
+            Err(LoadError::Io(_))
+        ));
+    }
 }
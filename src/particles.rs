@@ -0,0 +1,207 @@
+use crate::{canvas::Canvas, util::Rng, Context};
+
+/// How new particles are spawned by ```ParticleSystem::emit```
+///
+/// Ranges are (min, max), each particle picks a random value inside them
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmitConfig {
+    /// Spawn position in pixels
+    pub position: (f32, f32),
+    /// Direction of the initial velocity in radians
+    pub angle: (f32, f32),
+    /// Initial speed in pixels per second
+    pub speed: (f32, f32),
+    /// Time alive in seconds
+    pub lifetime: (f32, f32),
+    /// Color: Non premultiplied RGBA \[0,255\]
+    pub color: [u8; 4],
+    /// Radius of the drawn particle, 0 draws a single pixel
+    pub radius: u32,
+}
+
+impl Default for EmitConfig {
+    fn default() -> Self {
+        Self {
+            position: (0.0, 0.0),
+            angle: (0.0, std::f32::consts::TAU),
+            speed: (10.0, 50.0),
+            lifetime: (1.0, 2.0),
+            color: [255, 255, 255, 255],
+            radius: 0,
+        }
+    }
+}
+
+/// Simple particle simulation
+///
+/// Particles are stored in arrays and dead particles are reused by new emits
+#[derive(Debug, Clone, Default)]
+pub struct ParticleSystem {
+    /// Acceleration applied to all particles in pixels per second squared
+    pub gravity: (f32, f32),
+    /// Fraction of velocity lost per second
+    pub drag: f32,
+    positions: Vec<(f32, f32)>,
+    velocities: Vec<(f32, f32)>,
+    ages: Vec<f32>,
+    lifetimes: Vec<f32>,
+    colors: Vec<[u8; 4]>,
+    radii: Vec<u32>,
+}
+
+impl ParticleSystem {
+    /// Create empty particle system without gravity or drag
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spawn count particles
+    ///
+    /// Pass ```ctx.rng()``` for the application wide generator
+    pub fn emit(&mut self, rng: &mut Rng, count: u32, config: &EmitConfig) {
+        let mut dead = (0..self.ages.len())
+            .filter(|&i| !self.is_alive(i))
+            .collect::<Vec<_>>()
+            .into_iter();
+        for _ in 0..count {
+            let angle = rng.range_f32(config.angle.0, config.angle.1);
+            let speed = rng.range_f32(config.speed.0, config.speed.1);
+            let lifetime = rng.range_f32(config.lifetime.0, config.lifetime.1);
+            let velocity = (angle.cos() * speed, angle.sin() * speed);
+            match dead.next() {
+                Some(i) => {
+                    self.positions[i] = config.position;
+                    self.velocities[i] = velocity;
+                    self.ages[i] = 0.0;
+                    self.lifetimes[i] = lifetime;
+                    self.colors[i] = config.color;
+                    self.radii[i] = config.radius;
+                }
+                None => {
+                    self.positions.push(config.position);
+                    self.velocities.push(velocity);
+                    self.ages.push(0.0);
+                    self.lifetimes.push(lifetime);
+                    self.colors.push(config.color);
+                    self.radii.push(config.radius);
+                }
+            }
+        }
+    }
+
+    /// Moves and ages all live particles
+    ///
+    /// dt: Time since last update in seconds
+    pub fn update(&mut self, dt: f32) {
+        let drag = (1.0 - self.drag * dt).max(0.0);
+        for i in 0..self.ages.len() {
+            if !self.is_alive(i) {
+                continue;
+            }
+            let (vx, vy) = &mut self.velocities[i];
+            *vx = (*vx + self.gravity.0 * dt) * drag;
+            *vy = (*vy + self.gravity.1 * dt) * drag;
+            let (x, y) = &mut self.positions[i];
+            *x += *vx * dt;
+            *y += *vy * dt;
+            self.ages[i] += dt;
+        }
+    }
+
+    /// Number of particles currently alive
+    pub fn alive_count(&self) -> usize {
+        (0..self.ages.len()).filter(|&i| self.is_alive(i)).count()
+    }
+
+    /// Draw all live particles to the canvas
+    pub fn draw(&self, ctx: &mut Context) {
+        self.draw_to(&mut ctx.render.canvas);
+    }
+
+    pub(crate) fn draw_to(&self, canvas: &mut Canvas) {
+        for i in 0..self.ages.len() {
+            if !self.is_alive(i) {
+                continue;
+            }
+            let x = self.positions[i].0.round() as i32;
+            let y = self.positions[i].1.round() as i32;
+            match self.radii[i] {
+                0 => canvas.plot(x, y, &self.colors[i]),
+                radius => canvas.fill_circle_aa(x, y, radius, &self.colors[i]),
+            }
+        }
+    }
+
+    fn is_alive(&self, i: usize) -> bool {
+        self.ages[i] < self.lifetimes[i]
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::{EmitConfig, ParticleSystem};
+    use crate::{canvas::Canvas, util::Rng};
+
+    #[test]
+    fn test_particles_age_out() {
+        let mut rng = Rng::seed(0);
+        let mut particles = ParticleSystem::new();
+        let config = EmitConfig {
+            position: (4.0, 4.0),
+            speed: (0.0, 0.0),
+            lifetime: (1.0, 1.0),
+            ..Default::default()
+        };
+
+        particles.emit(&mut rng, 3, &config);
+        particles.update(0.5);
+
+        assert_eq!(particles.alive_count(), 3);
+        let mut canvas = Canvas::new(8, 8);
+        particles.draw_to(&mut canvas);
+        assert_eq!(canvas.pixel_rgba(4, 4), [255, 255, 255, 255]);
+
+        particles.update(0.6);
+
+        assert_eq!(particles.alive_count(), 0);
+        let mut canvas = Canvas::new(8, 8);
+        particles.draw_to(&mut canvas);
+        assert!(canvas.pixels.iter().all(|&p| p == 0));
+    }
+
+    #[test]
+    fn test_dead_particles_are_reused() {
+        let mut rng = Rng::seed(0);
+        let mut particles = ParticleSystem::new();
+        let config = EmitConfig {
+            lifetime: (1.0, 1.0),
+            ..Default::default()
+        };
+
+        particles.emit(&mut rng, 4, &config);
+        particles.update(2.0);
+        particles.emit(&mut rng, 2, &config);
+
+        assert_eq!(particles.ages.len(), 4);
+        assert_eq!(particles.alive_count(), 2);
+    }
+
+    #[test]
+    fn test_gravity() {
+        let mut rng = Rng::seed(0);
+        let mut particles = ParticleSystem {
+            gravity: (0.0, 10.0),
+            ..Default::default()
+        };
+        let config = EmitConfig {
+            speed: (0.0, 0.0),
+            ..Default::default()
+        };
+
+        particles.emit(&mut rng, 1, &config);
+        particles.update(1.0);
+
+        assert_eq!(particles.positions[0], (0.0, 10.0));
+    }
+}
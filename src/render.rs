@@ -1,34 +1,77 @@
 use crate::{
-    canvas::{Canvas, DEFAULT_CANVAS_HEIGHT, DEFAULT_CANVAS_WIDTH},
-    media::ScreenshotUploader,
+    canvas::{check_texture_size, Canvas, CanvasFormat, CanvasId, DirtyRegion, ResizeMode},
+    config::Config,
+    media::{padded_bytes_per_row, ScreenshotUploader},
+    Context,
 };
+use std::sync::{Arc, Mutex};
 use wgpu::{util::DeviceExt, Adapter, Device, PresentMode, Surface, SurfaceConfiguration};
 use winit::window::Window;
 
-pub struct RenderContext {
+pub(crate) struct RenderContext {
     #[allow(dead_code)]
     pub(crate) adapter: wgpu::Adapter,
-    pub(crate) device: wgpu::Device,
+    pub(crate) device: Arc<wgpu::Device>,
     pub(crate) queue: wgpu::Queue,
-    pub(crate) surface: wgpu::Surface,
+    /// Kept to recreate the surface on resume
+    instance: wgpu::Instance,
+    /// None while the app is suspended
+    pub(crate) surface: Option<wgpu::Surface>,
     pub(crate) surface_config: wgpu::SurfaceConfiguration,
-    pub(crate) window: Window,
+    /// None for headless contexts, which only draw frames to ```presented```
+    pub(crate) window: Option<Window>,
+    /// Frames are drawn here and copied to the surface so they can be read back
+    /// None while the app is suspended
+    pub(crate) presented: Option<PresentedTexture>,
+    presented_bind_group_layout: wgpu::BindGroupLayout,
+    /// Copies ```presented``` to the surface texture
+    present_pipeline: wgpu::RenderPipeline,
+    /// Drawn over the surface in place of the hidden OS cursor
+    pub(crate) cursor: Option<CursorSprite>,
 
     pub(crate) canvas: Canvas,
+    /// CPU only canvases created with ```create_offscreen```
+    pub(crate) offscreens: Vec<Canvas>,
+    pub(crate) canvas_format: CanvasFormat,
+    pub(crate) upscale_filter: UpscaleFilter,
+    pub(crate) address_mode: AddressMode,
 
     pub(crate) screenshot_uploader: ScreenshotUploader,
+    /// Skip drawing to the surface, see ```Config::software_canvas_only```
+    pub(crate) software_canvas_only: bool,
 
     pub(crate) vertex_buffer: wgpu::Buffer,
     pub(crate) index_buffer: wgpu::Buffer,
     pub(crate) render_pipeline: wgpu::RenderPipeline,
 
     pub(crate) texture: wgpu::Texture,
+    pub(crate) texture_bind_group_layout: wgpu::BindGroupLayout,
     pub(crate) texture_bind_group: wgpu::BindGroup,
+
+    pub(crate) layout: Layout,
+    /// Scaling of the locked logical resolution, None stretches the canvas
+    pub(crate) logical_scaling: Option<LogicalScaling>,
+    /// Width of a presented canvas pixel relative to its height
+    pub(crate) pixel_aspect: f32,
+    /// Texture of the offscreen canvas shown next to the canvas, created on demand
+    pub(crate) secondary: Option<SecondaryTexture>,
+
+    pub(crate) uniforms: Uniforms,
+    /// Fraction of a canvas pixel the presented quad is shifted by
+    pub(crate) subpixel_offset: (f32, f32),
+    pub(crate) uniform_buffer: wgpu::Buffer,
+    pub(crate) uniform_bind_group_layout: wgpu::BindGroupLayout,
+    pub(crate) uniform_bind_group: wgpu::BindGroup,
+
+    /// GPU errors not yet passed to ```Callbacks::on_render_error```
+    pub(crate) errors: Arc<Mutex<Vec<RenderError>>>,
+
+    pub(crate) gpu_timer: Option<GpuTimer>,
 }
 
 impl RenderContext {
     // Creating some of the wgpu types requires async code
-    pub(crate) async fn new(window: Window) -> Self {
+    pub(crate) async fn new(window: Window, config: &Config) -> Self {
         // Create surface
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
             backends: wgpu::Backends::all(),
@@ -38,38 +81,174 @@ impl RenderContext {
             unsafe { instance.create_surface(&window) }.expect("could not create surface");
 
         // Create adapter. device and queue
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
+        let named = config
+            .adapter_name_filter
+            .as_deref()
+            .and_then(|filter| named_adapter(&instance, &surface, filter));
+        let adapter = match named {
+            Some(adapter) => adapter,
+            None => instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::default(),
+                    compatible_surface: Some(&surface),
+                    force_fallback_adapter: false,
+                })
+                .await
+                .unwrap(),
+        };
+        let info = adapter.get_info();
+        log::info!("using adapter {} ({:?})", info.name, info.backend);
         let (device, queue) = adapter
             .request_device(
-                &wgpu::DeviceDescriptor {
-                    features: wgpu::Features::empty(),
-                    limits: wgpu::Limits::default(),
-                    label: None,
-                },
+                &device_descriptor(config, adapter.features()),
                 None, // Trace path
             )
             .await
             .unwrap();
 
         // Configure surface
-        let surface_config =
-            create_surface_config(&window, &surface, &adapter, PresentMode::AutoVsync);
+        let canvas_format = config.canvas_format;
+        let surface_config = create_surface_config(
+            &window,
+            &surface,
+            &adapter,
+            PresentMode::AutoVsync,
+            canvas_format.srgb(),
+            config.transparent,
+            config.surface_format,
+        );
         surface.configure(&device, &surface_config);
 
+        Self::with_device(
+            instance,
+            adapter,
+            device,
+            queue,
+            Some(window),
+            Some(surface),
+            surface_config,
+            config,
+        )
+    }
+
+    /// Creates a context without a window, frames are only drawn to ```presented```
+    /// None on machines without any adapter
+    #[cfg(test)]
+    pub(crate) fn headless(config: &Config, width: u32, height: u32) -> Option<Self> {
+        let (instance, adapter) = test_adapter()?;
+        let (device, queue) = pollster::block_on(
+            adapter.request_device(&device_descriptor(config, adapter.features()), None),
+        )
+        .ok()?;
+        let formats = [
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            wgpu::TextureFormat::Rgba8Unorm,
+        ];
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: select_surface_format(&formats, config.canvas_format.srgb(), None)?,
+            width,
+            height,
+            present_mode: PresentMode::AutoVsync,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+        };
+
+        Some(Self::with_device(
+            instance,
+            adapter,
+            device,
+            queue,
+            None,
+            None,
+            surface_config,
+            config,
+        ))
+    }
+
+    /// Creates the canvas pipeline and state on top of a configured device
+    #[allow(clippy::too_many_arguments)]
+    fn with_device(
+        instance: wgpu::Instance,
+        adapter: wgpu::Adapter,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        window: Option<Window>,
+        surface: Option<wgpu::Surface>,
+        surface_config: wgpu::SurfaceConfiguration,
+        config: &Config,
+    ) -> Self {
+        let device = Arc::new(device);
+
+        // Report GPU errors instead of panicking
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let handler_errors = errors.clone();
+        device.on_uncaptured_error(Box::new(move |error: wgpu::Error| {
+            handler_errors.lock().unwrap().push(error.into())
+        }));
+
+        let presented_bind_group_layout = create_presented_bind_group_layout(&device);
+        let presented =
+            PresentedTexture::new(&device, &presented_bind_group_layout, &surface_config);
+        let present_pipeline =
+            create_present_pipeline(&device, &presented_bind_group_layout, &surface_config);
+
+        // Uniforms
+        let uniforms = Uniforms::default();
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Uniform Buffer"),
+            contents: bytemuck::bytes_of(&uniforms),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let uniform_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("uniform_bind_group_layout"),
+            });
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &uniform_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+            label: Some("uniform_bind_group"),
+        });
+
         // Create pipeline
-        let (render_pipeline, texture, diffuse_bind_group) = create_pipeline(
-            &device,
-            &surface_config,
-            DEFAULT_CANVAS_WIDTH,
-            DEFAULT_CANVAS_HEIGHT,
-        );
+        let canvas_format = config.canvas_format;
+        let (mut canvas_width, mut canvas_height) = config.canvas_size();
+        let max = device.limits().max_texture_dimension_2d;
+        if let Err(err) = check_texture_size(canvas_width, canvas_height, max) {
+            canvas_width = canvas_width.min(max);
+            canvas_height = canvas_height.min(max);
+            log::warn!("{}, clamped to {}x{}", err, canvas_width, canvas_height);
+        }
+        let upscale_filter = UpscaleFilter::Nearest;
+        let address_mode = AddressMode::ClampToEdge;
+        let texture_bind_group_layout = create_texture_bind_group_layout(&device);
+        let (render_pipeline, texture, diffuse_bind_group) = capture_errors(&device, || {
+            create_pipeline(
+                &device,
+                &surface_config,
+                &texture_bind_group_layout,
+                canvas_width,
+                canvas_height,
+                canvas_format.texture_format(),
+                upscale_filter,
+                address_mode,
+                &uniform_bind_group_layout,
+            )
+        })
+        .unwrap_or_else(|err| panic!("could not create canvas pipeline: {}", err));
 
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Vertex Buffer"),
@@ -83,13 +262,22 @@ impl RenderContext {
             usage: wgpu::BufferUsages::INDEX,
         });
 
+        let gpu_timer = device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY)
+            .then(|| GpuTimer::new(&device, &queue));
+
         // Media
-        let canvas = Canvas::new(DEFAULT_CANVAS_WIDTH, DEFAULT_CANVAS_HEIGHT);
-        let screenshot_uploader =
-            ScreenshotUploader::new(DEFAULT_CANVAS_WIDTH, DEFAULT_CANVAS_HEIGHT);
+        let canvas = Canvas::with_supersample(canvas_width, canvas_height, config.supersample);
+        let screenshot_uploader = ScreenshotUploader::new(canvas_width, canvas_height);
 
         Self {
             window,
+            presented: Some(presented),
+            presented_bind_group_layout,
+            present_pipeline,
+            cursor: None,
+            instance,
             surface,
             device,
             adapter,
@@ -99,91 +287,1090 @@ impl RenderContext {
             vertex_buffer,
             index_buffer,
             texture_bind_group: diffuse_bind_group,
+            texture_bind_group_layout,
             texture,
+            layout: Layout::Single,
+            secondary: None,
             canvas,
+            offscreens: Vec::new(),
+            canvas_format,
+            upscale_filter,
+            address_mode,
             screenshot_uploader,
+            software_canvas_only: config.software_canvas_only,
+            logical_scaling: config.logical_resolution.map(|_| config.logical_scaling),
+            pixel_aspect: 1.0,
+            uniforms,
+            subpixel_offset: (0.0, 0.0),
+            uniform_buffer,
+            uniform_bind_group_layout,
+            uniform_bind_group,
+            gpu_timer,
+            errors,
         }
     }
 
+    /// Recreates the canvas texture and pipeline
+    /// Keeps the previous ones and reports the error if creation fails
     pub(crate) fn resize_canvas_texture(&mut self, width: u32, height: u32) {
-        let (pipeline, texture, bind_group) =
-            create_pipeline(&self.device, &self.surface_config, width, height);
+        match self.create_canvas_pipeline(width, height) {
+            Ok(created) => self.set_canvas_pipeline(created),
+            Err(err) => self.errors.lock().unwrap().push(err),
+        }
+    }
+
+    /// Resizes the canvas and recreates its texture and pipeline
+    /// Leaves the canvas untouched and reports the error if creation fails
+    pub(crate) fn resize_canvas(&mut self, width: u32, height: u32, mode: ResizeMode) {
+        let created = match self.create_canvas_pipeline(width, height) {
+            Ok(created) => created,
+            Err(err) => {
+                self.errors.lock().unwrap().push(err);
+                return;
+            }
+        };
+        self.canvas.resize_with(width, height, mode);
+        self.screenshot_uploader.resize(width, height);
+        self.set_canvas_pipeline(created);
+    }
+
+    /// Creates the texture and pipeline for a width x height canvas
+    fn create_canvas_pipeline(
+        &self,
+        width: u32,
+        height: u32,
+    ) -> Result<(wgpu::RenderPipeline, wgpu::Texture, wgpu::BindGroup), RenderError> {
+        capture_errors(&self.device, || {
+            create_pipeline(
+                &self.device,
+                &self.surface_config,
+                &self.texture_bind_group_layout,
+                width,
+                height,
+                self.canvas_format.texture_format(),
+                self.upscale_filter,
+                self.address_mode,
+                &self.uniform_bind_group_layout,
+            )
+        })
+    }
+
+    /// Replaces the canvas texture and pipeline with ones matching the canvas size
+    fn set_canvas_pipeline(
+        &mut self,
+        created: (wgpu::RenderPipeline, wgpu::Texture, wgpu::BindGroup),
+    ) {
+        let (pipeline, texture, bind_group) = created;
         self.render_pipeline = pipeline;
         self.texture = texture;
         self.texture_bind_group = bind_group;
+        // New texture is empty
+        self.canvas.mark_all_dirty();
+        // Recreated with the new settings on the next upload
+        self.secondary = None;
+        // A canvas pixel covers a different part of the quad
+        let (dx, dy) = self.subpixel_offset;
+        self.set_subpixel_offset(dx, dy);
+    }
+
+    pub(crate) fn set_upscale_filter(&mut self, upscale_filter: UpscaleFilter) {
+        self.upscale_filter = upscale_filter;
+        let (width, height) = self.canvas.logical_size();
+        self.resize_canvas_texture(width, height);
+    }
+
+    pub(crate) fn set_address_mode(&mut self, address_mode: AddressMode) {
+        self.address_mode = address_mode;
+        let (width, height) = self.canvas.logical_size();
+        self.resize_canvas_texture(width, height);
+    }
+
+    pub(crate) fn set_pan(&mut self, u_offset: f32, v_offset: f32) {
+        self.uniforms.pan = wrap_pan(u_offset, v_offset);
+        self.queue
+            .write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&self.uniforms));
+    }
+
+    /// Shifts the presented quad by a fraction of a canvas pixel
+    /// Whole pixels of the offset scroll the canvas instead
+    pub(crate) fn set_subpixel_offset(&mut self, dx: f32, dy: f32) {
+        let (whole_x, fraction_x) = split_subpixel(dx);
+        let (whole_y, fraction_y) = split_subpixel(dy);
+        self.canvas.scroll(whole_x, whole_y);
+        self.subpixel_offset = (fraction_x, fraction_y);
+        let (width, height) = self.canvas.logical_size();
+        self.uniforms.offset = subpixel_clip_offset(fraction_x, fraction_y, width, height);
+        self.queue
+            .write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&self.uniforms));
     }
 
     pub(crate) fn reconfigure_present_mode(&mut self, present_mode: PresentMode) {
         self.surface_config.present_mode = present_mode;
-        self.surface.configure(&self.device, &self.surface_config);
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.surface_config);
+        }
     }
 
     pub(crate) fn resize_window(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.surface_config.width = new_size.width;
             self.surface_config.height = new_size.height;
-            self.surface.configure(&self.device, &self.surface_config);
+            if let Some(surface) = &self.surface {
+                surface.configure(&self.device, &self.surface_config);
+            }
+            if self.presented.is_some() {
+                self.presented = Some(self.create_presented());
+            }
         }
     }
 
-    pub(crate) fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        // Update texture
-        self.queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &self.texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            self.canvas.pixels.as_slice(),
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: std::num::NonZeroU32::new(4 * self.canvas.width),
-                rows_per_image: std::num::NonZeroU32::new(self.canvas.height),
-            },
-            self.texture.size(),
-        );
+    /// Drops the surface, the platform may destroy the window while suspended
+    pub(crate) fn drop_surface(&mut self) {
+        self.surface = None;
+        self.presented = None;
+    }
+
+    /// Creates a new surface for the window and configures it to the window size
+    /// Headless contexts only recreate ```presented```
+    pub(crate) fn recreate_surface(&mut self) {
+        if let Some(window) = &self.window {
+            let surface =
+                unsafe { self.instance.create_surface(window) }.expect("could not create surface");
+            self.surface = Some(surface);
+        }
+        self.resize_window(self.window_size());
+        self.presented = Some(self.create_presented());
+    }
+
+    fn create_presented(&self) -> PresentedTexture {
+        PresentedTexture::new(
+            &self.device,
+            &self.presented_bind_group_layout,
+            &self.surface_config,
+        )
+    }
+
+    /// Returns true if events with id belong to the window, headless contexts accept any id
+    pub(crate) fn is_window(&self, id: winit::window::WindowId) -> bool {
+        self.window
+            .as_ref()
+            .map_or(true, |window| window.id() == id)
+    }
+
+    /// Size of the window in physical pixels, the surface size for headless contexts
+    pub(crate) fn window_size(&self) -> winit::dpi::PhysicalSize<u32> {
+        match &self.window {
+            Some(window) => window.inner_size(),
+            None => {
+                winit::dpi::PhysicalSize::new(self.surface_config.width, self.surface_config.height)
+            }
+        }
+    }
+
+    /// Acquires the surface texture and draws the canvas to ```presented```
+    /// Finish with ```end_frame```
+    pub(crate) fn begin_frame(&mut self) -> Result<Frame, wgpu::SurfaceError> {
+        // Read back timestamps from previous frames
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.poll(&self.device);
+        }
+
+        self.upload_canvas();
 
         // Render texture
-        let output = self.surface.get_current_texture()?;
-        let view = output
+        let view = self
+            .presented
+            .as_ref()
+            .ok_or(wgpu::SurfaceError::Lost)?
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
+        let output = match &self.window {
+            Some(_) => Some(
+                self.surface
+                    .as_ref()
+                    .ok_or(wgpu::SurfaceError::Lost)?
+                    .get_current_texture()?,
+            ),
+            None => None,
+        };
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
-        {
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.begin(&mut encoder);
+        }
+
+        self.draw_canvas(&mut encoder, &view);
+
+        Ok(Frame {
+            output,
+            view,
+            encoder,
+        })
+    }
+
+    /// Copies ```presented``` to the surface texture, submits the frame encoder and presents
+    pub(crate) fn end_frame(&mut self, frame: Frame) {
+        let Frame {
+            output,
+            mut encoder,
+            ..
+        } = frame;
+        if let (Some(output), Some(presented)) = (&output, &self.presented) {
+            let view = output
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default());
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
+                label: Some("Present Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                     view: &view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
                         store: true,
                     },
                 })],
                 depth_stencil_attachment: None,
             });
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, &self.texture_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-            render_pass.draw_indexed(0..SCREEN_QUAD_INDICES.len() as u32, 0, 0..1);
+            render_pass.set_pipeline(&self.present_pipeline);
+            render_pass.set_bind_group(0, &presented.bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+
+            if let Some(cursor) = &self.cursor {
+                let target = (self.surface_config.width, self.surface_config.height);
+                cursor.draw(&self.queue, &mut render_pass, target);
+            }
+        }
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.end(&mut encoder);
         }
 
         self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
+        if let Some(output) = output {
+            output.present();
+        }
 
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.map();
+        }
+    }
+
+    /// Uploads the canvas and presents a frame without a custom render pass
+    pub(crate) fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        let frame = self.begin_frame()?;
+        self.end_frame(frame);
         Ok(())
     }
+
+    /// Draws the RGBA pixels at the cursor instead of the OS cursor, (hotspot_x, hotspot_y) is placed at the cursor
+    pub(crate) fn set_cursor_sprite(
+        &mut self,
+        size: (u32, u32),
+        pixels: &[u8],
+        hotspot: (u32, u32),
+    ) {
+        self.cursor = Some(CursorSprite::new(
+            &self.device,
+            &self.queue,
+            &self.surface_config,
+            size,
+            pixels,
+            hotspot,
+        ));
+    }
+
+    /// Window region (x, y, w, h) the canvas is mapped to for input
+    /// The canvas region of the layout unless a logical resolution is scaled into it
+    pub(crate) fn canvas_viewport(&self) -> (u32, u32, u32, u32) {
+        let size = self.window_size();
+        scaled_viewport(
+            layout_regions(self.layout, (size.width, size.height))[0],
+            self.canvas.logical_size(),
+            self.logical_scaling,
+            self.pixel_aspect,
+        )
+    }
+
+    /// Returns and clears the GPU errors reported since the last call
+    pub(crate) fn take_errors(&self) -> Vec<RenderError> {
+        std::mem::take(&mut *self.errors.lock().unwrap())
+    }
+
+    /// Copies the canvas pixels changed since the last upload to the canvas texture
+    /// Also uploads the offscreen canvas shown by the layout
+    pub(crate) fn upload_canvas(&mut self) {
+        upload_dirty(&self.queue, &self.texture, &mut self.canvas);
+
+        let id = match self.layout.secondary() {
+            Some(id) => id,
+            None => return,
+        };
+        let canvas = &mut self.offscreens[id.0];
+        let size = canvas.logical_size();
+        if self.secondary.as_ref().map(|s| s.size) != Some(size) {
+            let (texture, bind_group) = create_canvas_texture(
+                &self.device,
+                &self.texture_bind_group_layout,
+                size.0,
+                size.1,
+                self.canvas_format.texture_format(),
+                self.upscale_filter,
+                self.address_mode,
+            );
+            self.secondary = Some(SecondaryTexture {
+                texture,
+                bind_group,
+                size,
+            });
+            canvas.mark_all_dirty();
+        }
+        let secondary = self.secondary.as_ref().unwrap();
+        upload_dirty(&self.queue, &secondary.texture, canvas);
+    }
+
+    pub(crate) fn set_layout(&mut self, layout: Layout) {
+        self.layout = layout;
+        self.secondary = None;
+    }
+
+    /// Draws the canvas texture to view
+    /// View must have the surface format
+    pub(crate) fn draw_canvas(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear_color(self.surface_config.alpha_mode)),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(1, &self.uniform_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+
+        let size = (self.surface_config.width, self.surface_config.height);
+        let mut bind_groups = vec![&self.texture_bind_group];
+        if let Some(secondary) = &self.secondary {
+            bind_groups.push(&secondary.bind_group);
+        }
+        let mut canvas_sizes = vec![self.canvas.logical_size()];
+        if let Some(secondary) = &self.secondary {
+            canvas_sizes.push(secondary.size);
+        }
+        for ((bind_group, canvas_size), region) in bind_groups
+            .into_iter()
+            .zip(canvas_sizes)
+            .zip(layout_regions(self.layout, size))
+        {
+            let (x, y, w, h) =
+                scaled_viewport(region, canvas_size, self.logical_scaling, self.pixel_aspect);
+            if w == 0 || h == 0 {
+                continue;
+            }
+            render_pass.set_viewport(x as f32, y as f32, w as f32, h as f32, 0.0, 1.0);
+            render_pass.set_bind_group(0, bind_group, &[]);
+            render_pass.draw_indexed(0..SCREEN_QUAD_INDICES.len() as u32, 0, 0..1);
+        }
+    }
+}
+
+/// Measures the time the GPU spends on a frame using timestamp queries
+///
+/// Results are read back without blocking so they lag behind a few frames
+pub(crate) struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback: TimestampReadback,
+    period: f32,
+    recording: bool,
+    last_time: Option<f32>,
+}
+
+impl GpuTimer {
+    pub(crate) fn new(device: &Device, queue: &wgpu::Queue) -> Self {
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("Timestamp Query Set"),
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        });
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Timestamp Resolve Buffer"),
+            size: TimestampReadback::SIZE,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            query_set,
+            resolve_buffer,
+            readback: TimestampReadback::new(device),
+            period: queue.get_timestamp_period(),
+            recording: false,
+            last_time: None,
+        }
+    }
+
+    /// Writes the start timestamp
+    /// Skipped while the previous result is still being read back
+    pub(crate) fn begin(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        self.recording = !self.readback.in_flight;
+        if self.recording {
+            encoder.write_timestamp(&self.query_set, 0);
+        }
+    }
+
+    /// Writes the end timestamp and copies the result to the readback buffer
+    pub(crate) fn end(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if !self.recording {
+            return;
+        }
+        encoder.write_timestamp(&self.query_set, 1);
+        encoder.resolve_query_set(&self.query_set, 0..2, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback.buffer,
+            0,
+            TimestampReadback::SIZE,
+        );
+    }
+
+    /// Starts mapping the readback buffer
+    /// Should be called after submit
+    pub(crate) fn map(&mut self) {
+        if !self.recording {
+            return;
+        }
+        self.recording = false;
+        self.readback.map();
+    }
+
+    /// Reads the result if the readback buffer has been mapped
+    pub(crate) fn poll(&mut self, device: &Device) {
+        if let Some([start, end]) = self.readback.poll(device) {
+            self.last_time = Some(timestamp_duration(start, end, self.period));
+        }
+    }
+}
+
+/// Buffer the start and end timestamps are mapped from without blocking
+struct TimestampReadback {
+    buffer: wgpu::Buffer,
+    in_flight: bool,
+    /// Set by the map callback once the map has finished
+    map_result: Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>,
+}
+
+impl TimestampReadback {
+    const SIZE: u64 = 2 * std::mem::size_of::<u64>() as u64;
+
+    fn new(device: &Device) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Timestamp Readback Buffer"),
+            size: Self::SIZE,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self {
+            buffer,
+            in_flight: false,
+            map_result: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    fn map(&mut self) {
+        self.in_flight = true;
+        let map_result = self.map_result.clone();
+        self.buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                *map_result.lock().unwrap() = Some(result);
+            });
+    }
+
+    /// Returns the timestamps once the map has finished
+    /// A failed map is dropped so the next frame is measured again
+    fn poll(&mut self, device: &Device) -> Option<[u64; 2]> {
+        if !self.in_flight {
+            return None;
+        }
+        device.poll(wgpu::Maintain::Poll);
+        let result = self.map_result.lock().unwrap().take()?;
+        self.in_flight = false;
+        if let Err(err) = result {
+            log::warn!("could not read back gpu timestamps: {}", err);
+            return None;
+        }
+
+        let timestamps = {
+            let data = self.buffer.slice(..).get_mapped_range();
+            let timestamps: &[u64] = bytemuck::cast_slice(&data);
+            [timestamps[0], timestamps[1]]
+        };
+        self.buffer.unmap();
+        Some(timestamps)
+    }
+}
+
+/// Converts two timestamps to seconds
+/// Period is the number of nanoseconds per timestamp tick
+fn timestamp_duration(start: u64, end: u64, period: f32) -> f32 {
+    (end.saturating_sub(start) as f64 * period as f64 / 1_000_000_000.0) as f32
+}
+
+/// Time the GPU spent on a frame in seconds
+fn gpu_frame_time(gpu_timer: Option<&GpuTimer>) -> Option<f32> {
+    gpu_timer.and_then(|gpu_timer| gpu_timer.last_time)
+}
+
+/// Frame being rendered, view is the presented texture
+pub(crate) struct Frame {
+    /// None for headless contexts
+    output: Option<wgpu::SurfaceTexture>,
+    pub(crate) view: wgpu::TextureView,
+    pub(crate) encoder: wgpu::CommandEncoder,
+}
+
+/// How the canvas and an offscreen canvas share the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// Only the canvas fills the window
+    Single,
+    /// Canvas on the left half, offscreen canvas on the right half
+    SideBySide(CanvasId),
+    /// Canvas on the top half, offscreen canvas on the bottom half
+    Stacked(CanvasId),
+}
+
+impl Layout {
+    /// Offscreen canvas shown next to the canvas
+    pub(crate) fn secondary(&self) -> Option<CanvasId> {
+        match self {
+            Layout::Single => None,
+            Layout::SideBySide(id) | Layout::Stacked(id) => Some(*id),
+        }
+    }
+}
+
+/// How a logical resolution is scaled to the window, see ```Config::logical_resolution```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogicalScaling {
+    /// Largest scale that fits the window, keeping the aspect ratio
+    Fit,
+    /// Largest whole number scale that fits the window, keeps pixels the same size
+    ///
+    /// Falls back to ```Fit``` if the window is smaller than the canvas
+    Integer,
+}
+
+/// Centers canvas_size in region (x, y, w, h) scaled by scaling
+/// Canvas pixels are pixel_aspect times wider than tall
+/// None stretches the canvas over the region unless the pixels are not square
+fn scaled_viewport(
+    region: (u32, u32, u32, u32),
+    canvas_size: (u32, u32),
+    scaling: Option<LogicalScaling>,
+    pixel_aspect: f32,
+) -> (u32, u32, u32, u32) {
+    let (x, y, w, h) = region;
+    let canvas_w = canvas_size.0.max(1) as f32 * pixel_aspect;
+    let canvas_h = canvas_size.1.max(1) as f32;
+    let fit = (w as f32 / canvas_w).min(h as f32 / canvas_h);
+    let scale = match scaling {
+        None if pixel_aspect == 1.0 => return region,
+        None | Some(LogicalScaling::Fit) => fit,
+        Some(LogicalScaling::Integer) if fit >= 1.0 => fit.floor(),
+        Some(LogicalScaling::Integer) => fit,
+    };
+    let scaled_w = ((canvas_w * scale).round() as u32).min(w);
+    let scaled_h = ((canvas_h * scale).round() as u32).min(h);
+    (
+        x + (w - scaled_w) / 2,
+        y + (h - scaled_h) / 2,
+        scaled_w,
+        scaled_h,
+    )
+}
+
+/// Texture frames are drawn to before being copied to the surface
+pub(crate) struct PresentedTexture {
+    /// Has the size and format of the surface
+    pub(crate) texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+}
+
+impl PresentedTexture {
+    fn new(
+        device: &Device,
+        layout: &wgpu::BindGroupLayout,
+        surface_config: &SurfaceConfiguration,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Presented Texture"),
+            size: wgpu::Extent3d {
+                width: surface_config.width,
+                height: surface_config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: surface_config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            }],
+            label: Some("presented_bind_group"),
+        });
+        Self {
+            texture,
+            bind_group,
+        }
+    }
+}
+
+/// Sprite drawn over the surface at the cursor
+pub(crate) struct CursorSprite {
+    size: (u32, u32),
+    hotspot: (u32, u32),
+    /// Cursor in physical window coordinates, None hides the sprite
+    pub(crate) position: Option<(f64, f64)>,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl CursorSprite {
+    fn new(
+        device: &Device,
+        queue: &wgpu::Queue,
+        surface_config: &SurfaceConfiguration,
+        size: (u32, u32),
+        pixels: &[u8],
+        hotspot: (u32, u32),
+    ) -> Self {
+        // Sprite colors are sRGB encoded like the canvas
+        let format = if surface_config.format.describe().srgb {
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        } else {
+            wgpu::TextureFormat::Rgba8Unorm
+        };
+        let texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("Cursor Texture"),
+                size: wgpu::Extent3d {
+                    width: size.0,
+                    height: size.1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            pixels,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cursor Uniform Buffer"),
+            size: std::mem::size_of::<[f32; 4]>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("cursor_bind_group_layout"),
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("cursor_bind_group"),
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Cursor Shader"),
+            source: wgpu::ShaderSource::Wgsl(CURSOR_SHADER_CODE.into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Cursor Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Cursor Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: surface_config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            size,
+            hotspot,
+            position: None,
+            uniform_buffer,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    /// Draws the sprite over a target of the surface format, unless hidden
+    fn draw<'a>(
+        &'a self,
+        queue: &wgpu::Queue,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        target: (u32, u32),
+    ) {
+        let position = match self.position {
+            Some(position) => position,
+            None => return,
+        };
+        let rect = cursor_rect(position, self.hotspot, self.size, target);
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&rect));
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..4, 0..1);
+    }
+}
+
+/// Returns the top left and bottom right corners in clip space of a cursor sprite drawn on a target
+/// One sprite pixel covers one physical pixel, like an OS cursor
+fn cursor_rect(
+    position: (f64, f64),
+    hotspot: (u32, u32),
+    size: (u32, u32),
+    target: (u32, u32),
+) -> [f32; 4] {
+    let left = position.0.floor() as f32 - hotspot.0 as f32;
+    let top = position.1.floor() as f32 - hotspot.1 as f32;
+    let to_clip_x = |x: f32| x / target.0 as f32 * 2.0 - 1.0;
+    let to_clip_y = |y: f32| 1.0 - y / target.1 as f32 * 2.0;
+    [
+        to_clip_x(left),
+        to_clip_y(top),
+        to_clip_x(left + size.0 as f32),
+        to_clip_y(top + size.1 as f32),
+    ]
+}
+
+/// Texture of the offscreen canvas shown by ```Layout```
+pub(crate) struct SecondaryTexture {
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    size: (u32, u32),
+}
+
+/// Returns the window regions (x, y, w, h) of the canvas followed by the offscreen canvas
+fn layout_regions(layout: Layout, size: (u32, u32)) -> Vec<(u32, u32, u32, u32)> {
+    let (width, height) = size;
+    match layout {
+        Layout::Single => vec![(0, 0, width, height)],
+        Layout::SideBySide(_) => vec![
+            (0, 0, width / 2, height),
+            (width / 2, 0, width - width / 2, height),
+        ],
+        Layout::Stacked(_) => vec![
+            (0, 0, width, height / 2),
+            (0, height / 2, width, height - height / 2),
+        ],
+    }
+}
+
+/// Copies the pixels of canvas changed since the last upload to texture
+/// Supersampled canvases are resolved and uploaded whole
+fn upload_dirty(queue: &wgpu::Queue, texture: &wgpu::Texture, canvas: &mut Canvas) {
+    let dirty = match canvas.take_dirty() {
+        Some(dirty) => dirty,
+        None => return,
+    };
+    if canvas.supersample() > 1 {
+        let (width, height) = canvas.logical_size();
+        let full = DirtyRegion::full(width, height);
+        write_region(queue, texture, &canvas.resolved(), width, &full);
+        return;
+    }
+    write_region(queue, texture, &canvas.pixels, canvas.width, &dirty);
+}
+
+/// Writes region of RGBA pixels with rows of width pixels to the same region of texture
+/// Rows that are not a multiple of the copy alignment are staged in a padded buffer
+fn write_region(
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    pixels: &[u8],
+    width: u32,
+    region: &DirtyRegion,
+) {
+    let copy_texture = wgpu::ImageCopyTexture {
+        texture,
+        mip_level: 0,
+        origin: wgpu::Origin3d {
+            x: region.min_x,
+            y: region.min_y,
+            z: 0,
+        },
+        aspect: wgpu::TextureAspect::All,
+    };
+    let size = wgpu::Extent3d {
+        width: region.width(),
+        height: region.height(),
+        depth_or_array_layers: 1,
+    };
+
+    if (4 * width) % wgpu::COPY_BYTES_PER_ROW_ALIGNMENT == 0 {
+        let layout = wgpu::ImageDataLayout {
+            offset: ((region.min_y * width + region.min_x) * 4) as u64,
+            bytes_per_row: std::num::NonZeroU32::new(4 * width),
+            rows_per_image: None,
+        };
+        queue.write_texture(copy_texture, pixels, layout, size);
+        return;
+    }
+
+    let row_bytes = (region.width() * 4) as usize;
+    let padded_row_bytes = padded_bytes_per_row(region.width());
+    let mut staging = vec![0; padded_row_bytes as usize * region.height() as usize];
+    for (row, y) in staging
+        .chunks_exact_mut(padded_row_bytes as usize)
+        .zip(region.min_y..region.max_y)
+    {
+        let start = ((y * width + region.min_x) * 4) as usize;
+        row[..row_bytes].copy_from_slice(&pixels[start..start + row_bytes]);
+    }
+    let layout = wgpu::ImageDataLayout {
+        offset: 0,
+        bytes_per_row: std::num::NonZeroU32::new(padded_row_bytes),
+        rows_per_image: None,
+    };
+    queue.write_texture(copy_texture, &staging, layout, size);
+}
+
+/// Error reported by the GPU
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenderError {
+    /// Invalid use of the GPU, such as a shader that does not compile
+    Validation(String),
+    /// The GPU ran out of memory
+    OutOfMemory,
+}
+
+impl From<wgpu::Error> for RenderError {
+    fn from(error: wgpu::Error) -> Self {
+        match error {
+            wgpu::Error::Validation { description, .. } => RenderError::Validation(description),
+            wgpu::Error::OutOfMemory { .. } => RenderError::OutOfMemory,
+        }
+    }
+}
+
+impl std::fmt::Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderError::Validation(description) => {
+                write!(f, "gpu validation error: {}", description)
+            }
+            RenderError::OutOfMemory => write!(f, "gpu is out of memory"),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+/// Instance and default adapter for tests, None on machines without any adapter
+#[cfg(test)]
+fn test_adapter() -> Option<(wgpu::Instance, wgpu::Adapter)> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let adapter =
+        pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))?;
+    Some((instance, adapter))
+}
+
+/// Device and queue of the default adapter for tests, None on machines without any adapter
+#[cfg(test)]
+pub(crate) fn test_device() -> Option<(Arc<Device>, wgpu::Queue)> {
+    let (_, adapter) = test_adapter()?;
+    let (device, queue) =
+        pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None))
+            .ok()?;
+    Some((Arc::new(device), queue))
+}
+
+/// Runs f and returns the validation error it caused instead of its result
+fn capture_errors<T>(device: &Device, f: impl FnOnce() -> T) -> Result<T, RenderError> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let value = f();
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(error) => Err(error.into()),
+        None => Ok(value),
+    }
+}
+
+/// Filter used when scaling the canvas to the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpscaleFilter {
+    /// Sharp pixels
+    Nearest,
+    /// Smooth interpolation between pixels
+    Linear,
+    /// Edge aware Scale2x upscaling
+    ///
+    /// Rounds off diagonal edges while keeping flat areas sharp
+    ScaleHQ,
+}
+
+impl UpscaleFilter {
+    /// Filter mode used by the canvas sampler
+    fn filter_mode(&self) -> wgpu::FilterMode {
+        match self {
+            UpscaleFilter::Nearest => wgpu::FilterMode::Nearest,
+            UpscaleFilter::Linear => wgpu::FilterMode::Linear,
+            UpscaleFilter::ScaleHQ => wgpu::FilterMode::Nearest,
+        }
+    }
+
+    /// Fragment shader entry point used by the pipeline
+    fn fragment_entry_point(&self) -> &'static str {
+        match self {
+            UpscaleFilter::Nearest => "fs_main",
+            UpscaleFilter::Linear => "fs_main",
+            UpscaleFilter::ScaleHQ => "fs_scale_hq",
+        }
+    }
+}
+
+/// Values passed to the shader
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct Uniforms {
+    /// Offset added to the canvas uv
+    pub(crate) pan: [f32; 2],
+    /// Offset added to the quad in clip space
+    pub(crate) offset: [f32; 2],
+}
+
+/// Splits an offset in canvas pixels into whole pixels and a fraction in (-1,1)
+fn split_subpixel(offset: f32) -> (i32, f32) {
+    let whole = offset.trunc();
+    (whole as i32, offset - whole)
+}
+
+/// Clip space offset moving the canvas quad right and down by (dx, dy) canvas pixels
+/// The quad spans 2 clip units across the canvas
+fn subpixel_clip_offset(dx: f32, dy: f32, width: u32, height: u32) -> [f32; 2] {
+    [
+        dx * 2.0 / width.max(1) as f32,
+        -dy * 2.0 / height.max(1) as f32,
+    ]
+}
+
+/// Wraps a uv offset into \[0,1)
+fn wrap_pan(u_offset: f32, v_offset: f32) -> [f32; 2] {
+    [u_offset.rem_euclid(1.0), v_offset.rem_euclid(1.0)]
+}
+
+/// How the canvas is sampled outside of the \[0,1\] uv range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressMode {
+    /// Repeat the edge pixels
+    ClampToEdge,
+    /// Tile the canvas
+    Repeat,
+    /// Tile the canvas, flipping every other tile
+    MirrorRepeat,
+}
+
+impl AddressMode {
+    /// Address mode used by the canvas sampler
+    fn wgpu_address_mode(&self) -> wgpu::AddressMode {
+        match self {
+            AddressMode::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+            AddressMode::Repeat => wgpu::AddressMode::Repeat,
+            AddressMode::MirrorRepeat => wgpu::AddressMode::MirrorRepeat,
+        }
+    }
+}
+
+/// Sampler used for presenting the canvas
+fn sampler_descriptor(
+    upscale_filter: UpscaleFilter,
+    address_mode: AddressMode,
+) -> wgpu::SamplerDescriptor<'static> {
+    wgpu::SamplerDescriptor {
+        address_mode_u: address_mode.wgpu_address_mode(),
+        address_mode_v: address_mode.wgpu_address_mode(),
+        address_mode_w: address_mode.wgpu_address_mode(),
+        mag_filter: upscale_filter.filter_mode(),
+        min_filter: upscale_filter.filter_mode(),
+        mipmap_filter: wgpu::FilterMode::Nearest,
+        ..Default::default()
+    }
 }
 
 fn create_surface_config(
@@ -191,15 +1378,19 @@ fn create_surface_config(
     surface: &Surface,
     adapter: &Adapter,
     present_mode: PresentMode,
+    srgb: bool,
+    transparent: bool,
+    forced_format: Option<wgpu::TextureFormat>,
 ) -> SurfaceConfiguration {
     let size = window.inner_size();
     let surface_caps = surface.get_capabilities(adapter);
-    let surface_format: wgpu::TextureFormat = surface_caps
-        .formats
-        .iter()
-        .copied()
-        .find(|f| f.describe().srgb)
-        .unwrap_or(surface_caps.formats[0]);
+    let surface_format = select_surface_format(&surface_caps.formats, srgb, forced_format)
+        .unwrap_or_else(|| {
+            panic!(
+                "surface format {:?} is not supported, supported formats are {:?}",
+                forced_format, surface_caps.formats
+            )
+        });
     wgpu::SurfaceConfiguration {
         usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
         format: surface_format,
@@ -207,67 +1398,184 @@ fn create_surface_config(
         height: size.height,
         // present_mode: surface_caps.present_modes[0],
         present_mode,
-        alpha_mode: surface_caps.alpha_modes[0],
+        alpha_mode: select_alpha_mode(&surface_caps.alpha_modes, transparent),
         view_formats: vec![],
     }
 }
 
-fn create_pipeline(
+/// Device request for the configured limits
+fn device_descriptor(
+    config: &Config,
+    adapter_features: wgpu::Features,
+) -> wgpu::DeviceDescriptor<'static> {
+    let limits = match &config.gpu_limits {
+        Some(limits) => limits.clone(),
+        None if config.compatibility_mode => wgpu::Limits::downlevel_defaults(),
+        None => wgpu::Limits::default(),
+    };
+    log::info!("requesting device limits {:?}", limits);
+    wgpu::DeviceDescriptor {
+        // Timestamps are only used for profiling so request if available
+        features: adapter_features & wgpu::Features::TIMESTAMP_QUERY,
+        limits,
+        label: None,
+    }
+}
+
+/// First adapter supporting surface whose name contains filter
+/// Returns None with a warning if no adapter matches
+fn named_adapter(
+    instance: &wgpu::Instance,
+    surface: &wgpu::Surface,
+    filter: &str,
+) -> Option<wgpu::Adapter> {
+    let mut adapters: Vec<wgpu::Adapter> = instance
+        .enumerate_adapters(wgpu::Backends::all())
+        .filter(|adapter| adapter.is_surface_supported(surface))
+        .collect();
+    let infos: Vec<wgpu::AdapterInfo> = adapters.iter().map(|adapter| adapter.get_info()).collect();
+    match select_adapter(&infos, filter) {
+        Some(index) => Some(adapters.swap_remove(index)),
+        None => {
+            log::warn!(
+                "no adapter named like {:?}, falling back to power preference",
+                filter
+            );
+            None
+        }
+    }
+}
+
+/// Index of the first adapter whose name contains filter, ignoring case
+fn select_adapter(infos: &[wgpu::AdapterInfo], filter: &str) -> Option<usize> {
+    let filter = filter.to_lowercase();
+    infos
+        .iter()
+        .position(|info| info.name.to_lowercase().contains(&filter))
+}
+
+/// Picks the forced format or the first surface format matching the wanted sRGB setting
+/// Falls back to the first supported format with a warning
+/// Returns None if the forced format is not supported
+fn select_surface_format(
+    formats: &[wgpu::TextureFormat],
+    srgb: bool,
+    forced: Option<wgpu::TextureFormat>,
+) -> Option<wgpu::TextureFormat> {
+    if let Some(format) = forced {
+        return formats.contains(&format).then_some(format);
+    }
+    let format = formats.iter().copied().find(|f| f.describe().srgb == srgb);
+    if format.is_none() {
+        log::warn!(
+            "no {} surface format available, falling back to {:?}, colors may look different",
+            if srgb { "sRGB" } else { "linear" },
+            formats[0]
+        );
+    }
+    Some(format.unwrap_or(formats[0]))
+}
+
+/// Picks an alpha mode that blends with the desktop if transparent, opaque otherwise
+/// Falls back to the first supported mode
+fn select_alpha_mode(
+    alpha_modes: &[wgpu::CompositeAlphaMode],
+    transparent: bool,
+) -> wgpu::CompositeAlphaMode {
+    let preferred: &[wgpu::CompositeAlphaMode] = if transparent {
+        &[
+            wgpu::CompositeAlphaMode::PreMultiplied,
+            wgpu::CompositeAlphaMode::PostMultiplied,
+        ]
+    } else {
+        &[wgpu::CompositeAlphaMode::Opaque]
+    };
+    preferred
+        .iter()
+        .copied()
+        .find(|mode| alpha_modes.contains(mode))
+        .unwrap_or(alpha_modes[0])
+}
+
+/// Blend state used when drawing the canvas quad
+/// Premultiplies the non premultiplied canvas over the cleared surface when required
+fn blend_state(alpha_mode: wgpu::CompositeAlphaMode) -> wgpu::BlendState {
+    match alpha_mode {
+        wgpu::CompositeAlphaMode::PreMultiplied => wgpu::BlendState::ALPHA_BLENDING,
+        _ => wgpu::BlendState::REPLACE,
+    }
+}
+
+/// Color the surface is cleared to before drawing the canvas
+fn clear_color(alpha_mode: wgpu::CompositeAlphaMode) -> wgpu::Color {
+    match alpha_mode {
+        wgpu::CompositeAlphaMode::PreMultiplied | wgpu::CompositeAlphaMode::PostMultiplied => {
+            wgpu::Color::TRANSPARENT
+        }
+        _ => wgpu::Color {
+            r: 0.1,
+            g: 0.2,
+            b: 0.3,
+            a: 1.0,
+        },
+    }
+}
+
+/// Layout of the canvas texture and sampler, shared by all canvas textures
+fn create_texture_bind_group_layout(device: &Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+        label: Some("texture_bind_group_layout"),
+    })
+}
+
+/// Creates a texture for a width x height canvas with its bind group
+fn create_canvas_texture(
     device: &Device,
-    surface_config: &SurfaceConfiguration,
+    texture_bind_group_layout: &wgpu::BindGroupLayout,
     width: u32,
     height: u32,
-) -> (wgpu::RenderPipeline, wgpu::Texture, wgpu::BindGroup) {
+    texture_format: wgpu::TextureFormat,
+    upscale_filter: UpscaleFilter,
+    address_mode: AddressMode,
+) -> (wgpu::Texture, wgpu::BindGroup) {
     let texture_size = wgpu::Extent3d {
         width,
         height,
         depth_or_array_layers: 1,
     };
-    let diffuse_texture = device.create_texture(&wgpu::TextureDescriptor {
-        size: texture_size,
-        mip_level_count: 1,
-        sample_count: 1,
-        dimension: wgpu::TextureDimension::D2,
-        format: wgpu::TextureFormat::Rgba8UnormSrgb,
-        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-        label: Some("diffuse_texture"),
-        view_formats: &[],
-    });
-
-    let diffuse_texture_view = diffuse_texture.create_view(&wgpu::TextureViewDescriptor::default());
-    let diffuse_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-        address_mode_u: wgpu::AddressMode::ClampToEdge,
-        address_mode_v: wgpu::AddressMode::ClampToEdge,
-        address_mode_w: wgpu::AddressMode::ClampToEdge,
-        mag_filter: wgpu::FilterMode::Nearest,
-        min_filter: wgpu::FilterMode::Nearest,
-        mipmap_filter: wgpu::FilterMode::Nearest,
-        ..Default::default()
-    });
-    let texture_bind_group_layout =
-        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        multisampled: false,
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    count: None,
-                },
-            ],
-            label: Some("texture_bind_group_layout"),
-        });
+    let diffuse_texture = device.create_texture(&wgpu::TextureDescriptor {
+        size: texture_size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: texture_format,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        label: Some("diffuse_texture"),
+        view_formats: &[],
+    });
+
+    let diffuse_texture_view = diffuse_texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let diffuse_sampler = device.create_sampler(&sampler_descriptor(upscale_filter, address_mode));
     let diffuse_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-        layout: &texture_bind_group_layout,
+        layout: texture_bind_group_layout,
         entries: &[
             wgpu::BindGroupEntry {
                 binding: 0,
@@ -281,6 +1589,88 @@ fn create_pipeline(
         label: Some("diffuse_bind_group"),
     });
 
+    (diffuse_texture, diffuse_bind_group)
+}
+
+/// Layout of the presented texture read when copying it to the surface
+fn create_presented_bind_group_layout(device: &Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            },
+            count: None,
+        }],
+        label: Some("presented_bind_group_layout"),
+    })
+}
+
+/// Pipeline copying the presented texture to a surface texture of the same size
+fn create_present_pipeline(
+    device: &Device,
+    presented_bind_group_layout: &wgpu::BindGroupLayout,
+    surface_config: &SurfaceConfiguration,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Present Shader"),
+        source: wgpu::ShaderSource::Wgsl(PRESENT_SHADER_CODE.into()),
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Present Pipeline Layout"),
+        bind_group_layouts: &[presented_bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Present Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: surface_config.format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn create_pipeline(
+    device: &Device,
+    surface_config: &SurfaceConfiguration,
+    texture_bind_group_layout: &wgpu::BindGroupLayout,
+    width: u32,
+    height: u32,
+    texture_format: wgpu::TextureFormat,
+    upscale_filter: UpscaleFilter,
+    address_mode: AddressMode,
+    uniform_bind_group_layout: &wgpu::BindGroupLayout,
+) -> (wgpu::RenderPipeline, wgpu::Texture, wgpu::BindGroup) {
+    let (diffuse_texture, diffuse_bind_group) = create_canvas_texture(
+        device,
+        texture_bind_group_layout,
+        width,
+        height,
+        texture_format,
+        upscale_filter,
+        address_mode,
+    );
+
     let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
         label: Some("Shader"),
         // source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/shader.wgsl").into()),
@@ -289,7 +1679,7 @@ fn create_pipeline(
 
     let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some("Render Pipeline Layout"),
-        bind_group_layouts: &[&texture_bind_group_layout],
+        bind_group_layouts: &[texture_bind_group_layout, uniform_bind_group_layout],
         push_constant_ranges: &[],
     });
 
@@ -303,10 +1693,10 @@ fn create_pipeline(
         },
         fragment: Some(wgpu::FragmentState {
             module: &shader,
-            entry_point: "fs_main",
+            entry_point: upscale_filter.fragment_entry_point(),
             targets: &[Some(wgpu::ColorTargetState {
                 format: surface_config.format,
-                blend: Some(wgpu::BlendState::REPLACE),
+                blend: Some(blend_state(surface_config.alpha_mode)),
                 write_mask: wgpu::ColorWrites::ALL,
             })],
         }),
@@ -377,13 +1767,21 @@ struct VertexOutput {
     @location(0) uv: vec2<f32>,
 }
 
+struct Uniforms {
+    pan: vec2<f32>,
+    offset: vec2<f32>,
+}
+
+@group(1) @binding(0)
+var<uniform> uniforms: Uniforms;
+
 @vertex
 fn vs_main(
     model: VertexInput,
 ) -> VertexOutput {
     var out: VertexOutput;
-    out.uv = model.uv;
-    out.clip_position = vec4<f32>(model.position, 1.0);
+    out.uv = model.uv + uniforms.pan;
+    out.clip_position = vec4<f32>(model.position.xy + uniforms.offset, model.position.z, 1.0);
     return out;
 }
 
@@ -398,4 +1796,772 @@ var s_diffuse: sampler;
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     return textureSample(t_diffuse, s_diffuse, in.uv);
 }
+
+// Scale2x fragment shader
+
+fn load_clamped(coord: vec2<i32>, size: vec2<i32>) -> vec4<f32> {
+    return textureLoad(t_diffuse, clamp(coord, vec2<i32>(0), size - vec2<i32>(1)), 0);
+}
+
+fn same(a: vec4<f32>, b: vec4<f32>) -> bool {
+    return all(abs(a - b) < vec4<f32>(0.002));
+}
+
+@fragment
+fn fs_scale_hq(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(t_diffuse));
+    let pos = in.uv * vec2<f32>(size);
+    let texel = vec2<i32>(floor(pos));
+    let sub = fract(pos);
+
+    let center = load_clamped(texel, size);
+    let up = load_clamped(texel + vec2<i32>(0, -1), size);
+    let down = load_clamped(texel + vec2<i32>(0, 1), size);
+    let left = load_clamped(texel + vec2<i32>(-1, 0), size);
+    let right = load_clamped(texel + vec2<i32>(1, 0), size);
+
+    // Neighbours closest to the current quadrant of the texel
+    var vertical = up;
+    var vertical_opposite = down;
+    if sub.y >= 0.5 {
+        vertical = down;
+        vertical_opposite = up;
+    }
+    var horizontal = left;
+    var horizontal_opposite = right;
+    if sub.x >= 0.5 {
+        horizontal = right;
+        horizontal_opposite = left;
+    }
+
+    if same(vertical, horizontal) && !same(vertical, horizontal_opposite) && !same(horizontal, vertical_opposite) {
+        return vertical;
+    }
+    return center;
+}
+";
+
+const PRESENT_SHADER_CODE: &str = "
+@group(0) @binding(0)
+var t_presented: texture_2d<f32>;
+
+// Triangle covering the whole target
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureLoad(t_presented, vec2<i32>(position.xy), 0);
+}
+";
+
+const CURSOR_SHADER_CODE: &str = "
+struct CursorRect {
+    top_left: vec2<f32>,
+    bottom_right: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var t_cursor: texture_2d<f32>;
+@group(0) @binding(1)
+var<uniform> rect: CursorRect;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+// Strip of the four corners of rect
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let corner = vec2<f32>(f32(index & 1u), f32(index >> 1u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(mix(rect.top_left, rect.bottom_right, corner), 0.0, 1.0);
+    out.uv = corner;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(t_cursor));
+    let texel = min(vec2<i32>(in.uv * vec2<f32>(size)), size - 1);
+    return textureLoad(t_cursor, texel, 0);
+}
 ";
+
+//
+// Commands
+//
+
+/// Set the filter used when scaling the canvas to the window
+///
+/// Default is ```UpscaleFilter::Nearest```
+pub fn set_upscale_filter(ctx: &mut Context, upscale_filter: UpscaleFilter) {
+    ctx.render.set_upscale_filter(upscale_filter);
+    ctx.window.presented_hash = None;
+}
+
+/// Set how the canvas is sampled outside of the \[0,1\] uv range
+///
+/// Default is ```AddressMode::ClampToEdge```
+pub fn set_address_mode(ctx: &mut Context, address_mode: AddressMode) {
+    ctx.render.set_address_mode(address_mode);
+    ctx.window.presented_hash = None;
+}
+
+/// Offset the uv coordinates used to present the canvas
+///
+/// Offsets wrap around at 1.0, combine with ```AddressMode::Repeat``` to scroll the canvas on the GPU
+pub fn set_pan(ctx: &mut Context, u_offset: f32, v_offset: f32) {
+    ctx.render.set_pan(u_offset, v_offset);
+    ctx.window.presented_hash = None;
+}
+
+/// Shift the presented canvas right and down by a fraction of a canvas pixel
+///
+/// The canvas is usually upscaled so a fraction of a canvas pixel is still whole screen pixels,
+/// giving smooth camera motion without blurring the content
+///
+/// Offsets are limited to less than one canvas pixel, whole pixels beyond that scroll the
+/// current canvas content and expose the clear color
+///
+/// A strip of up to one canvas pixel at the edges shows the window background
+pub fn set_subpixel_offset(ctx: &mut Context, dx: f32, dy: f32) {
+    ctx.render.set_subpixel_offset(dx, dy);
+    ctx.window.presented_hash = None;
+}
+
+/// Present canvas pixels ratio times wider than tall, 1.0 is square and the default
+///
+/// The stored canvas is unchanged, only the presented quad is stretched,
+/// a canvas that would otherwise be stretched over the window is fit to it instead
+///
+/// Panics if ratio is not positive
+pub fn set_pixel_aspect(ctx: &mut Context, ratio: f32) {
+    assert!(
+        ratio > 0.0 && ratio.is_finite(),
+        "pixel aspect must be positive, got {}",
+        ratio
+    );
+    ctx.render.pixel_aspect = ratio;
+    ctx.window.presented_hash = None;
+}
+
+/// Pause uploading and presenting the canvas, the last presented frame stays on screen
+///
+/// Events are still processed, see ```set_update_while_disabled``` for update
+pub fn set_rendering_enabled(ctx: &mut Context, enabled: bool) {
+    ctx.window.rendering_enabled = enabled;
+}
+
+/// Keep calling update while rendering is disabled, enabled by default
+///
+/// Disable to also pause update, input is still collected and update resumes with rendering
+pub fn set_update_while_disabled(ctx: &mut Context, enabled: bool) {
+    ctx.window.update_while_disabled = enabled;
+}
+
+/// Show an offscreen canvas next to the canvas in the same window
+///
+/// The offscreen canvas is uploaded every frame it changes, each half is scaled to fit its region
+///
+/// Mouse positions are mapped to the region of the canvas, ```Layout::Single``` restores the default
+pub fn set_layout(ctx: &mut Context, layout: Layout) {
+    ctx.render.set_layout(layout);
+    ctx.window.presented_hash = None;
+}
+
+/// Texture format of the window surface
+///
+/// Non sRGB formats are used as a fallback when no sRGB format is supported, see ```Config::surface_format```
+pub fn surface_format(ctx: &Context) -> wgpu::TextureFormat {
+    ctx.render.surface_config.format
+}
+
+/// Size of the window surface in physical pixels (width, height)
+pub fn surface_size(ctx: &Context) -> (u32, u32) {
+    (
+        ctx.render.surface_config.width,
+        ctx.render.surface_config.height,
+    )
+}
+
+/// Device used for rendering, for creating custom pipelines in ```Callbacks::render```
+pub fn device(ctx: &Context) -> &wgpu::Device {
+    &ctx.render.device
+}
+
+/// Queue used for rendering, for uploading custom resources
+pub fn queue(ctx: &Context) -> &wgpu::Queue {
+    &ctx.render.queue
+}
+
+/// Upload the canvas and present it immediately, for showing progress during a long update
+///
+/// ```Callbacks::render``` is not called for this frame
+///
+/// Presenting often competes with the regular present of the loop and may block on vsync
+///
+/// Does nothing with ```Config::software_canvas_only```
+pub fn present(ctx: &mut Context) -> Result<(), wgpu::SurfaceError> {
+    if ctx.render.software_canvas_only {
+        return Ok(());
+    }
+    ctx.render.render()
+}
+
+/// Returns the time in seconds the GPU spent rendering a recent frame
+///
+/// Requires the adapter to support timestamp queries, returns None otherwise
+pub fn last_frame_gpu_time(ctx: &Context) -> Option<f32> {
+    gpu_frame_time(ctx.render.gpu_timer.as_ref())
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::{
+        blend_state, capture_errors, clear_color, cursor_rect, device_descriptor, layout_regions,
+        present, sampler_descriptor, scaled_viewport, select_adapter, select_alpha_mode,
+        select_surface_format, set_layout, set_pan, set_subpixel_offset, set_upscale_filter,
+        split_subpixel, subpixel_clip_offset, test_device, timestamp_duration, upload_dirty,
+        AddressMode, Layout, LogicalScaling, RenderError, TimestampReadback, UpscaleFilter,
+        CURSOR_SHADER_CODE, PRESENT_SHADER_CODE, SHADER_CODE,
+    };
+    use crate::{
+        app::headless_canvas,
+        canvas::{self, Canvas, CanvasId},
+        config::Config,
+        media::{read_surface, read_texture},
+        Context,
+    };
+    use wgpu::TextureFormat;
+
+    #[test]
+    fn test_shader_is_valid() {
+        let module = naga::front::wgsl::parse_str(SHADER_CODE).unwrap();
+        let mut validator = naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::empty(),
+        );
+        validator.validate(&module).unwrap();
+
+        let present = naga::front::wgsl::parse_str(PRESENT_SHADER_CODE).unwrap();
+        validator.validate(&present).unwrap();
+
+        let cursor = naga::front::wgsl::parse_str(CURSOR_SHADER_CODE).unwrap();
+        validator.validate(&cursor).unwrap();
+
+        for filter in [
+            UpscaleFilter::Nearest,
+            UpscaleFilter::Linear,
+            UpscaleFilter::ScaleHQ,
+        ] {
+            let entry_point = filter.fragment_entry_point();
+            assert!(module.entry_points.iter().any(|e| e.name == entry_point));
+        }
+    }
+
+    #[test]
+    fn test_upscale_filter_pipeline() {
+        assert_eq!(UpscaleFilter::Nearest.fragment_entry_point(), "fs_main");
+        assert_eq!(UpscaleFilter::ScaleHQ.fragment_entry_point(), "fs_scale_hq");
+        assert_eq!(
+            UpscaleFilter::Linear.filter_mode(),
+            wgpu::FilterMode::Linear
+        );
+        assert_eq!(
+            UpscaleFilter::ScaleHQ.filter_mode(),
+            wgpu::FilterMode::Nearest
+        );
+    }
+
+    #[test]
+    fn test_upscale_filter_switches_pipeline() {
+        // Skip on machines without any adapter
+        let Some(mut ctx) = headless_canvas((2, 2), (4, 4)) else {
+            return;
+        };
+        // Checkerboard upscaled 2x, white in the top left
+        ctx.render.canvas.write_pixel(0, 0, &[255, 255, 255]);
+        ctx.render.canvas.write_pixel(1, 0, &[0, 0, 0]);
+        ctx.render.canvas.write_pixel(0, 1, &[0, 0, 0]);
+        ctx.render.canvas.write_pixel(1, 1, &[255, 255, 255]);
+        // Red channel of the bottom right window pixel covered by the top left canvas pixel
+        let inner_corner = |ctx: &mut Context, filter| {
+            set_upscale_filter(ctx, filter);
+            present(ctx).unwrap();
+            let frame = read_surface(ctx).unwrap();
+            frame.pixel_rgba(1, 1)[0]
+        };
+
+        assert_eq!(inner_corner(&mut ctx, UpscaleFilter::Nearest), 255);
+        // Scale2x rounds the corner off towards the black neighbours
+        assert_eq!(inner_corner(&mut ctx, UpscaleFilter::ScaleHQ), 0);
+        let linear = inner_corner(&mut ctx, UpscaleFilter::Linear);
+        assert!(linear > 0 && linear < 255, "{}", linear);
+        assert_eq!(inner_corner(&mut ctx, UpscaleFilter::Nearest), 255);
+    }
+
+    #[test]
+    fn test_sampler_address_mode() {
+        let default = sampler_descriptor(UpscaleFilter::Nearest, AddressMode::ClampToEdge);
+        assert_eq!(default.address_mode_u, wgpu::AddressMode::ClampToEdge);
+
+        let repeat = sampler_descriptor(UpscaleFilter::Linear, AddressMode::Repeat);
+        assert_eq!(repeat.address_mode_u, wgpu::AddressMode::Repeat);
+        assert_eq!(repeat.address_mode_v, wgpu::AddressMode::Repeat);
+        assert_eq!(repeat.mag_filter, wgpu::FilterMode::Linear);
+
+        let mirror = sampler_descriptor(UpscaleFilter::Nearest, AddressMode::MirrorRepeat);
+        assert_eq!(mirror.address_mode_v, wgpu::AddressMode::MirrorRepeat);
+    }
+
+    #[test]
+    fn test_pan_uniform() {
+        // Skip on machines without any adapter
+        let Some(mut ctx) = headless_canvas((4, 4), (4, 4)) else {
+            return;
+        };
+
+        set_pan(&mut ctx, 1.25, -0.25);
+
+        // Offsets wrap around at 1.0
+        let uniforms = ctx.render.uniforms;
+        assert_eq!(uniforms.pan, [0.25, 0.75]);
+        assert_eq!(
+            bytemuck::bytes_of(&uniforms),
+            bytemuck::cast_slice::<f32, u8>(&[0.25, 0.75, 0.0, 0.0])
+        );
+    }
+
+    #[test]
+    fn test_scaled_viewport() {
+        let canvas = (320, 180);
+        let fit = Some(LogicalScaling::Fit);
+        let integer = Some(LogicalScaling::Integer);
+
+        // The canvas keeps its size, only the presented quad changes
+        assert_eq!(
+            scaled_viewport((0, 0, 1280, 720), canvas, fit, 1.0),
+            (0, 0, 1280, 720)
+        );
+        assert_eq!(
+            scaled_viewport((0, 0, 1280, 1000), canvas, fit, 1.0),
+            (0, 140, 1280, 720)
+        );
+        assert_eq!(
+            scaled_viewport((0, 0, 1000, 720), canvas, integer, 1.0),
+            (20, 90, 960, 540)
+        );
+        assert_eq!(
+            scaled_viewport((0, 0, 160, 180), canvas, integer, 1.0),
+            (0, 45, 160, 90)
+        );
+        assert_eq!(
+            scaled_viewport((10, 0, 1000, 720), canvas, None, 1.0),
+            (10, 0, 1000, 720)
+        );
+
+        // Wide pixels make the quad wider than the square canvas
+        assert_eq!(
+            scaled_viewport((0, 0, 1280, 720), (240, 240), fit, 1.2),
+            (208, 0, 864, 720)
+        );
+        assert_eq!(
+            scaled_viewport((0, 0, 1280, 720), (240, 240), None, 1.2),
+            (208, 0, 864, 720)
+        );
+        assert_eq!(
+            scaled_viewport((0, 0, 1280, 1000), (100, 100), integer, 2.0),
+            (40, 200, 1200, 600)
+        );
+    }
+
+    #[test]
+    fn test_subpixel_offset() {
+        assert_eq!(split_subpixel(0.25), (0, 0.25));
+        assert_eq!(split_subpixel(2.5), (2, 0.5));
+        assert_eq!(split_subpixel(-1.75), (-1, -0.75));
+
+        // Half a pixel of a 4x2 canvas is an eighth of the 2 unit wide quad
+        assert_eq!(subpixel_clip_offset(0.5, 0.5, 4, 2), [0.25, -0.5]);
+        assert_eq!(subpixel_clip_offset(0.0, 0.0, 4, 2), [0.0, -0.0]);
+    }
+
+    #[test]
+    fn test_subpixel_offset_scrolls_whole_pixels() {
+        // Skip on machines without any adapter
+        let Some(mut ctx) = headless_canvas((4, 2), (4, 2)) else {
+            return;
+        };
+        ctx.render.canvas.write_pixel(0, 0, &[255, 0, 0]);
+
+        set_subpixel_offset(&mut ctx, 2.5, 0.0);
+        assert_eq!(ctx.render.subpixel_offset, (0.5, 0.0));
+        assert_eq!(ctx.render.uniforms.offset, [0.25, -0.0]);
+        assert_eq!(ctx.render.canvas.pixel_rgba(2, 0), [255, 0, 0, 255]);
+        assert_eq!(ctx.render.canvas.pixel_rgba(0, 0)[..3], [0, 0, 0]);
+
+        // Fractions are not accumulated
+        set_subpixel_offset(&mut ctx, 0.5, 0.0);
+        assert_eq!(ctx.render.canvas.pixel_rgba(2, 0), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_select_alpha_mode() {
+        use wgpu::CompositeAlphaMode::{Auto, Opaque, PostMultiplied, PreMultiplied};
+
+        assert_eq!(
+            select_alpha_mode(&[Opaque, PreMultiplied], true),
+            PreMultiplied
+        );
+        assert_eq!(
+            select_alpha_mode(&[Opaque, PostMultiplied], true),
+            PostMultiplied
+        );
+        assert_eq!(select_alpha_mode(&[Opaque, PreMultiplied], false), Opaque);
+        assert_eq!(select_alpha_mode(&[Auto], true), Auto);
+
+        assert_eq!(blend_state(PreMultiplied), wgpu::BlendState::ALPHA_BLENDING);
+        assert_eq!(blend_state(Opaque), wgpu::BlendState::REPLACE);
+        assert_eq!(clear_color(PostMultiplied), wgpu::Color::TRANSPARENT);
+    }
+
+    #[test]
+    fn test_capture_errors() {
+        // Skip on machines without any adapter
+        let (device, _queue) = match test_device() {
+            Some(device) => device,
+            None => return,
+        };
+
+        let invalid = capture_errors(&device, || {
+            device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl("fn broken( {".into()),
+            })
+        });
+        assert!(matches!(invalid, Err(RenderError::Validation(_))));
+
+        let valid = capture_errors(&device, || {
+            device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl(SHADER_CODE.into()),
+            })
+        });
+        assert!(valid.is_ok());
+    }
+
+    #[test]
+    fn test_layout_regions() {
+        let id = CanvasId(0);
+
+        assert_eq!(
+            layout_regions(Layout::Single, (101, 50)),
+            vec![(0, 0, 101, 50)]
+        );
+        assert_eq!(
+            layout_regions(Layout::SideBySide(id), (101, 50)),
+            vec![(0, 0, 50, 50), (50, 0, 51, 50)]
+        );
+        assert_eq!(
+            layout_regions(Layout::Stacked(id), (100, 51)),
+            vec![(0, 0, 100, 25), (0, 25, 100, 26)]
+        );
+    }
+
+    #[test]
+    fn test_oversized_config_canvas_is_clamped() {
+        // Skip on machines without any adapter
+        let Some(ctx) = headless_canvas((u32::MAX, 2), (4, 2)) else {
+            return;
+        };
+        let max = ctx.render.device.limits().max_texture_dimension_2d;
+
+        assert_eq!(
+            (ctx.render.canvas.width, ctx.render.canvas.height),
+            (max, 2)
+        );
+        assert_eq!(ctx.render.texture.width(), max);
+    }
+
+    #[test]
+    fn test_layout_presents_both_canvases() {
+        // Skip on machines without any adapter
+        let Some(mut ctx) = headless_canvas((1, 1), (4, 2)) else {
+            return;
+        };
+        let id = canvas::create_offscreen(&mut ctx, 1, 1);
+        ctx.render.canvas.write_pixel(0, 0, &[255, 0, 0]);
+        ctx.render.offscreens[id.0].write_pixel(0, 0, &[0, 0, 255]);
+        set_layout(&mut ctx, Layout::SideBySide(id));
+        present(&mut ctx).unwrap();
+
+        let (red, blue) = ([255, 0, 0, 255], [0, 0, 255, 255]);
+        let presented = read_surface(&ctx).unwrap();
+        assert_eq!(
+            presented.pixels,
+            [red, red, blue, blue, red, red, blue, blue].concat()
+        );
+        // Input maps to the left half only
+        assert_eq!(ctx.render.canvas_viewport(), (0, 0, 2, 2));
+    }
+
+    #[test]
+    fn test_select_adapter() {
+        let info = |name: &str, device_type| wgpu::AdapterInfo {
+            name: name.to_string(),
+            vendor: 0,
+            device: 0,
+            device_type,
+            driver: String::new(),
+            driver_info: String::new(),
+            backend: wgpu::Backend::Vulkan,
+        };
+        let infos = [
+            info("Intel(R) UHD Graphics 630", wgpu::DeviceType::IntegratedGpu),
+            info("NVIDIA GeForce RTX 3060", wgpu::DeviceType::DiscreteGpu),
+            info("llvmpipe (LLVM 15.0.7, 256 bits)", wgpu::DeviceType::Cpu),
+        ];
+
+        assert_eq!(select_adapter(&infos, "nvidia"), Some(1));
+        assert_eq!(select_adapter(&infos, "UHD"), Some(0));
+        assert_eq!(select_adapter(&infos, "LLVMpipe"), Some(2));
+        // First match wins
+        assert_eq!(select_adapter(&infos, "G"), Some(0));
+        assert_eq!(select_adapter(&infos, "radeon"), None);
+        assert_eq!(select_adapter(&[], "nvidia"), None);
+    }
+
+    #[test]
+    fn test_select_surface_format() {
+        let formats = [TextureFormat::Bgra8Unorm, TextureFormat::Bgra8UnormSrgb];
+
+        assert_eq!(
+            select_surface_format(&formats, true, None),
+            Some(TextureFormat::Bgra8UnormSrgb)
+        );
+        assert_eq!(
+            select_surface_format(&formats, false, None),
+            Some(TextureFormat::Bgra8Unorm)
+        );
+    }
+
+    #[test]
+    fn test_select_surface_format_fallback() {
+        let formats = [TextureFormat::Rgba8Unorm, TextureFormat::Bgra8Unorm];
+
+        assert_eq!(
+            select_surface_format(&formats, true, None),
+            Some(TextureFormat::Rgba8Unorm)
+        );
+        assert_eq!(
+            select_surface_format(&formats, true, Some(TextureFormat::Bgra8Unorm)),
+            Some(TextureFormat::Bgra8Unorm)
+        );
+        assert_eq!(
+            select_surface_format(&formats, true, Some(TextureFormat::Bgra8UnormSrgb)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_failed_timestamp_map_is_dropped() {
+        // Skip on machines without any adapter
+        let Some((device, _)) = test_device() else {
+            return;
+        };
+        // Mapping a buffer without the map usage fails
+        let mut readback = TimestampReadback {
+            buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: TimestampReadback::SIZE,
+                usage: wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            ..TimestampReadback::new(&device)
+        };
+        let mapped = capture_errors(&device, || readback.map());
+        assert!(mapped.is_err());
+
+        assert_eq!(readback.poll(&device), None);
+        assert!(!readback.in_flight);
+    }
+
+    #[test]
+    fn test_timestamp_duration() {
+        assert_eq!(timestamp_duration(1_000, 3_000, 1.0), 0.000002);
+        assert_eq!(timestamp_duration(0, 1_000_000, 1000.0), 1.0);
+        assert_eq!(timestamp_duration(5, 1, 1.0), 0.0);
+    }
+
+    #[test]
+    fn test_device_limits() {
+        let features = wgpu::Features::all();
+        let default = device_descriptor(&Config::default(), features);
+        assert_eq!(default.limits, wgpu::Limits::default());
+        assert_eq!(default.features, wgpu::Features::TIMESTAMP_QUERY);
+
+        let compatibility = Config {
+            compatibility_mode: true,
+            ..Default::default()
+        };
+        let descriptor = device_descriptor(&compatibility, features);
+        assert_eq!(descriptor.limits, wgpu::Limits::downlevel_defaults());
+
+        let limits = wgpu::Limits {
+            max_texture_dimension_2d: 1024,
+            ..wgpu::Limits::downlevel_webgl2_defaults()
+        };
+        let custom = Config {
+            gpu_limits: Some(limits.clone()),
+            compatibility_mode: true,
+            ..Default::default()
+        };
+        assert_eq!(device_descriptor(&custom, features).limits, limits);
+    }
+
+    #[test]
+    fn test_present() {
+        // Skip on machines without any adapter
+        let Some(mut ctx) = headless_canvas((3, 2), (3, 2)) else {
+            return;
+        };
+        let read_back = |ctx: &Context| {
+            let render = &ctx.render;
+            let encoder = render.device.create_command_encoder(&Default::default());
+            let presented = &render.presented.as_ref().unwrap().texture;
+            pollster::block_on(read_texture(
+                &render.device,
+                &render.queue,
+                encoder,
+                presented,
+            ))
+            .unwrap()
+        };
+
+        present(&mut ctx).unwrap();
+        assert_eq!(read_back(&ctx).pixels, ctx.render.canvas.pixels);
+
+        // Only the changed pixel is uploaded but the frame must show the whole buffer
+        ctx.render.canvas.write_pixel(2, 1, &[10, 20, 30]);
+        present(&mut ctx).unwrap();
+        assert_eq!(read_back(&ctx).pixels, ctx.render.canvas.pixels);
+        assert!(ctx.render.canvas.take_dirty().is_none());
+    }
+
+    #[test]
+    fn test_upload_unaligned_width() {
+        // Skip on machines without any adapter
+        let (device, queue) = match test_device() {
+            Some(device) => device,
+            None => return,
+        };
+
+        // 100 and 300 wide rows are not a multiple of the copy alignment, 64 is
+        for width in [100, 300, 64] {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: None,
+                size: wgpu::Extent3d {
+                    width,
+                    height: 3,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            });
+            let mut canvas = Canvas::new(width, 3);
+            upload_dirty(&queue, &texture, &mut canvas);
+            canvas.fill_rect(5, 1, 7, 2, &[1, 2, 3, 255]);
+
+            device.push_error_scope(wgpu::ErrorFilter::Validation);
+            upload_dirty(&queue, &texture, &mut canvas);
+            let error = pollster::block_on(device.pop_error_scope());
+            assert!(error.is_none(), "width {}: {:?}", width, error);
+
+            let encoder = device.create_command_encoder(&Default::default());
+            let read_back =
+                pollster::block_on(read_texture(&device, &queue, encoder, &texture)).unwrap();
+            assert_eq!(read_back.pixels, canvas.pixels);
+        }
+    }
+
+    #[test]
+    fn test_cursor_rect() {
+        // Hotspot (1, 2) at the cursor (4.5, 4) of an 8x8 target
+        assert_eq!(
+            cursor_rect((4.5, 4.0), (1, 2), (4, 2), (8, 8)),
+            [-0.25, 0.5, 0.75, 0.0]
+        );
+        assert_eq!(
+            cursor_rect((0.0, 0.0), (0, 0), (8, 8), (8, 8)),
+            [-1.0, 1.0, 1.0, -1.0]
+        );
+    }
+
+    #[test]
+    fn test_cursor_sprite_is_drawn_at_position() {
+        // Skip on machines without any adapter
+        let Some(mut ctx) = headless_canvas((4, 4), (4, 4)) else {
+            return;
+        };
+        present(&mut ctx).unwrap();
+
+        // Red 2x2 sprite with a transparent bottom right pixel, hotspot in its center
+        let red = [255, 0, 0, 255];
+        let pixels = [red, red, red, [0; 4]].concat();
+        ctx.render.set_cursor_sprite((2, 2), &pixels, (1, 1));
+        let cursor = ctx.render.cursor.as_mut().unwrap();
+        cursor.position = Some((2.5, 2.0));
+
+        let render = &ctx.render;
+        let presented = &render.presented.as_ref().unwrap().texture;
+        let view = presented.create_view(&Default::default());
+        let mut encoder = render.device.create_command_encoder(&Default::default());
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            let cursor = render.cursor.as_ref().unwrap();
+            cursor.draw(&render.queue, &mut render_pass, (4, 4));
+        }
+        let encoder_read = render.device.create_command_encoder(&Default::default());
+        render.queue.submit(std::iter::once(encoder.finish()));
+        let frame = pollster::block_on(read_texture(
+            &render.device,
+            &render.queue,
+            encoder_read,
+            presented,
+        ))
+        .unwrap();
+
+        let canvas = &render.canvas.pixels;
+        for y in 0..4 {
+            for x in 0..4 {
+                let i = (y * 4 + x) * 4;
+                let expected = match (x, y) {
+                    (1, 1) | (2, 1) | (1, 2) => &red[..],
+                    _ => &canvas[i..i + 4],
+                };
+                assert_eq!(&frame.pixels[i..i + 4], expected, "pixel ({}, {})", x, y);
+            }
+        }
+    }
+}
@@ -2,9 +2,42 @@ use std::time;
 
 use crate::Context;
 
+/// Remaining time below which pacing spins instead of sleeping
+///
+/// ```thread::sleep``` commonly overshoots by around a millisecond
+#[cfg(not(feature = "spin_sleep"))]
+const SPIN_THRESHOLD: time::Duration = time::Duration::from_millis(2);
+
+/// Frame rate of the low power mode while unfocused or idle
+const LOW_POWER_FPS: u32 = 10;
+
+/// Time without input after which the low power mode considers the app idle
+const IDLE_TIMEOUT: time::Duration = time::Duration::from_secs(5);
+
+/// Rounding error accepted when comparing accumulated time to the update interval
+const UPDATE_EPSILON: f32 = 1e-5;
+
 pub(crate) struct TimeContext {
     pub(crate) start_time: time::SystemTime,
     pub(crate) current_time: time::SystemTime,
+    pub(crate) max_delta: Option<f32>,
+    pub(crate) raw_delta: f32,
+    pub(crate) update_rate: Option<u32>,
+    pub(crate) update_accumulator: f32,
+    /// Time since the last update in seconds
+    pub(crate) update_elapsed: f32,
+    pub(crate) frame_count: u64,
+    pub(crate) target_fps: Option<u32>,
+    pub(crate) frame_start: time::Instant,
+    pub(crate) frame_time: f32,
+    pub(crate) frame_budget: Option<f32>,
+    /// Start of the update of the current frame
+    pub(crate) work_start: time::Instant,
+    /// Time from ```work_start``` to the end of update or render in seconds
+    pub(crate) work_time: f32,
+    pub(crate) low_power: bool,
+    /// Time of the last input event, used by the low power mode
+    pub(crate) last_input: time::Instant,
 }
 
 impl Default for TimeContext {
@@ -13,19 +46,118 @@ impl Default for TimeContext {
         Self {
             start_time,
             current_time: start_time,
+            max_delta: None,
+            raw_delta: 0.0,
+            update_rate: None,
+            update_accumulator: 0.0,
+            update_elapsed: 0.0,
+            frame_count: 0,
+            target_fps: None,
+            frame_start: time::Instant::now(),
+            frame_time: 0.0,
+            frame_budget: None,
+            work_start: time::Instant::now(),
+            work_time: 0.0,
+            low_power: false,
+            last_input: time::Instant::now(),
         }
     }
 }
 
 impl TimeContext {
     pub(crate) fn update_time(&mut self) -> f32 {
-        let new_time = std::time::SystemTime::now();
+        self.advance_to(std::time::SystemTime::now())
+    }
+
+    /// Moves the frame clock to ```new_time```
+    /// Returns the delta clamped to ```max_delta```
+    pub(crate) fn advance_to(&mut self, new_time: time::SystemTime) -> f32 {
         let dt = new_time
             .duration_since(self.current_time)
             .unwrap()
             .as_secs_f32();
         self.current_time = new_time;
-        dt
+        self.raw_delta = dt;
+        self.frame_count += 1;
+
+        match self.max_delta {
+            Some(max_delta) => dt.min(max_delta),
+            None => dt,
+        }
+    }
+
+    /// Accumulates frame time until the next update should run
+    /// Returns the time since the last update when it is time to update, the remainder carries over
+    pub(crate) fn consume_update(&mut self, dt: f32) -> Option<f32> {
+        let update_rate = match self.update_rate.filter(|rate| *rate > 0) {
+            Some(update_rate) => update_rate,
+            None => return Some(dt),
+        };
+
+        let interval = 1.0 / update_rate as f32;
+        self.update_accumulator += dt;
+        self.update_elapsed += dt;
+        // Tolerates rounding so frames summing to exactly one interval update
+        if self.update_accumulator < interval - UPDATE_EPSILON {
+            return None;
+        }
+
+        self.update_accumulator -= interval;
+        Some(std::mem::take(&mut self.update_elapsed))
+    }
+
+    /// Starts measuring the work of a frame, call before update
+    pub(crate) fn begin_work(&mut self) {
+        self.work_start = time::Instant::now();
+        self.work_time = 0.0;
+    }
+
+    /// Records the work of the frame so far, call after update and after presenting
+    /// Waiting for the next frame afterwards is not counted
+    pub(crate) fn end_work(&mut self) {
+        self.work_time = self.work_start.elapsed().as_secs_f32();
+    }
+
+    /// Waits until the target frame time has passed since the last call
+    /// Records the achieved frame time
+    /// Returns the time spent on the frame before waiting if it exceeded the frame budget
+    pub(crate) fn pace_frame(&mut self) -> Option<f32> {
+        let work = self.work_time;
+        let overrun = self
+            .frame_budget
+            .filter(|budget| work > *budget)
+            .map(|budget| {
+                log::debug!(
+                    "frame took {:.2}ms, budget is {:.2}ms",
+                    work * 1000.0,
+                    budget * 1000.0
+                );
+                work
+            });
+
+        if let Some(target_fps) = self.target_fps.filter(|fps| *fps > 0) {
+            let target = time::Duration::from_secs_f32(1.0 / target_fps as f32);
+            let remaining = sleep_duration(target, self.frame_start.elapsed());
+            wait(remaining);
+        }
+
+        let now = time::Instant::now();
+        self.frame_time = now.duration_since(self.frame_start).as_secs_f32();
+        self.frame_start = now;
+
+        overrun
+    }
+
+    /// Returns the time to wait between frames if the low power mode is active
+    /// Active while low power is enabled and the window is unfocused or has not received input recently
+    pub(crate) fn low_power_interval(
+        &self,
+        focused: bool,
+        now: time::Instant,
+    ) -> Option<time::Duration> {
+        let idle = now.duration_since(self.last_input) >= IDLE_TIMEOUT;
+        (self.low_power && (!focused || idle))
+            .then(|| time::Duration::from_secs_f32(1.0 / LOW_POWER_FPS as f32))
     }
 
     pub(crate) fn time_since_start(&self) -> f32 {
@@ -37,6 +169,102 @@ impl TimeContext {
     }
 }
 
+/// Fires every duration seconds of accumulated delta time
+///
+/// Owned by the user, advance it with the delta passed to update
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timer {
+    duration: f32,
+    elapsed: f32,
+    repeating: bool,
+    finished: bool,
+}
+
+impl Timer {
+    /// Create a repeating timer firing every duration seconds
+    pub fn new(duration: f32) -> Self {
+        Self {
+            duration,
+            elapsed: 0.0,
+            repeating: true,
+            finished: false,
+        }
+    }
+
+    /// Create a timer firing once after duration seconds
+    pub fn once(duration: f32) -> Self {
+        Self {
+            repeating: false,
+            ..Self::new(duration)
+        }
+    }
+
+    /// Advance the timer by dt seconds, returns true on the tick it elapses
+    ///
+    /// Repeating timers keep the leftover time so they do not drift
+    pub fn tick(&mut self, dt: f32) -> bool {
+        if self.finished {
+            return false;
+        }
+        self.elapsed += dt;
+        if self.elapsed < self.duration {
+            return false;
+        }
+        if self.repeating && self.duration > 0.0 {
+            self.elapsed %= self.duration;
+        } else {
+            self.elapsed = self.duration;
+            self.finished = true;
+        }
+        true
+    }
+
+    /// Progress towards the next firing in range [0,1]
+    pub fn progress(&self) -> f32 {
+        if self.duration <= 0.0 {
+            1.0
+        } else {
+            (self.elapsed / self.duration).min(1.0)
+        }
+    }
+
+    /// Returns true once a one shot timer has fired
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Start over without firing
+    pub fn reset(&mut self) {
+        self.elapsed = 0.0;
+        self.finished = false;
+    }
+}
+
+/// Returns how long to wait for a frame that has taken ```elapsed``` to reach ```target```
+fn sleep_duration(target: time::Duration, elapsed: time::Duration) -> time::Duration {
+    target.saturating_sub(elapsed)
+}
+
+/// Sleeps for ```duration``` with sub millisecond precision
+#[cfg(feature = "spin_sleep")]
+fn wait(duration: time::Duration) {
+    spin_sleep::sleep(duration);
+}
+
+/// Sleeps for ```duration``` with sub millisecond precision
+///
+/// Coarse sleeps until close to the deadline and spins the rest
+#[cfg(not(feature = "spin_sleep"))]
+fn wait(duration: time::Duration) {
+    let deadline = time::Instant::now() + duration;
+    if duration > SPIN_THRESHOLD {
+        std::thread::sleep(duration - SPIN_THRESHOLD);
+    }
+    while time::Instant::now() < deadline {
+        std::hint::spin_loop();
+    }
+}
+
 //
 // Commands
 //
@@ -50,3 +278,258 @@ pub fn time_since_start(ctx: &Context) -> f32 {
 pub fn current_time(ctx: &Context) -> time::SystemTime {
     ctx.time.current_time
 }
+
+/// Set the maximum delta time passed to update
+///
+/// Protects against huge time steps after the window has been stalled
+///
+/// None disables clamping
+pub fn set_max_delta(ctx: &mut Context, max_delta: Option<f32>) {
+    ctx.time.max_delta = max_delta;
+}
+
+/// Returns the real time since last frame in seconds
+///
+/// Not affected by ```set_max_delta```
+pub fn raw_delta(ctx: &Context) -> f32 {
+    ctx.time.raw_delta
+}
+
+/// Returns the number of frames since the start of the application
+pub fn frame_count(ctx: &Context) -> u64 {
+    ctx.time.frame_count
+}
+
+/// Returns the frames per second based on the last frame
+pub fn fps(ctx: &Context) -> f32 {
+    if ctx.time.raw_delta > 0.0 {
+        1.0 / ctx.time.raw_delta
+    } else {
+        0.0
+    }
+}
+
+/// Set how many times per second update is called
+///
+/// Rendering still happens every frame, update receives the time since the last update as dt
+///
+/// None or 0 calls update every frame
+pub fn set_update_rate(ctx: &mut Context, update_rate: Option<u32>) {
+    ctx.time.update_rate = update_rate.filter(|rate| *rate > 0);
+    ctx.time.update_accumulator = 0.0;
+    ctx.time.update_elapsed = 0.0;
+}
+
+/// Limit the frame rate by waiting at the end of each frame
+///
+/// Useful when vsync is disabled
+///
+/// None or 0 disables the limit
+pub fn set_target_fps(ctx: &mut Context, target_fps: Option<u32>) {
+    ctx.time.target_fps = target_fps.filter(|fps| *fps > 0);
+}
+
+/// Drop to 10 frames per second while the window is unfocused or idle
+///
+/// Idle is 5 seconds without input, focus or input restores the full frame rate
+///
+/// The event loop sleeps between frames instead of polling, saving battery for background apps
+pub fn set_low_power_mode(ctx: &mut Context, enabled: bool) {
+    ctx.time.low_power = enabled;
+}
+
+/// Returns the achieved duration of the last frame in seconds
+///
+/// Includes the time spent waiting for ```set_target_fps```
+pub fn frame_time(ctx: &Context) -> f32 {
+    ctx.time.frame_time
+}
+
+/// Warn when the work of a frame takes longer than budget seconds
+///
+/// Work is measured from the start of update until the frame is presented,
+/// waiting for ```set_target_fps``` afterwards is not counted
+///
+/// Overruns are logged at debug level and passed to ```Callbacks::on_frame_overrun```
+///
+/// None disables the warning
+pub fn set_frame_budget(ctx: &mut Context, budget: Option<f32>) {
+    ctx.time.frame_budget = budget;
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::{sleep_duration, TimeContext, Timer, IDLE_TIMEOUT, LOW_POWER_FPS};
+    use std::time::Duration;
+
+    #[test]
+    fn test_max_delta_clamps() {
+        let mut time = TimeContext {
+            max_delta: Some(0.1),
+            ..Default::default()
+        };
+        let new_time = time.current_time + Duration::from_secs(2);
+
+        let dt = time.advance_to(new_time);
+
+        assert_eq!(dt, 0.1);
+        assert_eq!(time.raw_delta, 2.0);
+        assert_eq!(time.frame_count, 1);
+    }
+
+    #[test]
+    fn test_no_max_delta() {
+        let mut time = TimeContext::default();
+        let new_time = time.current_time + Duration::from_secs(2);
+
+        let dt = time.advance_to(new_time);
+
+        assert_eq!(dt, 2.0);
+        assert_eq!(time.raw_delta, 2.0);
+    }
+
+    #[test]
+    fn test_update_rate() {
+        let mut time = TimeContext {
+            update_rate: Some(10),
+            ..Default::default()
+        };
+
+        let updates = (0..6)
+            .map(|_| time.consume_update(0.04))
+            .collect::<Vec<_>>();
+
+        // The 0.02 left over after the first update carries into the second
+        let expected = [None, None, Some(0.12), None, Some(0.08), None];
+        for (update, expected) in updates.iter().zip(expected) {
+            assert_eq!(update.is_some(), expected.is_some());
+            if let (Some(dt), Some(expected)) = (update, expected) {
+                assert!((dt - expected).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_update_rate_cadence() {
+        let mut time = TimeContext {
+            update_rate: Some(30),
+            ..Default::default()
+        };
+
+        let updates = (0..60)
+            .filter_map(|_| time.consume_update(1.0 / 60.0))
+            .collect::<Vec<_>>();
+
+        assert_eq!(updates.len(), 30);
+        assert!(updates.iter().all(|dt| (dt - 1.0 / 30.0).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_zero_update_rate() {
+        let mut time = TimeContext {
+            update_rate: Some(0),
+            ..Default::default()
+        };
+
+        assert_eq!(time.consume_update(0.04), Some(0.04));
+    }
+
+    #[test]
+    fn test_no_update_rate() {
+        let mut time = TimeContext::default();
+
+        assert_eq!(time.consume_update(0.04), Some(0.04));
+    }
+
+    #[test]
+    fn test_sleep_duration() {
+        let target = Duration::from_millis(16);
+
+        assert_eq!(
+            sleep_duration(target, Duration::from_millis(10)),
+            Duration::from_millis(6)
+        );
+        assert_eq!(sleep_duration(target, target), Duration::ZERO);
+        assert_eq!(
+            sleep_duration(target, Duration::from_millis(20)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_pace_frame_reaches_target() {
+        let mut time = TimeContext {
+            target_fps: Some(100),
+            ..Default::default()
+        };
+
+        time.pace_frame();
+
+        assert!(time.frame_time >= 0.01);
+    }
+
+    #[test]
+    fn test_pace_frame_zero_target() {
+        let mut time = TimeContext {
+            target_fps: Some(0),
+            ..Default::default()
+        };
+
+        // Zero means no limit instead of an infinite wait
+        assert_eq!(time.pace_frame(), None);
+    }
+
+    #[test]
+    fn test_frame_budget_overrun() {
+        let mut time = TimeContext {
+            frame_budget: Some(0.005),
+            ..Default::default()
+        };
+
+        time.begin_work();
+        std::thread::sleep(Duration::from_millis(10));
+        time.end_work();
+        let overrun = time.pace_frame();
+        assert!(overrun.unwrap() >= 0.01);
+
+        // Waiting after the work, such as for vsync, is not counted
+        time.begin_work();
+        time.end_work();
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(time.pace_frame(), None);
+    }
+
+    #[test]
+    fn test_timer_repeats() {
+        let mut timer = Timer::new(0.5);
+        let fired: Vec<usize> = (1..=10).filter(|_| timer.tick(0.125)).collect();
+        assert_eq!(fired, vec![4, 8]);
+        assert_eq!(timer.progress(), 0.5);
+
+        let mut timer = Timer::once(0.25);
+        let fired: Vec<usize> = (1..=10).filter(|_| timer.tick(0.125)).collect();
+        assert_eq!(fired, vec![2]);
+        assert!(timer.finished());
+        assert_eq!(timer.progress(), 1.0);
+    }
+
+    #[test]
+    fn test_low_power_interval() {
+        let mut time = TimeContext::default();
+        let now = time.last_input;
+        let low = Some(Duration::from_secs_f32(1.0 / LOW_POWER_FPS as f32));
+        assert_eq!(time.low_power_interval(false, now), None);
+
+        time.low_power = true;
+        assert_eq!(time.low_power_interval(true, now), None);
+        assert_eq!(time.low_power_interval(false, now), low);
+        assert_eq!(time.low_power_interval(true, now), None);
+
+        // Idle while focused until the next input
+        let later = now + IDLE_TIMEOUT;
+        assert_eq!(time.low_power_interval(true, later), low);
+        time.last_input = later;
+        assert_eq!(time.low_power_interval(true, later), None);
+    }
+}
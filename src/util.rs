@@ -0,0 +1,463 @@
+/// Small deterministic random number generator
+///
+/// Uses PCG32 (<https://www.pcg-random.org>), the same seed produces the same sequence on all platforms
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+    inc: u64,
+}
+
+impl Rng {
+    const MULTIPLIER: u64 = 6364136223846793005;
+    const INCREMENT: u64 = 1442695040888963407;
+
+    /// Create generator from a seed
+    pub fn seed(seed: u64) -> Self {
+        let mut rng = Self {
+            state: 0,
+            inc: Self::INCREMENT,
+        };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    /// Random u32 in full range
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(Self::MULTIPLIER).wrapping_add(self.inc);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    /// Random f32 in range [0,1)
+    pub fn next_f32(&mut self) -> f32 {
+        // 24 bits fit exactly in the f32 mantissa
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    /// Random i32 in range [lo,hi)
+    ///
+    /// Panics if hi <= lo
+    pub fn range(&mut self, lo: i32, hi: i32) -> i32 {
+        assert!(lo < hi, "empty range [{}, {})", lo, hi);
+        let span = (hi as i64 - lo as i64) as u64;
+        let offset = (self.next_u32() as u64 * span) >> 32;
+        (lo as i64 + offset as i64) as i32
+    }
+
+    /// Random f32 in range [lo,hi)
+    pub fn range_f32(&mut self, lo: f32, hi: f32) -> f32 {
+        lo + (hi - lo) * self.next_f32()
+    }
+}
+
+/// Easing curves by Robert Penner, mapping progress [0,1] to eased progress
+///
+/// Back and elastic curves overshoot outside [0,1]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    ExpoIn,
+    ExpoOut,
+    ExpoInOut,
+    BackIn,
+    BackOut,
+    BackInOut,
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+}
+
+impl Easing {
+    /// Eased value of t, t is clamped to [0,1]
+    pub fn apply(&self, t: f32) -> f32 {
+        use std::f32::consts::PI;
+        const BACK: f32 = 1.70158;
+        const BACK_IN_OUT: f32 = BACK * 1.525;
+
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::QuadInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Easing::SineIn => 1.0 - (t * PI / 2.0).cos(),
+            Easing::SineOut => (t * PI / 2.0).sin(),
+            Easing::SineInOut => -((PI * t).cos() - 1.0) / 2.0,
+            Easing::ExpoIn => {
+                if t == 0.0 {
+                    0.0
+                } else {
+                    2f32.powf(10.0 * t - 10.0)
+                }
+            }
+            Easing::ExpoOut => {
+                if t == 1.0 {
+                    1.0
+                } else {
+                    1.0 - 2f32.powf(-10.0 * t)
+                }
+            }
+            Easing::ExpoInOut => {
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else if t < 0.5 {
+                    2f32.powf(20.0 * t - 10.0) / 2.0
+                } else {
+                    (2.0 - 2f32.powf(-20.0 * t + 10.0)) / 2.0
+                }
+            }
+            Easing::BackIn => (BACK + 1.0) * t * t * t - BACK * t * t,
+            Easing::BackOut => 1.0 + (BACK + 1.0) * (t - 1.0).powi(3) + BACK * (t - 1.0).powi(2),
+            Easing::BackInOut => {
+                if t < 0.5 {
+                    (2.0 * t).powi(2) * ((BACK_IN_OUT + 1.0) * 2.0 * t - BACK_IN_OUT) / 2.0
+                } else {
+                    ((2.0 * t - 2.0).powi(2)
+                        * ((BACK_IN_OUT + 1.0) * (t * 2.0 - 2.0) + BACK_IN_OUT)
+                        + 2.0)
+                        / 2.0
+                }
+            }
+            Easing::ElasticIn => {
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else {
+                    -2f32.powf(10.0 * t - 10.0) * ((t * 10.0 - 10.75) * (2.0 * PI / 3.0)).sin()
+                }
+            }
+            Easing::ElasticOut => {
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else {
+                    2f32.powf(-10.0 * t) * ((t * 10.0 - 0.75) * (2.0 * PI / 3.0)).sin() + 1.0
+                }
+            }
+            Easing::ElasticInOut => {
+                let wave = ((20.0 * t - 11.125) * (2.0 * PI / 4.5)).sin();
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else if t < 0.5 {
+                    -(2f32.powf(20.0 * t - 10.0) * wave) / 2.0
+                } else {
+                    2f32.powf(-20.0 * t + 10.0) * wave / 2.0 + 1.0
+                }
+            }
+            Easing::BounceIn => 1.0 - bounce_out(1.0 - t),
+            Easing::BounceOut => bounce_out(t),
+            Easing::BounceInOut => {
+                if t < 0.5 {
+                    (1.0 - bounce_out(1.0 - 2.0 * t)) / 2.0
+                } else {
+                    (1.0 + bounce_out(2.0 * t - 1.0)) / 2.0
+                }
+            }
+        }
+    }
+}
+
+fn bounce_out(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+    if t < 1.0 / D {
+        N * t * t
+    } else if t < 2.0 / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
+
+/// Interpolates a value from start to end over a duration in seconds
+///
+/// Advance with ```update``` using the frame delta
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tween {
+    start: f32,
+    end: f32,
+    duration: f32,
+    elapsed: f32,
+    easing: Easing,
+}
+
+impl Tween {
+    /// Create tween from start to end taking duration seconds
+    pub fn new(start: f32, end: f32, duration: f32, easing: Easing) -> Self {
+        Self {
+            start,
+            end,
+            duration,
+            elapsed: 0.0,
+            easing,
+        }
+    }
+
+    /// Move the tween forward by dt seconds
+    pub fn update(&mut self, dt: f32) {
+        self.elapsed = (self.elapsed + dt).min(self.duration);
+    }
+
+    /// Start over from the start value
+    pub fn reset(&mut self) {
+        self.elapsed = 0.0;
+    }
+
+    /// Progress in range [0,1] before easing
+    pub fn progress(&self) -> f32 {
+        if self.duration <= 0.0 {
+            1.0
+        } else {
+            self.elapsed / self.duration
+        }
+    }
+
+    /// Current interpolated value
+    pub fn value(&self) -> f32 {
+        let t = self.easing.apply(self.progress());
+        self.start + (self.end - self.start) * t
+    }
+
+    /// Returns true once the duration has passed
+    pub fn finished(&self) -> bool {
+        self.progress() >= 1.0
+    }
+}
+
+/// Hashes a lattice point, every seed gives an unrelated set of values
+fn hash_2d(x: i32, y: i32, seed: u64) -> u32 {
+    // SplitMix64 finalizer over the packed coordinates
+    let mut h = seed ^ ((x as u32 as u64) << 32 | y as u32 as u64);
+    h = h.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    (h ^ (h >> 31)) as u32
+}
+
+/// Quintic fade, smooth interpolation with zero first and second derivative at 0 and 1
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Value noise at (x, y) in range [-1,1]
+///
+/// Random values on the integer lattice smoothly interpolated, the same input always gives the same value
+pub fn value_noise_2d(x: f32, y: f32, seed: u64) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (tx, ty) = (fade(x - x0), fade(y - y0));
+    let (x0, y0) = (x0 as i32, y0 as i32);
+    let value = |dx: i32, dy: i32| {
+        hash_2d(x0.wrapping_add(dx), y0.wrapping_add(dy), seed) as f32 / u32::MAX as f32 * 2.0 - 1.0
+    };
+    lerp(
+        lerp(value(0, 0), value(1, 0), tx),
+        lerp(value(0, 1), value(1, 1), tx),
+        ty,
+    )
+}
+
+/// Perlin gradient noise at (x, y) in range [-1,1]
+///
+/// Zero on the integer lattice, the same input always gives the same value
+pub fn perlin_2d(x: f32, y: f32, seed: u64) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (x0, y0) = (x0 as i32, y0 as i32);
+    // Dot product of one of 8 unit gradients with the offset to the corner
+    let gradient = |dx: i32, dy: i32| {
+        let angle = (hash_2d(x0.wrapping_add(dx), y0.wrapping_add(dy), seed) & 7) as f32
+            * std::f32::consts::FRAC_PI_4;
+        let (ox, oy) = (fx - dx as f32, fy - dy as f32);
+        angle.cos() * ox + angle.sin() * oy
+    };
+    let (tx, ty) = (fade(fx), fade(fy));
+    let noise = lerp(
+        lerp(gradient(0, 0), gradient(1, 0), tx),
+        lerp(gradient(0, 1), gradient(1, 1), tx),
+        ty,
+    );
+    // Unit gradients reach at most sqrt(1/2)
+    (noise * std::f32::consts::SQRT_2).clamp(-1.0, 1.0)
+}
+
+/// Fractal Brownian motion at (x, y) in range [-1,1]
+///
+/// Sums octaves of ```perlin_2d```, each with double the frequency and half the amplitude
+///
+/// 0 octaves returns 0
+pub fn fbm_2d(x: f32, y: f32, octaves: u32, seed: u64) -> f32 {
+    let (mut sum, mut total, mut amplitude, mut frequency) = (0.0, 0.0, 1.0, 1.0);
+    for octave in 0..octaves {
+        let octave_seed = seed.wrapping_add(octave as u64);
+        sum += perlin_2d(x * frequency, y * frequency, octave_seed) * amplitude;
+        total += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    if total == 0.0 {
+        0.0
+    } else {
+        sum / total
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::{fbm_2d, perlin_2d, value_noise_2d, Easing, Rng, Tween};
+
+    #[test]
+    fn test_same_seed_same_sequence() {
+        let mut a = Rng::seed(42);
+        let mut b = Rng::seed(42);
+
+        for _ in 0..100 {
+            assert_eq!(a.next_u32(), b.next_u32());
+        }
+    }
+
+    #[test]
+    fn test_different_seed_different_sequence() {
+        let mut a = Rng::seed(1);
+        let mut b = Rng::seed(2);
+
+        let a = (0..10).map(|_| a.next_u32()).collect::<Vec<_>>();
+        let b = (0..10).map(|_| b.next_u32()).collect::<Vec<_>>();
+
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_ranges() {
+        let mut rng = Rng::seed(7);
+
+        for _ in 0..1000 {
+            let f = rng.next_f32();
+            assert!((0.0..1.0).contains(&f));
+
+            let i = rng.range(-3, 5);
+            assert!((-3..5).contains(&i));
+
+            let f = rng.range_f32(2.0, 4.0);
+            assert!((2.0..4.0).contains(&f));
+        }
+    }
+
+    #[test]
+    fn test_linear_tween() {
+        let mut tween = Tween::new(10.0, 20.0, 2.0, Easing::Linear);
+        assert_eq!(tween.value(), 10.0);
+
+        tween.update(1.0);
+        assert_eq!(tween.value(), 15.0);
+        assert!(!tween.finished());
+
+        tween.update(5.0);
+        assert_eq!(tween.value(), 20.0);
+        assert!(tween.finished());
+    }
+
+    #[test]
+    fn test_easing_endpoints() {
+        let easings = [
+            Easing::Linear,
+            Easing::QuadIn,
+            Easing::QuadOut,
+            Easing::QuadInOut,
+            Easing::CubicIn,
+            Easing::CubicOut,
+            Easing::CubicInOut,
+            Easing::SineIn,
+            Easing::SineOut,
+            Easing::SineInOut,
+            Easing::ExpoIn,
+            Easing::ExpoOut,
+            Easing::ExpoInOut,
+            Easing::BackIn,
+            Easing::BackOut,
+            Easing::BackInOut,
+            Easing::ElasticIn,
+            Easing::ElasticOut,
+            Easing::ElasticInOut,
+            Easing::BounceIn,
+            Easing::BounceOut,
+            Easing::BounceInOut,
+        ];
+        for easing in easings {
+            assert!(easing.apply(0.0).abs() < 1e-5, "{:?}", easing);
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-5, "{:?}", easing);
+        }
+        assert_eq!(Easing::QuadInOut.apply(0.5), 0.5);
+        assert_eq!(Easing::BounceInOut.apply(0.5), 0.5);
+        assert!((Easing::ElasticInOut.apply(0.5) - 0.5).abs() < 1e-5);
+        assert!(Easing::BackOut.apply(0.7) > 1.0);
+        assert!(Easing::ElasticIn.apply(0.9) < 0.0);
+    }
+
+    #[test]
+    fn test_noise_deterministic_and_in_range() {
+        let mut rng = Rng::seed(3);
+        for _ in 0..1000 {
+            let x = rng.next_f32() * 200.0 - 100.0;
+            let y = rng.next_f32() * 200.0 - 100.0;
+            for noise in [
+                value_noise_2d(x, y, 5),
+                perlin_2d(x, y, 5),
+                fbm_2d(x, y, 5, 5),
+            ] {
+                assert!((-1.0..=1.0).contains(&noise), "{} out of range", noise);
+            }
+            assert_eq!(value_noise_2d(x, y, 5), value_noise_2d(x, y, 5));
+            assert_eq!(perlin_2d(x, y, 5), perlin_2d(x, y, 5));
+            assert_eq!(fbm_2d(x, y, 4, 9), fbm_2d(x, y, 4, 9));
+        }
+
+        assert_eq!(perlin_2d(3.0, -2.0, 1), 0.0);
+        assert_eq!(fbm_2d(1.5, 2.5, 0, 1), 0.0);
+        // Other seeds give other noise
+        let values = |seed| {
+            (0..16)
+                .map(|i| value_noise_2d(i as f32 * 0.7, 0.3, seed))
+                .collect::<Vec<_>>()
+        };
+        assert_ne!(values(1), values(2));
+    }
+}
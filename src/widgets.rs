@@ -0,0 +1,310 @@
+use crate::{
+    canvas::Canvas,
+    font,
+    input::{self, MouseButton},
+    Context,
+};
+
+/// Idle widget background
+const IDLE_COLOR: [u8; 4] = [60, 60, 60, 220];
+/// Background while the mouse is over the widget
+const HOT_COLOR: [u8; 4] = [90, 90, 90, 230];
+/// Background while the widget is held down
+const ACTIVE_COLOR: [u8; 4] = [130, 130, 130, 240];
+/// Label and slider handle color
+const FOREGROUND_COLOR: [u8; 4] = [255, 255, 255, 255];
+/// Width of the slider handle in pixels
+const HANDLE_WIDTH: u32 = 3;
+
+/// Left mouse state read by the widgets
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Pointer {
+    /// Position in canvas pixels, None while the mouse is off screen
+    pub(crate) pos: Option<(i32, i32)>,
+    pub(crate) just_pressed: bool,
+    pub(crate) released: bool,
+}
+
+impl Pointer {
+    /// Reads the left mouse button, positions are moved into the current transform
+    /// Ignores ```input::consume_mouse``` so widgets keep working after one consumed the mouse
+    fn read(ctx: &Context) -> Self {
+        let pos = input::mouse_on_screen(ctx).then(|| {
+            let (x, y) = input::mouse_pos_pixel(ctx);
+            let (dx, dy) = ctx.render.canvas.offset();
+            (x as i32 - dx, y as i32 - dy)
+        });
+        let (just_pressed, released) = ctx.input.mouse.button_transition(MouseButton::Left);
+        Self {
+            pos,
+            just_pressed,
+            released,
+        }
+    }
+}
+
+/// Which widget is under the mouse and which is held down
+#[derive(Debug, Default)]
+pub(crate) struct WidgetContext {
+    hot: Option<u64>,
+    active: Option<u64>,
+}
+
+impl WidgetContext {
+    /// Updates the hot and active widget
+    /// Returns true if the widget was pressed and released while hovered
+    fn interact(&mut self, id: u64, rect: (i32, i32, u32, u32), pointer: &Pointer) -> bool {
+        let hovered = pointer.pos.is_some_and(|pos| contains(rect, pos));
+        if hovered {
+            self.hot = Some(id);
+        } else if self.hot == Some(id) {
+            self.hot = None;
+        }
+        if hovered && pointer.just_pressed {
+            self.active = Some(id);
+        }
+        if self.active == Some(id) && pointer.released {
+            self.active = None;
+            return hovered;
+        }
+        false
+    }
+
+    /// Returns true if the widget is under the mouse or held down
+    fn captures_pointer(&self, id: u64) -> bool {
+        self.hot == Some(id) || self.active == Some(id)
+    }
+
+    /// Background color for the current state of the widget
+    fn background(&self, id: u64) -> [u8; 4] {
+        if self.active == Some(id) {
+            ACTIVE_COLOR
+        } else if self.hot == Some(id) {
+            HOT_COLOR
+        } else {
+            IDLE_COLOR
+        }
+    }
+
+    /// Draw a button with a centered label, returns true when clicked
+    pub(crate) fn button(
+        &mut self,
+        canvas: &mut Canvas,
+        pointer: &Pointer,
+        id: u64,
+        rect: (i32, i32, u32, u32),
+        label: &str,
+    ) -> bool {
+        let clicked = self.interact(id, rect, pointer);
+
+        let (x, y, w, h) = rect;
+        canvas.fill_rect(x, y, w, h, &self.background(id));
+        let label_x = x + (w as i32 - font::text_width(label) as i32) / 2;
+        let label_y = y + (h as i32 - font::text_height(label) as i32) / 2;
+        canvas.draw_text(label, label_x, label_y, &FOREGROUND_COLOR);
+
+        clicked
+    }
+
+    /// Draw a horizontal slider for value in range [min, max], returns true if value changed
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn slider(
+        &mut self,
+        canvas: &mut Canvas,
+        pointer: &Pointer,
+        id: u64,
+        rect: (i32, i32, u32, u32),
+        value: &mut f32,
+        min: f32,
+        max: f32,
+    ) -> bool {
+        let was_active = self.active == Some(id);
+        self.interact(id, rect, pointer);
+
+        let (x, y, w, h) = rect;
+        let travel = w.saturating_sub(HANDLE_WIDTH).max(1) as f32;
+        let mut changed = false;
+        // Keeps the value while dragged off screen
+        let dragging = was_active || self.active == Some(id);
+        if let (true, Some((pos_x, _))) = (dragging, pointer.pos) {
+            let t = ((pos_x - x - HANDLE_WIDTH as i32 / 2) as f32 / travel).clamp(0.0, 1.0);
+            let dragged = min + t * (max - min);
+            if dragged != *value {
+                *value = dragged;
+                changed = true;
+            }
+        }
+
+        canvas.fill_rect(x, y, w, h, &self.background(id));
+        let t = if max == min {
+            0.0
+        } else {
+            ((*value - min) / (max - min)).clamp(0.0, 1.0)
+        };
+        let handle_x = x + (t * travel).round() as i32;
+        canvas.fill_rect(handle_x, y, HANDLE_WIDTH, h, &FOREGROUND_COLOR);
+
+        changed
+    }
+}
+
+/// Returns true if pos is inside rect (x, y, w, h)
+fn contains(rect: (i32, i32, u32, u32), pos: (i32, i32)) -> bool {
+    let (x, y, w, h) = rect;
+    pos.0 >= x && pos.1 >= y && pos.0 < x + w as i32 && pos.1 < y + h as i32
+}
+
+//
+// Commands
+//
+
+/// Draw a button at rect (x, y, w, h), returns true the frame it is clicked
+///
+/// A click is a left press and release over the button, ids must be unique among widgets
+///
+/// Rects are in canvas pixels and follow the current transform
+///
+/// Consumes the mouse while hovered or held, see ```input::consume_mouse```
+pub fn button(ctx: &mut Context, id: u64, rect: (i32, i32, u32, u32), label: &str) -> bool {
+    let pointer = Pointer::read(ctx);
+    let clicked = ctx
+        .widgets
+        .button(&mut ctx.render.canvas, &pointer, id, rect, label);
+    if ctx.widgets.captures_pointer(id) || clicked {
+        input::consume_mouse(ctx);
+    }
+    clicked
+}
+
+/// Draw a horizontal slider at rect (x, y, w, h) controlling value in range \[min,max\]
+///
+/// Dragging with the left mouse button sets value, returns true if value changed
+///
+/// Rects are in canvas pixels and follow the current transform
+///
+/// Consumes the mouse while hovered or dragged, see ```input::consume_mouse```
+pub fn slider(
+    ctx: &mut Context,
+    id: u64,
+    rect: (i32, i32, u32, u32),
+    value: &mut f32,
+    min: f32,
+    max: f32,
+) -> bool {
+    let pointer = Pointer::read(ctx);
+    let changed = ctx
+        .widgets
+        .slider(&mut ctx.render.canvas, &pointer, id, rect, value, min, max);
+    if ctx.widgets.captures_pointer(id) {
+        input::consume_mouse(ctx);
+    }
+    changed
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::{Pointer, WidgetContext, ACTIVE_COLOR, HOT_COLOR, IDLE_COLOR};
+    use crate::canvas::Canvas;
+
+    #[test]
+    fn test_button_click() {
+        let mut widgets = WidgetContext::default();
+        let mut canvas = Canvas::new(32, 16);
+        let rect = (4, 4, 20, 8);
+        let mut frame = |pos, just_pressed, released| {
+            let pointer = Pointer {
+                pos: Some(pos),
+                just_pressed,
+                released,
+            };
+            widgets.button(&mut canvas, &pointer, 1, rect, "OK")
+        };
+
+        assert!(!frame((10, 6), true, false));
+        assert!(!frame((10, 6), false, false));
+        assert!(frame((11, 7), false, true));
+
+        // Releasing outside cancels the click
+        assert!(!frame((10, 6), true, false));
+        assert!(!frame((30, 6), false, true));
+
+        // Pressing outside and releasing inside is not a click
+        assert!(!frame((0, 0), true, false));
+        assert!(!frame((10, 6), false, true));
+    }
+
+    #[test]
+    fn test_button_style() {
+        let mut widgets = WidgetContext::default();
+        let mut canvas = Canvas::new(8, 8);
+        let rect = (0, 0, 4, 4);
+        let mut press = |widgets: &mut WidgetContext, pos, just_pressed| {
+            let pointer = Pointer {
+                pos: Some(pos),
+                just_pressed,
+                released: false,
+            };
+            widgets.button(&mut canvas, &pointer, 7, rect, "");
+            widgets.background(7)
+        };
+
+        assert_eq!(press(&mut widgets, (6, 6), false), IDLE_COLOR);
+        assert!(!widgets.captures_pointer(7));
+        assert_eq!(press(&mut widgets, (1, 1), false), HOT_COLOR);
+        assert!(widgets.captures_pointer(7));
+        assert_eq!(press(&mut widgets, (1, 1), true), ACTIVE_COLOR);
+    }
+
+    #[test]
+    fn test_slider_drag() {
+        let mut widgets = WidgetContext::default();
+        let mut canvas = Canvas::new(32, 8);
+        let rect = (0, 0, 23, 4);
+        let mut value = 0.0;
+
+        let mut frame = |pos, just_pressed, released, value: &mut f32| {
+            let pointer = Pointer {
+                pos: Some(pos),
+                just_pressed,
+                released,
+            };
+            widgets.slider(&mut canvas, &pointer, 2, rect, value, 0.0, 10.0)
+        };
+
+        assert!(frame((11, 1), true, false, &mut value));
+        assert_eq!(value, 5.0);
+        // Dragging outside clamps to the range
+        assert!(frame((40, 1), false, false, &mut value));
+        assert_eq!(value, 10.0);
+        assert!(!frame((40, 1), false, true, &mut value));
+        assert!(!frame((1, 1), false, false, &mut value));
+        assert_eq!(value, 10.0);
+    }
+
+    #[test]
+    fn test_slider_drag_off_screen() {
+        let mut widgets = WidgetContext::default();
+        let mut canvas = Canvas::new(32, 8);
+        let rect = (0, 0, 23, 4);
+        let mut value = 0.0;
+        let mut frame = |pos, just_pressed, released, value: &mut f32| {
+            let pointer = Pointer {
+                pos,
+                just_pressed,
+                released,
+            };
+            widgets.slider(&mut canvas, &pointer, 3, rect, value, 0.0, 10.0)
+        };
+
+        assert!(frame(Some((11, 1)), true, false, &mut value));
+        assert_eq!(value, 5.0);
+        // Leaving the window while dragging keeps the value
+        assert!(!frame(None, false, false, &mut value));
+        assert_eq!(value, 5.0);
+        // Dragging continues when the mouse returns
+        assert!(frame(Some((1, 1)), false, false, &mut value));
+        assert_eq!(value, 0.0);
+        assert!(!frame(None, false, true, &mut value));
+    }
+}
@@ -1,104 +1,502 @@
 #![allow(clippy::single_match, clippy::collapsible_match)]
 
+pub use winit::window::CursorIcon;
+
 use crate::{
     app::{App, Callbacks},
+    canvas::Sprite,
+    config::Config,
     context::Context,
+    input,
+};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    time::Instant,
 };
 use winit::{
-    event::{DeviceEvent, ElementState, Event, WindowEvent},
+    dpi::PhysicalSize,
+    event::{DeviceEvent, Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    monitor::{MonitorHandle, VideoMode},
+    window::{Window, WindowBuilder},
 };
 
-pub(crate) fn new_window() -> (winit::window::Window, winit::event_loop::EventLoop<()>) {
+pub(crate) struct WindowContext {
+    pub(crate) focused: bool,
+    pub(crate) exit_requested: bool,
+    pub(crate) aspect_ratio: Option<(u32, u32)>,
+    pub(crate) skip_identical_frames: bool,
+    /// Canvas hash of the last presented frame
+    pub(crate) presented_hash: Option<u64>,
+    pub(crate) rendering_enabled: bool,
+    /// Keep calling update while rendering is disabled
+    pub(crate) update_while_disabled: bool,
+    /// The surface is dropped until the app is resumed
+    pub(crate) suspended: bool,
+    /// See ```set_cursor_enabled```
+    pub(crate) cursor_enabled: bool,
+}
+
+/// What to do with the surface after a lifecycle event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SurfaceAction {
+    Drop,
+    Recreate,
+}
+
+impl Default for WindowContext {
+    fn default() -> Self {
+        Self {
+            focused: true,
+            exit_requested: false,
+            aspect_ratio: None,
+            skip_identical_frames: false,
+            presented_hash: None,
+            rendering_enabled: true,
+            update_while_disabled: true,
+            suspended: false,
+            cursor_enabled: true,
+        }
+    }
+}
+
+impl WindowContext {
+    /// Updates the focus state
+    /// Returns true if it changed
+    pub(crate) fn set_focused(&mut self, focused: bool) -> bool {
+        let changed = self.focused != focused;
+        self.focused = focused;
+        changed
+    }
+
+    /// Returns the size the window should have after being resized to size
+    /// None if no change is needed
+    pub(crate) fn locked_size(&self, size: PhysicalSize<u32>) -> Option<PhysicalSize<u32>> {
+        let ratio = self.aspect_ratio?;
+        let locked = fit_aspect_ratio((size.width, size.height), ratio);
+        let locked = PhysicalSize::new(locked.0, locked.1);
+        (locked != size).then_some(locked)
+    }
+
+    /// Returns the canvas hash of this frame, None unless identical frames are skipped
+    /// Hash is only computed when skipping is enabled
+    pub(crate) fn frame_hash(&self, hash: impl FnOnce() -> u64) -> Option<u64> {
+        self.skip_identical_frames.then(hash)
+    }
+
+    /// Returns false if hash matches the last presented frame
+    /// Store the hash in ```presented_hash``` once the frame has been presented
+    pub(crate) fn should_present(&self, hash: Option<u64>) -> bool {
+        hash.is_none() || hash != self.presented_hash
+    }
+
+    /// Returns true if update should run this frame
+    pub(crate) fn should_update(&self) -> bool {
+        self.rendering_enabled || self.update_while_disabled
+    }
+
+    /// Returns true if the canvas should be uploaded and presented this frame
+    pub(crate) fn should_render(&self, software_canvas_only: bool) -> bool {
+        self.rendering_enabled && !self.suspended && !software_canvas_only
+    }
+
+    /// Updates the suspended state from Suspended and Resumed events
+    /// The Resumed event sent on startup is ignored since the surface already exists
+    pub(crate) fn lifecycle<T>(&mut self, event: &Event<T>) -> Option<SurfaceAction> {
+        match event {
+            Event::Suspended if !self.suspended => {
+                self.suspended = true;
+                Some(SurfaceAction::Drop)
+            }
+            Event::Resumed if self.suspended => {
+                self.suspended = false;
+                self.presented_hash = None;
+                Some(SurfaceAction::Recreate)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns true if the event loop should stop after this frame
+    pub(crate) fn should_exit(&self, update_exit: bool) -> bool {
+        update_exit || self.exit_requested
+    }
+}
+
+/// Monitor connected to the system
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorInfo {
+    /// Human readable name, None if the platform does not report it
+    pub name: Option<String>,
+    /// Resolution in physical pixels (width, height)
+    pub size: (u32, u32),
+    pub scale_factor: f64,
+    /// Refresh rate in Hz, None if the platform does not report it
+    pub refresh_rate: Option<f32>,
+    /// Modes usable with ```set_fullscreen_exclusive```, indexed in the same order
+    pub video_modes: Vec<VideoModeInfo>,
+}
+
+impl From<MonitorHandle> for MonitorInfo {
+    fn from(monitor: MonitorHandle) -> Self {
+        let size = monitor.size();
+        Self {
+            name: monitor.name(),
+            size: (size.width, size.height),
+            scale_factor: monitor.scale_factor(),
+            refresh_rate: monitor.refresh_rate_millihertz().map(millihertz_to_hertz),
+            video_modes: monitor.video_modes().map(VideoModeInfo::from).collect(),
+        }
+    }
+}
+
+/// Resolution and refresh rate a monitor supports in exclusive fullscreen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VideoModeInfo {
+    /// Resolution in physical pixels (width, height)
+    pub size: (u32, u32),
+    pub bit_depth: u16,
+    /// Refresh rate in Hz
+    pub refresh_rate: f32,
+}
+
+impl From<VideoMode> for VideoModeInfo {
+    fn from(mode: VideoMode) -> Self {
+        let size = mode.size();
+        Self {
+            size: (size.width, size.height),
+            bit_depth: mode.bit_depth(),
+            refresh_rate: millihertz_to_hertz(mode.refresh_rate_millihertz()),
+        }
+    }
+}
+
+/// Error returned by ```set_fullscreen_exclusive```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FullscreenError {
+    /// No monitor has the index
+    NoMonitor,
+    /// The monitor has no video mode with the index
+    NoVideoMode,
+}
+
+impl std::fmt::Display for FullscreenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FullscreenError::NoMonitor => write!(f, "monitor does not exist"),
+            FullscreenError::NoVideoMode => write!(f, "video mode does not exist"),
+        }
+    }
+}
+
+impl std::error::Error for FullscreenError {}
+
+/// Error returned by ```set_cursor_sprite```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorError {
+    /// The sprite has no pixels
+    Empty,
+    /// The hotspot is not inside the sprite
+    HotspotOutside { x: u32, y: u32 },
+}
+
+impl std::fmt::Display for CursorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CursorError::Empty => write!(f, "cursor sprite is empty"),
+            CursorError::HotspotOutside { x, y } => {
+                write!(f, "cursor hotspot ({}, {}) is outside the sprite", x, y)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CursorError {}
+
+/// Checks that a width x height cursor image can use hotspot (x, y)
+fn check_cursor(width: u32, height: u32, x: u32, y: u32) -> Result<(), CursorError> {
+    if width == 0 || height == 0 {
+        return Err(CursorError::Empty);
+    }
+    if x >= width || y >= height {
+        return Err(CursorError::HotspotOutside { x, y });
+    }
+    Ok(())
+}
+
+fn millihertz_to_hertz(millihertz: u32) -> f32 {
+    millihertz as f32 / 1000.0
+}
+
+/// Picks the video mode at mode_index of the monitor at monitor_index
+fn select_video_mode<M, V>(
+    mut monitors: impl Iterator<Item = M>,
+    monitor_index: usize,
+    mode_index: usize,
+    video_modes: impl FnOnce(M) -> V,
+) -> Result<V::Item, FullscreenError>
+where
+    V: Iterator,
+{
+    let monitor = monitors
+        .nth(monitor_index)
+        .ok_or(FullscreenError::NoMonitor)?;
+    video_modes(monitor)
+        .nth(mode_index)
+        .ok_or(FullscreenError::NoVideoMode)
+}
+
+/// Returns the size closest to size that has the aspect ratio (width, height)
+/// Keeps either the width or the height, whichever needs the smallest change
+fn fit_aspect_ratio(size: (u32, u32), ratio: (u32, u32)) -> (u32, u32) {
+    let (width, height) = size;
+    let (ratio_width, ratio_height) = ratio;
+    if ratio_width == 0 || ratio_height == 0 {
+        return size;
+    }
+
+    let height_from_width =
+        ((width as f64 * ratio_height as f64 / ratio_width as f64).round() as u32).max(1);
+    let width_from_height =
+        ((height as f64 * ratio_width as f64 / ratio_height as f64).round() as u32).max(1);
+
+    if height_from_width.abs_diff(height) <= width_from_height.abs_diff(width) {
+        (width, height_from_width)
+    } else {
+        (width_from_height, height)
+    }
+}
+
+/// Returns true for events caused by the user interacting with the window
+fn is_input(event: &WindowEvent) -> bool {
+    matches!(
+        event,
+        WindowEvent::KeyboardInput { .. }
+            | WindowEvent::ReceivedCharacter(_)
+            | WindowEvent::CursorMoved { .. }
+            | WindowEvent::MouseInput { .. }
+            | WindowEvent::MouseWheel { .. }
+            | WindowEvent::Touch(_)
+    )
+}
+
+pub(crate) fn new_window(
+    config: &Config,
+) -> (winit::window::Window, winit::event_loop::EventLoop<()>) {
     let event_loop = EventLoop::new();
 
-    let window = WindowBuilder::new()
+    let window = window_builder(config)
         .build(&event_loop)
         .expect("could not build window");
 
     (window, event_loop)
 }
 
+/// Window with the attributes set from config
+fn window_builder(config: &Config) -> WindowBuilder {
+    WindowBuilder::new()
+        .with_transparent(config.transparent)
+        .with_maximized(config.maximized)
+        .with_visible(config.visible)
+}
+
 pub(crate) async fn run_window<C: Callbacks + 'static>(
     event_loop: EventLoop<()>,
     mut app: App<C>,
     mut ctx: Context,
 ) {
-    event_loop.run(move |event, _, control_flow| match event {
-        Event::WindowEvent {
-            ref event,
-            window_id,
-        } => {
-            if window_id == ctx.render.window.id() {
+    event_loop.run(move |mut event, _, control_flow| {
+        handle_event(&mut ctx, &mut app, &mut event, control_flow)
+    });
+}
+
+/// Handles one event of the event loop, calling the callbacks it concerns
+fn handle_event<C: Callbacks + 'static>(
+    ctx: &mut Context,
+    app: &mut App<C>,
+    event: &mut Event<()>,
+    control_flow: &mut ControlFlow,
+) {
+    match event {
+        Event::WindowEvent { event, window_id } => {
+            if ctx.render.is_window(*window_id) {
+                let consumed = app.callbacks.on_event(ctx, event);
+
+                // Surface must always follow the window size
                 match event {
-                    WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
                     WindowEvent::Resized(physical_size) => {
+                        ctx.window.presented_hash = None;
                         ctx.render.resize_window(*physical_size);
+                        if let Some(locked) = ctx.window.locked_size(*physical_size) {
+                            if let Some(window) = &ctx.render.window {
+                                window.set_inner_size(locked);
+                            }
+                        }
                     }
                     WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                        ctx.window.presented_hash = None;
+                        // Winit resizes the window to new_inner_size after this event
+                        if let Some(locked) = ctx.window.locked_size(**new_inner_size) {
+                            **new_inner_size = locked;
+                        }
                         ctx.render.resize_window(**new_inner_size);
                     }
-                    WindowEvent::CursorMoved { position, .. } => {
-                        ctx.input.mouse.set_pos(position.x, position.y);
-                    }
-                    WindowEvent::CursorLeft { .. } => ctx.input.mouse.set_on_screen(false),
-                    WindowEvent::CursorEntered { .. } => ctx.input.mouse.set_on_screen(true),
-                    WindowEvent::MouseInput { state, button, .. } => match state {
-                        ElementState::Pressed => ctx.input.mouse.press_button(*button),
-                        ElementState::Released => ctx.input.mouse.release_button(*button),
-                    },
-                    WindowEvent::MouseWheel { delta, .. } => {
-                        let (x, y) = match delta {
-                            winit::event::MouseScrollDelta::LineDelta(x, y) => {
-                                (*x as f64, *y as f64)
-                            }
-                            winit::event::MouseScrollDelta::PixelDelta(pos) => (pos.x, pos.y),
-                        };
-                        ctx.input.mouse.set_scroll_delta((x, y));
+                    _ => {}
+                }
+
+                if consumed {
+                    app.notify_canvas_resize(ctx);
+                    return;
+                }
+
+                match &*event {
+                    WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                    WindowEvent::DroppedFile(path) => {
+                        ctx.input.process_event(event);
+                        app.callbacks.on_file_dropped(ctx, path.clone());
                     }
-                    WindowEvent::ModifiersChanged(modifiers) => {
-                        ctx.input.keyboard.modifiers_changed(*modifiers)
+                    WindowEvent::Focused(focused) => {
+                        if ctx.window.set_focused(*focused) {
+                            app.callbacks.on_focus_changed(ctx, *focused);
+                        }
                     }
-                    WindowEvent::KeyboardInput { input, .. } => {
-                        if let Some(keycode) = input.virtual_keycode {
-                            match input.state {
-                                ElementState::Pressed => ctx.input.keyboard.set_key(keycode),
-                                ElementState::Released => ctx.input.keyboard.release_key(keycode),
-                            }
+                    _ => {
+                        if is_input(event) {
+                            ctx.time.last_input = Instant::now();
                         }
+                        ctx.input.process_event(event)
                     }
-                    _ => {}
                 }
+                app.notify_canvas_resize(ctx);
             }
         }
-        Event::DeviceEvent { ref event, .. } => match event {
+        Event::DeviceEvent { event, .. } => match event {
             DeviceEvent::MouseMotion { delta } => ctx.input.mouse.set_mouse_delta(*delta),
             _ => {}
         },
-        Event::RedrawRequested(window_id) if window_id == ctx.render.window.id() => {
-            let new_size = ctx.render.window.inner_size();
-            match ctx.render.render() {
-                Ok(_) => {}
-                Err(wgpu::SurfaceError::Lost) => ctx.render.resize_window(new_size),
+        Event::RedrawRequested(window_id)
+            if ctx.render.is_window(*window_id)
+                && ctx.window.should_render(ctx.render.software_canvas_only) =>
+        {
+            update_cursor_sprite(ctx);
+            let canvas = &ctx.render.canvas;
+            let secondary = ctx
+                .render
+                .layout
+                .secondary()
+                .map(|id| &ctx.render.offscreens[id.0]);
+            let cursor = ctx
+                .render
+                .cursor
+                .as_ref()
+                .and_then(|cursor| cursor.position);
+            // The laid out offscreen and a moved sprite cursor change the frame
+            let hash = ctx.window.frame_hash(|| {
+                let mut hasher = DefaultHasher::new();
+                canvas.content_hash().hash(&mut hasher);
+                secondary
+                    .map(|offscreen| offscreen.content_hash())
+                    .hash(&mut hasher);
+                cursor
+                    .map(|(x, y)| (x.to_bits(), y.to_bits()))
+                    .hash(&mut hasher);
+                hasher.finish()
+            });
+            if !ctx.window.should_present(hash) {
+                return;
+            }
+            let new_size = ctx.render.window_size();
+            match ctx.render.begin_frame() {
+                Ok(mut frame) => {
+                    app.callbacks.render(ctx, &mut frame.encoder, &frame.view);
+                    ctx.render.end_frame(frame);
+                    ctx.time.end_work();
+                    ctx.window.presented_hash = hash;
+                }
+                Err(wgpu::SurfaceError::Lost) => {
+                    ctx.window.presented_hash = None;
+                    ctx.render.resize_window(new_size);
+                }
                 Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
                 Err(e) => eprintln!("{:?}", e),
             }
         }
         Event::MainEventsCleared => {
-            if app.update(&mut ctx) {
+            ctx.time.begin_work();
+            let update_exit = ctx.window.should_update() && app.update(ctx);
+            ctx.time.end_work();
+            if ctx.window.should_exit(update_exit) {
                 *control_flow = ControlFlow::Exit;
             }
-            ctx.render.window.request_redraw();
+            if let (true, Some(window)) = (ctx.window.rendering_enabled, &ctx.render.window) {
+                window.request_redraw();
+            }
         }
+        Event::RedrawEventsCleared => {
+            if let Some(actual) = ctx.time.pace_frame() {
+                app.callbacks.on_frame_overrun(ctx, actual);
+            }
+            // Exit can not be undone
+            if *control_flow == ControlFlow::Exit {
+                return;
+            }
+            // Sleep until the next frame instead of polling, input wakes the loop early
+            *control_flow = match ctx
+                .time
+                .low_power_interval(ctx.window.focused, Instant::now())
+            {
+                Some(interval) => ControlFlow::WaitUntil(ctx.time.frame_start + interval),
+                None => ControlFlow::Poll,
+            };
+        }
+        Event::Suspended | Event::Resumed => match ctx.window.lifecycle(event) {
+            Some(SurfaceAction::Drop) => {
+                app.callbacks.on_suspend(ctx);
+                ctx.render.drop_surface();
+            }
+            Some(SurfaceAction::Recreate) => {
+                ctx.render.recreate_surface();
+                app.callbacks.on_resume(ctx);
+            }
+            None => {}
+        },
+        Event::LoopDestroyed => app.callbacks.on_exit(ctx),
         _ => {}
-    });
+    }
 }
 
 //
 // Commands
 //
 
+/// Returns true if the window has input focus
+///
+/// Windows start focused
+pub fn is_focused(ctx: &Context) -> bool {
+    ctx.window.focused
+}
+
+/// Exit the application at the end of the current frame
+///
+/// Same as returning true from ```Callbacks::update``` but callable from anywhere
+pub fn request_exit(ctx: &mut Context) {
+    ctx.window.exit_requested = true;
+}
+
+/// Keep the window at the aspect ratio (width, height) while resizing
+///
+/// After a resize the window is requested to the closest matching size, None unlocks
+///
+/// Some compositors (e.g. tiling window managers and Wayland) ignore the size request
+pub fn lock_aspect_ratio(ctx: &mut Context, ratio: Option<(u32, u32)>) {
+    ctx.window.aspect_ratio = ratio;
+    let size = ctx.render.window_size();
+    if let (Some(locked), Some(window)) = (ctx.window.locked_size(size), &ctx.render.window) {
+        window.set_inner_size(locked);
+    }
+}
+
 /// Enable/Disable vsync
 pub fn set_vsync(ctx: &mut Context, vsync: bool) {
     let present_mode = if vsync {
@@ -116,34 +514,873 @@ pub fn set_fullscreen(ctx: &mut Context, fullscreen: bool) {
     } else {
         None
     };
-    ctx.render.window.set_fullscreen(fullscreen_mode);
+    if let Some(window) = &ctx.render.window {
+        window.set_fullscreen(fullscreen_mode);
+    }
+}
+
+/// Returns all monitors connected to the system
+///
+/// Empty if the platform does not report any monitors
+pub fn monitors(ctx: &Context) -> Vec<MonitorInfo> {
+    ctx.render
+        .window
+        .iter()
+        .flat_map(Window::available_monitors)
+        .map(MonitorInfo::from)
+        .collect()
+}
+
+/// Enter exclusive fullscreen using a video mode from ```monitors```
+///
+/// Changes the monitor resolution, lower latency than ```set_fullscreen``` on some platforms
+///
+/// Leave with ```set_fullscreen(ctx, false)```
+pub fn set_fullscreen_exclusive(
+    ctx: &mut Context,
+    monitor_index: usize,
+    mode_index: usize,
+) -> Result<(), FullscreenError> {
+    let mode = select_video_mode(
+        ctx.render
+            .window
+            .iter()
+            .flat_map(Window::available_monitors),
+        monitor_index,
+        mode_index,
+        |monitor| monitor.video_modes(),
+    )?;
+    if let Some(window) = &ctx.render.window {
+        window.set_fullscreen(Some(winit::window::Fullscreen::Exclusive(mode)));
+    }
+    Ok(())
+}
+
+/// Maximize or restore the window
+pub fn set_maximized(ctx: &mut Context, maximized: bool) {
+    if let Some(window) = &ctx.render.window {
+        window.set_maximized(maximized);
+    }
+}
+
+/// Minimize or restore the window
+///
+/// Not supported on all platforms (e.g. Wayland cannot restore)
+pub fn set_minimized(ctx: &mut Context, minimized: bool) {
+    if let Some(window) = &ctx.render.window {
+        window.set_minimized(minimized);
+    }
+}
+
+/// Show or hide the window
+///
+/// Combine with ```Config::visible``` to avoid showing the window before it is ready
+pub fn set_visible(ctx: &mut Context, visible: bool) {
+    if let Some(window) = &ctx.render.window {
+        window.set_visible(visible);
+    }
+}
+
+/// Skip presenting frames whose canvas is identical to the last presented frame
+///
+/// Saves power in mostly static applications, the canvas is hashed every frame to compare
+///
+/// Changes not stored in the canvas, such as ```render::set_pan``` or ```Callbacks::render```, are not detected
+pub fn set_skip_identical_frames(ctx: &mut Context, skip: bool) {
+    ctx.window.skip_identical_frames = skip;
+    ctx.window.presented_hash = None;
 }
 
 /// Enable/Disable window resizing
 pub fn set_resizeable(ctx: &mut Context, resizable: bool) {
-    ctx.render.window.set_resizable(resizable);
+    if let Some(window) = &ctx.render.window {
+        window.set_resizable(resizable);
+    }
 }
 
 /// Set the inner size of the window
 pub fn set_size(ctx: &mut Context, size: (u32, u32)) {
-    ctx.render
-        .window
-        .set_inner_size(winit::dpi::PhysicalSize::new(size.0, size.1));
+    if let Some(window) = &ctx.render.window {
+        window.set_inner_size(winit::dpi::PhysicalSize::new(size.0, size.1));
+    }
 }
 
 /// Enable/Disable the cursor
 ///
 /// If disabled: Turns off cursor graphics and locks cursor to middle of window
 pub fn set_cursor_enabled(ctx: &mut Context, enabled: bool) {
+    ctx.window.cursor_enabled = enabled;
+    let window = match &ctx.render.window {
+        Some(window) => window,
+        None => return,
+    };
     // TODO handle error
-    ctx.render.window.set_cursor_visible(enabled);
+    // A sprite cursor keeps the OS cursor hidden
+    window.set_cursor_visible(enabled && ctx.render.cursor.is_none());
     let grab_mode = if enabled {
         winit::window::CursorGrabMode::None
     } else {
         winit::window::CursorGrabMode::Locked
     };
-    ctx.render
-        .window
+    window
         .set_cursor_grab(grab_mode)
         .expect("could not set cursor grab mode");
 }
+
+/// Use a standard system cursor over the window
+///
+/// Replaces a cursor set with ```set_cursor_sprite```
+pub fn set_cursor_icon(ctx: &mut Context, icon: CursorIcon) {
+    ctx.render.cursor = None;
+    if let Some(window) = &ctx.render.window {
+        window.set_cursor_icon(icon);
+        window.set_cursor_visible(ctx.window.cursor_enabled);
+    }
+}
+
+/// Use sprite as the cursor over the window, (hotspot_x, hotspot_y) is the pixel that clicks
+///
+/// The OS cursor is hidden and the sprite is drawn over each presented frame at the cursor, one sprite pixel per window pixel
+///
+/// Not part of screenshots or ```media::read_surface```
+pub fn set_cursor_sprite(
+    ctx: &mut Context,
+    sprite: &Sprite,
+    hotspot_x: u32,
+    hotspot_y: u32,
+) -> Result<(), CursorError> {
+    check_cursor(sprite.width, sprite.height, hotspot_x, hotspot_y)?;
+    ctx.render.set_cursor_sprite(
+        (sprite.width, sprite.height),
+        &sprite.pixels,
+        (hotspot_x, hotspot_y),
+    );
+    if let Some(window) = &ctx.render.window {
+        window.set_cursor_visible(false);
+    }
+    Ok(())
+}
+
+/// Moves the sprite cursor to the mouse, hidden while the mouse is outside the window or the cursor is disabled
+fn update_cursor_sprite(ctx: &mut Context) {
+    let position = (ctx.window.cursor_enabled && input::mouse_on_screen(ctx))
+        .then(|| input::mouse_pos_physical(ctx));
+    if let Some(cursor) = &mut ctx.render.cursor {
+        cursor.position = position;
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_cursor, fit_aspect_ratio, handle_event, is_focused, lock_aspect_ratio,
+        millihertz_to_hertz, request_exit, select_video_mode, set_cursor_enabled, set_cursor_icon,
+        set_cursor_sprite, set_skip_identical_frames, window_builder, CursorError, FullscreenError,
+        SurfaceAction, WindowContext,
+    };
+    use crate::{
+        app::{headless_canvas, headless_context, App, Callbacks},
+        canvas::{self, Sprite},
+        input, media, render, time, Config, Context,
+    };
+    use std::{cell::RefCell, path::PathBuf, rc::Rc, time::Duration};
+    use winit::{
+        dpi::{PhysicalPosition, PhysicalSize},
+        event::{DeviceId, Event, WindowEvent},
+        event_loop::ControlFlow,
+        window::{CursorIcon, WindowBuilder, WindowId},
+    };
+
+    /// Records the callbacks reached by events
+    #[derive(Default)]
+    struct Recorder {
+        calls: Rc<RefCell<Vec<String>>>,
+        consume_events: bool,
+        exit_in_update: bool,
+        update_time: Duration,
+        resize_to: Option<(u32, u32)>,
+    }
+
+    impl Recorder {
+        fn record(&self, call: String) {
+            self.calls.borrow_mut().push(call);
+        }
+    }
+
+    impl Callbacks for Recorder {
+        fn update(&mut self, ctx: &mut Context, _dt: f32) -> bool {
+            self.record("update".to_string());
+            std::thread::sleep(self.update_time);
+            if let Some((width, height)) = self.resize_to.take() {
+                canvas::resize(ctx, width, height);
+            }
+            if self.exit_in_update {
+                request_exit(ctx);
+            }
+            false
+        }
+
+        fn on_event(&mut self, _ctx: &mut Context, event: &WindowEvent) -> bool {
+            self.record(format!("event {:?}", event));
+            self.consume_events
+        }
+
+        fn render(
+            &mut self,
+            _ctx: &mut Context,
+            _encoder: &mut wgpu::CommandEncoder,
+            _view: &wgpu::TextureView,
+        ) {
+            self.record("render".to_string());
+        }
+
+        fn on_file_dropped(&mut self, ctx: &mut Context, path: PathBuf) {
+            self.record(format!("dropped {}", path.display()));
+            if let Some((width, height)) = self.resize_to.take() {
+                canvas::resize(ctx, width, height);
+            }
+        }
+
+        fn on_canvas_resize(&mut self, _ctx: &mut Context, width: u32, height: u32) {
+            self.record(format!("resize {}x{}", width, height));
+        }
+
+        fn on_focus_changed(&mut self, _ctx: &mut Context, focused: bool) {
+            self.record(format!("focused {}", focused));
+        }
+
+        fn on_frame_overrun(&mut self, _ctx: &mut Context, _actual: f32) {
+            self.record("overrun".to_string());
+        }
+
+        fn on_suspend(&mut self, ctx: &mut Context) {
+            self.record(format!("suspend {}", ctx.render.presented.is_some()));
+        }
+
+        fn on_resume(&mut self, ctx: &mut Context) {
+            self.record(format!("resume {}", ctx.render.presented.is_some()));
+        }
+
+        fn on_exit(&mut self, _ctx: &mut Context) {
+            self.record("exit".to_string());
+        }
+    }
+
+    /// App recording its callbacks on a headless context
+    /// None on machines without any adapter
+    fn recording_app(recorder: Recorder) -> Option<(Context, App<Recorder>)> {
+        Some((
+            headless_canvas((4, 4), (4, 4))?,
+            App {
+                callbacks: recorder,
+            },
+        ))
+    }
+
+    /// Events of one iteration of the event loop
+    fn frame() -> [Event<'static, ()>; 3] {
+        [
+            Event::MainEventsCleared,
+            Event::RedrawRequested(unsafe { WindowId::dummy() }),
+            Event::RedrawEventsCleared,
+        ]
+    }
+
+    /// Event sent to the window
+    fn window_event(event: WindowEvent) -> Event<()> {
+        Event::WindowEvent {
+            // Headless contexts accept events of any window
+            window_id: unsafe { WindowId::dummy() },
+            event,
+        }
+    }
+
+    /// Handles events in order and returns the resulting control flow
+    fn dispatch<C: Callbacks + 'static>(
+        ctx: &mut Context,
+        app: &mut App<C>,
+        events: &[Event<'static, ()>],
+    ) -> ControlFlow {
+        let mut control_flow = ControlFlow::Poll;
+        for event in events {
+            handle_event(ctx, app, &mut event.clone(), &mut control_flow);
+        }
+        control_flow
+    }
+
+    #[test]
+    fn test_focus_changes() {
+        let mut window = WindowContext::default();
+        assert!(window.focused);
+
+        assert!(window.set_focused(false));
+        assert!(!window.focused);
+        assert!(!window.set_focused(false));
+        assert!(window.set_focused(true));
+        assert!(window.focused);
+    }
+
+    #[test]
+    fn test_request_exit() {
+        let mut window = WindowContext::default();
+        assert!(!window.should_exit(false));
+        assert!(window.should_exit(true));
+
+        window.exit_requested = true;
+
+        assert!(window.should_exit(false));
+    }
+
+    #[test]
+    fn test_fit_aspect_ratio() {
+        assert_eq!(fit_aspect_ratio((800, 600), (4, 3)), (800, 600));
+        assert_eq!(fit_aspect_ratio((800, 610), (4, 3)), (800, 600));
+        assert_eq!(fit_aspect_ratio((1000, 600), (4, 3)), (1000, 750));
+        assert_eq!(fit_aspect_ratio((500, 400), (2, 1)), (500, 250));
+        assert_eq!(fit_aspect_ratio((300, 400), (2, 1)), (300, 150));
+    }
+
+    #[test]
+    fn test_locked_size() {
+        let mut window = WindowContext::default();
+        let size = PhysicalSize::new(900, 600);
+        assert_eq!(window.locked_size(size), None);
+
+        window.aspect_ratio = Some((1, 1));
+
+        assert_eq!(window.locked_size(size), Some(PhysicalSize::new(900, 900)));
+        assert_eq!(window.locked_size(PhysicalSize::new(600, 600)), None);
+    }
+
+    #[test]
+    fn test_scale_factor_change_keeps_aspect_ratio() {
+        // Skip on machines without any adapter
+        let Some((mut ctx, mut app)) = recording_app(Recorder::default()) else {
+            return;
+        };
+        lock_aspect_ratio(&mut ctx, Some((1, 1)));
+        let mut new_inner_size = PhysicalSize::new(8, 4);
+        let mut event = window_event(WindowEvent::ScaleFactorChanged {
+            scale_factor: 2.0,
+            new_inner_size: &mut new_inner_size,
+        });
+
+        handle_event(&mut ctx, &mut app, &mut event, &mut ControlFlow::Poll);
+
+        assert_eq!(new_inner_size, PhysicalSize::new(8, 8));
+        assert_eq!(render::surface_size(&ctx), (8, 8));
+    }
+
+    #[test]
+    fn test_select_video_mode() {
+        let monitors = || vec![vec![(640, 480), (1920, 1080)], vec![]].into_iter();
+
+        assert_eq!(
+            select_video_mode(monitors(), 0, 1, |modes| modes.into_iter()),
+            Ok((1920, 1080))
+        );
+        assert_eq!(
+            select_video_mode(monitors(), 1, 0, |modes| modes.into_iter()),
+            Err(FullscreenError::NoVideoMode)
+        );
+        // Headless systems report no monitors
+        assert_eq!(
+            select_video_mode(std::iter::empty::<Vec<()>>(), 0, 0, |modes| modes
+                .into_iter()),
+            Err(FullscreenError::NoMonitor)
+        );
+        assert_eq!(millihertz_to_hertz(59_940), 59.94);
+    }
+
+    #[test]
+    fn test_window_builder_from_config() {
+        let config = Config {
+            maximized: true,
+            visible: false,
+            transparent: true,
+            ..Default::default()
+        };
+        // The attributes are private, compare against the expected builder instead
+        let expected = WindowBuilder::new()
+            .with_transparent(true)
+            .with_maximized(true)
+            .with_visible(false);
+        assert_eq!(
+            format!("{:?}", window_builder(&config)),
+            format!("{:?}", expected)
+        );
+
+        assert_eq!(
+            format!("{:?}", window_builder(&Config::default())),
+            format!("{:?}", WindowBuilder::new())
+        );
+    }
+
+    #[test]
+    fn test_rendering_disabled() {
+        let mut window = WindowContext::default();
+        assert!(window.should_render(false));
+        assert!(!window.should_render(true));
+
+        // Events and updates keep running while the last frame stays on screen
+        window.rendering_enabled = false;
+        assert!(!window.should_render(false));
+        assert!(window.should_update());
+        assert!(!window.should_exit(false));
+
+        window.update_while_disabled = false;
+        assert!(!window.should_update());
+
+        window.rendering_enabled = true;
+        assert!(window.should_render(false));
+        assert!(window.should_update());
+    }
+
+    #[test]
+    fn test_skip_identical_frames() {
+        let mut window = WindowContext::default();
+        assert_eq!(window.frame_hash(|| 1), None);
+        assert!(window.should_present(None));
+
+        window.skip_identical_frames = true;
+        assert_eq!(window.frame_hash(|| 1), Some(1));
+        assert!(window.should_present(Some(1)));
+        // Only a presented frame is skipped next time
+        assert!(window.should_present(Some(1)));
+        window.presented_hash = Some(1);
+        assert!(!window.should_present(Some(1)));
+        assert!(window.should_present(Some(2)));
+    }
+
+    #[test]
+    fn test_resume_recreates_surface() {
+        let mut window = WindowContext::default();
+        // Startup resume keeps the surface created in new
+        assert_eq!(window.lifecycle(&Event::<()>::Resumed), None);
+
+        window.presented_hash = Some(1);
+        assert_eq!(
+            window.lifecycle(&Event::<()>::Suspended),
+            Some(SurfaceAction::Drop)
+        );
+        assert!(!window.should_render(false));
+        assert_eq!(window.lifecycle(&Event::<()>::Suspended), None);
+
+        assert_eq!(
+            window.lifecycle(&Event::<()>::Resumed),
+            Some(SurfaceAction::Recreate)
+        );
+        assert!(window.should_render(false));
+        assert_eq!(window.presented_hash, None);
+        assert_eq!(window.lifecycle(&Event::<()>::Resumed), None);
+    }
+
+    #[test]
+    fn test_check_cursor() {
+        assert_eq!(check_cursor(16, 16, 0, 0), Ok(()));
+        assert_eq!(check_cursor(16, 8, 15, 7), Ok(()));
+        assert_eq!(check_cursor(0, 16, 0, 0), Err(CursorError::Empty));
+        assert_eq!(
+            check_cursor(16, 8, 4, 8),
+            Err(CursorError::HotspotOutside { x: 4, y: 8 })
+        );
+    }
+
+    #[test]
+    fn test_event_reaches_callback() {
+        let recorder = Recorder::default();
+        let calls = recorder.calls.clone();
+        // Skip on machines without any adapter
+        let Some((mut ctx, mut app)) = recording_app(recorder) else {
+            return;
+        };
+
+        dispatch(
+            &mut ctx,
+            &mut app,
+            &[window_event(WindowEvent::CloseRequested)],
+        );
+        assert_eq!(*calls.borrow(), ["event CloseRequested"]);
+
+        // Consumed events skip the internal handling
+        app.callbacks.consume_events = true;
+        let control_flow = dispatch(
+            &mut ctx,
+            &mut app,
+            &[window_event(WindowEvent::CloseRequested)],
+        );
+        assert_eq!(control_flow, ControlFlow::Poll);
+        app.callbacks.consume_events = false;
+        let control_flow = dispatch(
+            &mut ctx,
+            &mut app,
+            &[window_event(WindowEvent::CloseRequested)],
+        );
+        assert_eq!(control_flow, ControlFlow::Exit);
+    }
+
+    #[test]
+    fn test_dropped_file_reaches_callback() {
+        let recorder = Recorder::default();
+        let calls = recorder.calls.clone();
+        // Skip on machines without any adapter
+        let Some((mut ctx, mut app)) = recording_app(recorder) else {
+            return;
+        };
+        let path = PathBuf::from("sprite.png");
+
+        dispatch(
+            &mut ctx,
+            &mut app,
+            &[window_event(WindowEvent::DroppedFile(path.clone()))],
+        );
+
+        assert_eq!(
+            *calls.borrow(),
+            ["event DroppedFile(\"sprite.png\")", "dropped sprite.png"]
+        );
+        assert_eq!(input::dropped_files(&ctx), [path]);
+    }
+
+    #[test]
+    fn test_focus_event_reaches_callback() {
+        let recorder = Recorder::default();
+        let calls = recorder.calls.clone();
+        // Skip on machines without any adapter
+        let Some((mut ctx, mut app)) = recording_app(recorder) else {
+            return;
+        };
+        assert!(is_focused(&ctx));
+
+        // Repeated focus events only fire the callback on change
+        dispatch(
+            &mut ctx,
+            &mut app,
+            &[
+                window_event(WindowEvent::Focused(false)),
+                window_event(WindowEvent::Focused(false)),
+            ],
+        );
+
+        assert!(!is_focused(&ctx));
+        assert_eq!(
+            *calls.borrow(),
+            [
+                "event Focused(false)",
+                "focused false",
+                "event Focused(false)"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_boxed_callbacks_run_frames() {
+        let recorder = Recorder::default();
+        let calls = recorder.calls.clone();
+        // Skip on machines without any adapter
+        let Some(mut ctx) = headless_canvas((4, 4), (4, 4)) else {
+            return;
+        };
+        let callbacks: Box<dyn Callbacks> = Box::new(recorder);
+        let mut app = App { callbacks };
+
+        for _ in 0..2 {
+            dispatch(&mut ctx, &mut app, &frame());
+        }
+
+        assert_eq!(*calls.borrow(), ["update", "render", "update", "render"]);
+    }
+
+    #[test]
+    fn test_request_exit_stops_loop() {
+        let recorder = Recorder {
+            exit_in_update: true,
+            ..Default::default()
+        };
+        let calls = recorder.calls.clone();
+        // Skip on machines without any adapter
+        let Some((mut ctx, mut app)) = recording_app(recorder) else {
+            return;
+        };
+
+        // The requested frame still renders before the loop exits
+        let control_flow = dispatch(&mut ctx, &mut app, &frame());
+        assert_eq!(control_flow, ControlFlow::Exit);
+        dispatch(&mut ctx, &mut app, &[Event::LoopDestroyed]);
+
+        assert_eq!(*calls.borrow(), ["update", "render", "exit"]);
+    }
+
+    #[test]
+    fn test_rendering_disabled_skips_render() {
+        let recorder = Recorder::default();
+        let calls = recorder.calls.clone();
+        // Skip on machines without any adapter
+        let Some((mut ctx, mut app)) = recording_app(recorder) else {
+            return;
+        };
+
+        render::set_rendering_enabled(&mut ctx, false);
+        dispatch(&mut ctx, &mut app, &frame());
+        dispatch(
+            &mut ctx,
+            &mut app,
+            &[window_event(WindowEvent::Focused(false))],
+        );
+        assert_eq!(
+            *calls.borrow(),
+            ["update", "event Focused(false)", "focused false"]
+        );
+        calls.borrow_mut().clear();
+
+        render::set_update_while_disabled(&mut ctx, false);
+        dispatch(&mut ctx, &mut app, &frame());
+        assert!(calls.borrow().is_empty());
+
+        render::set_rendering_enabled(&mut ctx, true);
+        dispatch(&mut ctx, &mut app, &frame());
+        assert_eq!(*calls.borrow(), ["update", "render"]);
+    }
+
+    #[test]
+    fn test_software_canvas_only_skips_surface() {
+        let recorder = Recorder::default();
+        let calls = recorder.calls.clone();
+        let config = Config {
+            canvas_width: 4,
+            canvas_height: 4,
+            software_canvas_only: true,
+            ..Default::default()
+        };
+        // Skip on machines without any adapter
+        let Some(mut ctx) = headless_context(&config, 4, 4) else {
+            return;
+        };
+        let mut app = App {
+            callbacks: recorder,
+        };
+
+        canvas::write_pixel_rgb(&mut ctx, 1, 2, &[255, 0, 0]);
+        dispatch(&mut ctx, &mut app, &frame());
+        render::present(&mut ctx).unwrap();
+        assert_eq!(*calls.borrow(), ["update"]);
+
+        // Nothing reaches the surface
+        let presented = media::read_surface(&ctx).unwrap();
+        assert!(presented.pixels.iter().all(|byte| *byte == 0));
+
+        // The CPU canvas still works
+        assert_eq!(ctx.render.canvas.pixel_rgba(1, 2), [255, 0, 0, 255]);
+        let path = std::env::temp_dir().join("pixelated_test_software_canvas.png");
+        media::export_screenshot(&ctx, path.to_str().unwrap()).unwrap();
+        let matches = media::matches_golden(&ctx, &path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches);
+    }
+
+    #[test]
+    fn test_resume_event_reconfigures_surface() {
+        let recorder = Recorder::default();
+        let calls = recorder.calls.clone();
+        // Skip on machines without any adapter
+        let Some((mut ctx, mut app)) = recording_app(recorder) else {
+            return;
+        };
+
+        // Startup resume keeps the surface
+        dispatch(&mut ctx, &mut app, &[Event::Resumed]);
+        assert!(calls.borrow().is_empty());
+
+        // Surface resources are dropped after the callback and nothing renders
+        dispatch(&mut ctx, &mut app, &[Event::Suspended]);
+        assert!(ctx.render.presented.is_none());
+        dispatch(&mut ctx, &mut app, &frame());
+        assert_eq!(*calls.borrow(), ["suspend true", "update"]);
+        calls.borrow_mut().clear();
+
+        // Recreated at the surface size before the callback
+        dispatch(&mut ctx, &mut app, &[Event::Resumed]);
+        let presented = ctx.render.presented.as_ref().unwrap().texture.size();
+        assert_eq!(
+            (presented.width, presented.height),
+            render::surface_size(&ctx)
+        );
+        dispatch(&mut ctx, &mut app, &frame());
+        assert_eq!(*calls.borrow(), ["resume true", "update", "render"]);
+    }
+
+    #[test]
+    fn test_failed_frame_is_presented_later() {
+        let recorder = Recorder::default();
+        let calls = recorder.calls.clone();
+        // Skip on machines without any adapter
+        let Some((mut ctx, mut app)) = recording_app(recorder) else {
+            return;
+        };
+        set_skip_identical_frames(&mut ctx, true);
+        let renders = || {
+            calls
+                .borrow()
+                .iter()
+                .filter(|call| *call == "render")
+                .count()
+        };
+
+        dispatch(&mut ctx, &mut app, &frame());
+        dispatch(&mut ctx, &mut app, &frame());
+        assert_eq!(renders(), 1);
+
+        // A changed frame that fails to begin is presented once the surface is back
+        ctx.render.canvas.write_pixel(0, 0, &[255, 0, 0]);
+        ctx.render.presented = None;
+        dispatch(&mut ctx, &mut app, &frame());
+        assert_eq!(renders(), 1);
+        ctx.render.recreate_surface();
+        dispatch(&mut ctx, &mut app, &frame());
+        dispatch(&mut ctx, &mut app, &frame());
+        assert_eq!(renders(), 2);
+    }
+
+    #[test]
+    fn test_slow_frame_calls_overrun() {
+        let recorder = Recorder {
+            update_time: Duration::from_millis(20),
+            ..Default::default()
+        };
+        let calls = recorder.calls.clone();
+        // Skip on machines without any adapter
+        let Some((mut ctx, mut app)) = recording_app(recorder) else {
+            return;
+        };
+        time::set_frame_budget(&mut ctx, Some(0.01));
+
+        dispatch(&mut ctx, &mut app, &frame());
+        assert_eq!(*calls.borrow(), ["update", "render", "overrun"]);
+        calls.borrow_mut().clear();
+
+        // Time between presenting and the end of the frame is not work
+        app.callbacks.update_time = Duration::ZERO;
+        let [main, redraw, redraw_cleared] = frame();
+        dispatch(&mut ctx, &mut app, &[main, redraw]);
+        std::thread::sleep(Duration::from_millis(20));
+        dispatch(&mut ctx, &mut app, &[redraw_cleared]);
+        assert_eq!(*calls.borrow(), ["update", "render"]);
+    }
+
+    #[test]
+    fn test_canvas_resize_reaches_callback() {
+        let recorder = Recorder {
+            resize_to: Some((8, 6)),
+            ..Default::default()
+        };
+        let calls = recorder.calls.clone();
+        // Skip on machines without any adapter
+        let Some((mut ctx, mut app)) = recording_app(recorder) else {
+            return;
+        };
+
+        dispatch(&mut ctx, &mut app, &frame());
+        assert_eq!(*calls.borrow(), ["update", "resize 8x6", "render"]);
+        calls.borrow_mut().clear();
+
+        // Resizing from an event callback is reported before the next update
+        app.callbacks.resize_to = Some((3, 2));
+        dispatch(
+            &mut ctx,
+            &mut app,
+            &[window_event(WindowEvent::DroppedFile(PathBuf::from(
+                "a.png",
+            )))],
+        );
+        assert_eq!(
+            *calls.borrow(),
+            [
+                "event DroppedFile(\"a.png\")",
+                "dropped a.png",
+                "resize 3x2"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_presentation_changes_are_not_skipped() {
+        let recorder = Recorder::default();
+        let calls = recorder.calls.clone();
+        // Skip on machines without any adapter
+        let Some((mut ctx, mut app)) = recording_app(recorder) else {
+            return;
+        };
+        set_skip_identical_frames(&mut ctx, true);
+        let id = canvas::create_offscreen(&mut ctx, 2, 2);
+        dispatch(&mut ctx, &mut app, &frame());
+
+        let changes: [&dyn Fn(&mut Context); 7] = [
+            &|ctx| render::set_layout(ctx, render::Layout::SideBySide(id)),
+            &|ctx| canvas::write_pixel_on(ctx, id, 0, 0, &[255, 0, 0, 255]),
+            &|ctx| render::set_pan(ctx, 0.5, 0.0),
+            &|ctx| render::set_subpixel_offset(ctx, 0.5, 0.0),
+            &|ctx| render::set_pixel_aspect(ctx, 2.0),
+            &|ctx| render::set_upscale_filter(ctx, render::UpscaleFilter::Linear),
+            &|ctx| render::set_address_mode(ctx, render::AddressMode::Repeat),
+        ];
+        for change in changes {
+            // The unchanged frame is skipped, the changed one is presented
+            calls.borrow_mut().clear();
+            dispatch(&mut ctx, &mut app, &frame());
+            change(&mut ctx);
+            dispatch(&mut ctx, &mut app, &frame());
+            assert_eq!(*calls.borrow(), ["update", "update", "render"]);
+        }
+    }
+
+    #[test]
+    fn test_cursor_sprite_follows_mouse() {
+        let recorder = Recorder::default();
+        let calls = recorder.calls.clone();
+        // Skip on machines without any adapter
+        let Some((mut ctx, mut app)) = recording_app(recorder) else {
+            return;
+        };
+        set_skip_identical_frames(&mut ctx, true);
+        let sprite = Sprite::new(2, 2, vec![255; 2 * 2 * 4]);
+        assert_eq!(set_cursor_sprite(&mut ctx, &sprite, 1, 1), Ok(()));
+        assert_eq!(
+            set_cursor_sprite(&mut ctx, &sprite, 2, 0),
+            Err(CursorError::HotspotOutside { x: 2, y: 0 })
+        );
+        #[allow(deprecated)]
+        let moved = |x, y| {
+            window_event(WindowEvent::CursorMoved {
+                device_id: unsafe { DeviceId::dummy() },
+                position: PhysicalPosition::new(x, y),
+                modifiers: Default::default(),
+            })
+        };
+        let entered = window_event(WindowEvent::CursorEntered {
+            device_id: unsafe { DeviceId::dummy() },
+        });
+        let cursor_position = |ctx: &Context| ctx.render.cursor.as_ref().unwrap().position;
+
+        dispatch(&mut ctx, &mut app, &[entered, moved(1.0, 2.0)]);
+        dispatch(&mut ctx, &mut app, &frame());
+        assert_eq!(cursor_position(&ctx), Some((1.0, 2.0)));
+        calls.borrow_mut().clear();
+
+        // Only a moved cursor changes an identical canvas
+        dispatch(&mut ctx, &mut app, &frame());
+        dispatch(&mut ctx, &mut app, &[moved(3.0, 2.0)]);
+        dispatch(&mut ctx, &mut app, &frame());
+        let renders = calls.borrow().iter().filter(|c| *c == "render").count();
+        assert_eq!(renders, 1);
+        assert_eq!(cursor_position(&ctx), Some((3.0, 2.0)));
+
+        // Hidden with the cursor
+        set_cursor_enabled(&mut ctx, false);
+        dispatch(&mut ctx, &mut app, &frame());
+        assert_eq!(cursor_position(&ctx), None);
+
+        set_cursor_icon(&mut ctx, CursorIcon::Hand);
+        assert!(ctx.render.cursor.is_none());
+    }
+}